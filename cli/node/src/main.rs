@@ -1,14 +1,140 @@
+use clap::{Parser, Subcommand};
+use ethereum_types::H160;
 use intmax_config::{Config, ConfigKind};
-use intmax_runner::gen_runner;
+use intmax_runner::{
+    export_snapshot, export_state_diffs, force_exit_calldata, gen_runner, import_snapshot,
+    import_state_diffs,
+};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[clap(author, version, about)]
+struct Cli {
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Prints the `Rollup.forceExit` calldata exiting a balance without the operator.
+    /// Run it while the node is stopped.
+    ForceExit {
+        /// Account whose balance is exited.
+        #[clap(long)]
+        account: H160,
+        /// Token to exit. The zero address is the L1 native token.
+        #[clap(long, default_value = "0x0000000000000000000000000000000000000000")]
+        token: H160,
+    },
+    /// Writes the state diffs of the sealed blocks to a file. Run it while the node is stopped.
+    DaExport {
+        /// First block to export.
+        #[clap(long, default_value = "1")]
+        from: u64,
+        /// Last block to export, the latest one if omitted.
+        #[clap(long)]
+        to: Option<u64>,
+        #[clap(long)]
+        path: PathBuf,
+    },
+    /// Reads state diffs written by `da-export` into the database, checking every block against
+    /// the diff root committed on L1. Run it while the node is stopped.
+    DaImport {
+        #[clap(long)]
+        path: PathBuf,
+    },
+    /// Snapshots of the state at the latest block, to bootstrap a new node. Run it while the
+    /// node is stopped.
+    Snapshot {
+        #[clap(subcommand)]
+        command: SnapshotCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum SnapshotCommand {
+    /// Writes a snapshot of the state at the latest block to a file.
+    Export {
+        #[clap(long)]
+        path: PathBuf,
+    },
+    /// Bootstraps an empty database from a snapshot written by `snapshot export`, checking the
+    /// rebuilt state root against the snapshot.
+    Import {
+        #[clap(long)]
+        path: PathBuf,
+    },
+}
 
 async fn run() {
     let config = Config::new(ConfigKind::DEV).expect("setup config file error.");
+    eth_provider::validate_l1_network(&config.eth_server)
+        .await
+        .expect("L1 network validation error.");
     let runner = gen_runner(&config);
     runner.run().await;
 }
 
+async fn force_exit(account: H160, token: H160) {
+    let config = Config::new(ConfigKind::DEV).expect("setup config file error.");
+    let calldata = force_exit_calldata(&config, account, token)
+        .await
+        .expect("force exit error.");
+
+    let hex: String = calldata.iter().map(|b| format!("{:02x}", b)).collect();
+    println!("0x{}", hex);
+}
+
+fn da_export(from: u64, to: Option<u64>, path: PathBuf) {
+    let config = Config::new(ConfigKind::DEV).expect("setup config file error.");
+    let data = export_state_diffs(&config, from, to);
+    std::fs::write(&path, data).expect("write export error.");
+}
+
+async fn da_import(path: PathBuf) {
+    let config = Config::new(ConfigKind::DEV).expect("setup config file error.");
+    let data = std::fs::read(&path).expect("read export error.");
+    let imported = import_state_diffs(&config, &data)
+        .await
+        .expect("import error.");
+    println!("imported the state diffs of {} blocks", imported);
+}
+
+fn snapshot_export(path: PathBuf) {
+    let config = Config::new(ConfigKind::DEV).expect("setup config file error.");
+    let file = File::create(&path).expect("create snapshot error.");
+    let block = export_snapshot(&config, BufWriter::new(file)).expect("snapshot export error.");
+    println!(
+        "exported the state at block {} ({:?})",
+        block.number, block.hash
+    );
+}
+
+fn snapshot_import(path: PathBuf) {
+    let config = Config::new(ConfigKind::DEV).expect("setup config file error.");
+    let file = File::open(&path).expect("open snapshot error.");
+    let block = import_snapshot(&config, BufReader::new(file)).expect("snapshot import error.");
+    println!(
+        "imported the state at block {} ({:?})",
+        block.number, block.hash
+    );
+}
+
 #[tokio::main]
 async fn main() {
-    println!("Hello, world!");
-    run().await;
+    match Cli::parse().command {
+        None => {
+            println!("Hello, world!");
+            run().await;
+        }
+        Some(Command::ForceExit { account, token }) => force_exit(account, token).await,
+        Some(Command::DaExport { from, to, path }) => da_export(from, to, path),
+        Some(Command::DaImport { path }) => da_import(path).await,
+        Some(Command::Snapshot { command }) => match command {
+            SnapshotCommand::Export { path } => snapshot_export(path),
+            SnapshotCommand::Import { path } => snapshot_import(path),
+        },
+    }
 }
@@ -1,8 +1,182 @@
+//! Prepares the sealed blocks for their commitment on L1: a block is committed by
+//! `Rollup.commitBatch` with its state root and the aggregated proof of its state updates.
+use std::sync::Arc;
+
+use db::Database;
+use ethereum_types::H256;
+use executor::Executor;
+use intmax_verkle::VerkleStorage;
+use intmax_zk::ProofSystem;
+use su_receiver::{Aggregator, Result};
+use tracing::info;
+
+/// What is posted to L1 for the block `number`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchCommitment {
+    pub number: u64,
+    pub block_hash: H256,
+    /// State root at the end of the block, `stateRoot` of `Rollup.commitBatch`. Recorded in the
+    /// block when it is sealed, so the state tree of the block may be pruned.
+    pub state_root: H256,
+    /// Root of the `intmax_zk::diff_tree` of the state diffs of the block, `txRoot` of
+    /// `Rollup.commitBatch`.
+    pub diff_root: H256,
+    /// Aggregated proof of the state diffs under `diff_root`.
+    pub proof: Vec<u8>,
+}
+
+pub struct Commiter<S: VerkleStorage, D: Database, P: ProofSystem> {
+    executor: Arc<Executor<S, D>>,
+    aggregator: Aggregator<D, P>,
+}
+
+impl<S: VerkleStorage, D: Database, P: ProofSystem> Commiter<S, D, P> {
+    pub fn new(executor: Arc<Executor<S, D>>, aggregator: Aggregator<D, P>) -> Self {
+        Commiter {
+            executor,
+            aggregator,
+        }
+    }
+
+    /// The commitment of the block `number`, aggregating its state updates on the first call.
+    /// `None` if the block is not sealed.
+    pub fn commitment(&self, number: u64) -> Result<Option<BatchCommitment>> {
+        let block = match self.executor.block(number) {
+            Some(block) => block,
+            None => return Ok(None),
+        };
+        let aggregated = self.aggregator.aggregate(&block)?;
+
+        Ok(Some(BatchCommitment {
+            number,
+            block_hash: block.hash,
+            state_root: block.state_root,
+            diff_root: aggregated.diff_root,
+            proof: aggregated.proof,
+        }))
+    }
+
+    /// Prepares the commitments of the blocks sealed since the last call, in order. Returns the
+    /// latest prepared block.
+    pub fn prepare_sealed_blocks(&self) -> Result<u64> {
+        let latest = self.executor.latest_block_number();
+        for number in self.aggregator.latest_aggregated_block() + 1..=latest {
+            if let Some(commitment) = self.commitment(number)? {
+                info!(
+                    "batch {} is ready with diff root {:?}",
+                    commitment.number, commitment.diff_root
+                );
+            }
+        }
+
+        Ok(latest)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use db::Db;
+    use ethereum_types::H160;
+    use executor::{ExecutorTrait, Transaction};
+    use intmax_verkle::VerkleTree;
+    use intmax_zk::{diff_tree, DevProofSystem, StateDiff};
+    use kvdb_memorydb::InMemory;
+    use su_receiver::{DaStore, SuReceiver};
+
+    #[test]
+    fn success_prepare_sealed_blocks() {
+        let db: Db<InMemory> = Db::new(kvdb_memorydb::create(db::columns::NUM_COLUMNS), 0);
+        let executor = Arc::new(Executor::new(
+            VerkleTree::new(db.with_column(db::columns::STATE)),
+            db.with_column(db::columns::EXECUTOR),
+        ));
+        let su_receiver = SuReceiver::new(
+            executor.clone(),
+            db.with_column(db::columns::SU_RECEIVER),
+            DevProofSystem,
+        );
+        let commiter = Commiter::new(
+            executor.clone(),
+            Aggregator::new(
+                db.with_column(db::columns::SU_RECEIVER),
+                DaStore::new(db.with_column(db::columns::DA)),
+                DevProofSystem,
+            ),
+        );
+        assert_eq!(commiter.commitment(1).unwrap(), None);
+
+        let diff = StateDiff {
+            user: H160::random(),
+            old_root: H256::zero(),
+            new_root: H256::random(),
+            nonce: 0,
+        };
+        su_receiver
+            .accept(&DevProofSystem.prove(&diff))
+            .expect("accept ok.");
+        let first = executor.seal_block(1).unwrap().unwrap();
+        executor
+            .execute(&Transaction::Deposit(executor::Deposit {
+                id: H256::random(),
+                recipient: H160::random(),
+                token: H160::zero(),
+                amount: 100.into(),
+                l1_block: 1,
+            }))
+            .unwrap();
+        executor.seal_block(2).unwrap();
+
+        assert_eq!(commiter.prepare_sealed_blocks().expect("prepare ok."), 2);
+        let commitment = commiter.commitment(1).unwrap().expect("sealed block");
+        assert_eq!(commitment.block_hash, first.hash);
+        assert_eq!(commitment.state_root, executor.state_root_at(1).unwrap());
+        assert_eq!(commitment.diff_root, diff_tree::root(&[diff.hash()]));
+        let commitment = commiter.commitment(2).unwrap().expect("sealed block");
+        assert_eq!(commitment.state_root, executor.state_root().unwrap());
+        assert_eq!(commitment.diff_root, H256::zero());
+    }
+
     #[test]
-    fn it_works() {
-        let result = 2 + 2;
-        assert_eq!(result, 4);
+    fn success_commitment_of_pruned_block() {
+        let db: Db<InMemory> = Db::new(kvdb_memorydb::create(db::columns::NUM_COLUMNS), 0);
+        let executor = Arc::new(
+            Executor::new(
+                VerkleTree::new(db.with_column(db::columns::STATE)),
+                db.with_column(db::columns::EXECUTOR),
+            )
+            .with_version_window(1),
+        );
+        let commiter = Commiter::new(
+            executor.clone(),
+            Aggregator::new(
+                db.with_column(db::columns::SU_RECEIVER),
+                DaStore::new(db.with_column(db::columns::DA)),
+                DevProofSystem,
+            ),
+        );
+        let mut roots = vec![];
+        for id in 1..4u64 {
+            executor
+                .execute(&Transaction::Deposit(executor::Deposit {
+                    id: H256::from_low_u64_be(id),
+                    recipient: H160::random(),
+                    token: H160::zero(),
+                    amount: 100.into(),
+                    l1_block: id,
+                }))
+                .unwrap();
+            executor.seal_block(id).unwrap();
+            roots.push(executor.state_root().unwrap());
+        }
+
+        // Block 1 is not committed on L1 yet when its state tree is pruned.
+        assert!(executor.prune_state(None).unwrap() > 0);
+        assert!(executor
+            .balance_at(&H160::zero(), &H160::zero(), 1)
+            .is_err());
+        let commitment = commiter.commitment(1).unwrap().expect("sealed block");
+        assert_eq!(commitment.state_root, roots[0]);
+        assert_eq!(commiter.prepare_sealed_blocks().expect("prepare ok."), 3);
     }
 }
@@ -0,0 +1,131 @@
+//! Transactions of every account, in the order they were sealed.
+//!
+//! The `i`-th transaction of an account is stored under its position, so that pages are
+//! stable while new transactions are appended. Deposits are indexed under their recipient,
+//! withdrawals under the withdrawing account and state updates under their user.
+use std::collections::HashMap;
+
+use db::{DBKey, Database};
+use ethereum_types::{H160, H256};
+use intmax_verkle::VerkleStorage;
+
+use crate::error::Result;
+use crate::{prefixed_key, Block, Executor, Transaction};
+
+const COUNT_PREFIX: &[u8] = b"count";
+const TX_PREFIX: &[u8] = b"tx";
+
+impl<S: VerkleStorage, D: Database> Executor<S, D> {
+    /// Number of sealed transactions of `account`.
+    pub fn account_transaction_count(&self, account: &H160) -> u64 {
+        self.account_index
+            .as_ref()
+            .and_then(|index| index.get(&prefixed_key(COUNT_PREFIX, account.as_bytes())))
+            .unwrap_or_default()
+    }
+
+    /// Hashes of at most `limit` sealed transactions of `account`, newest first, before the
+    /// position `cursor` or from the newest one if `None`. Also returns the cursor of the next
+    /// page, `None` if this is the last one.
+    pub fn account_transactions(
+        &self,
+        account: &H160,
+        cursor: Option<u64>,
+        limit: u64,
+    ) -> (Vec<H256>, Option<u64>) {
+        let index = match self.account_index.as_ref() {
+            Some(index) => index,
+            None => return (vec![], None),
+        };
+
+        let end = cursor
+            .unwrap_or(u64::MAX)
+            .min(self.account_transaction_count(account));
+        let start = end.saturating_sub(limit);
+        let hashes = (start..end)
+            .rev()
+            .filter_map(|position| index.get(&account_tx_key(account, position)))
+            .collect();
+
+        (hashes, Some(start).filter(|&start| start > 0))
+    }
+
+    /// Appends the transactions of `block` to the index of their accounts.
+    ///
+    /// Like the log index, it is written before the block is committed. If the node stops in
+    /// between, the block is sealed again and its transactions are indexed twice.
+    pub(crate) fn index_account_transactions(&self, block: &Block) -> Result<()> {
+        let index = match self.account_index.as_ref() {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+
+        let mut counts = HashMap::new();
+        let mut tx = index.make_tx();
+        for transaction in block.transactions.iter() {
+            let account = match transaction {
+                Transaction::Deposit(deposit) => deposit.recipient,
+                Transaction::Withdraw(withdrawal) => withdrawal.account,
+                Transaction::UpdateState(diff) => diff.user,
+            };
+            let count = counts
+                .entry(account)
+                .or_insert_with(|| self.account_transaction_count(&account));
+            tx.put(&account_tx_key(&account, *count), &transaction.hash());
+            *count += 1;
+        }
+        for (account, count) in counts {
+            tx.put(&prefixed_key(COUNT_PREFIX, account.as_bytes()), &count);
+        }
+        index.commit(tx)?;
+
+        Ok(())
+    }
+}
+
+fn account_tx_key(account: &H160, position: u64) -> DBKey {
+    let mut key = prefixed_key(TX_PREFIX, account.as_bytes());
+    key.extend_from_slice(&position.to_be_bytes());
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{deposit, executor, withdrawal};
+    use crate::ExecutorTrait;
+    use db::Db;
+    use kvdb_memorydb::InMemory;
+
+    #[test]
+    fn success_account_transactions() {
+        let db: Db<InMemory> = Db::new(kvdb_memorydb::create(db::columns::NUM_COLUMNS), 0);
+        let executor = executor().with_account_index(db.with_column(db::columns::ACCOUNT_TXS));
+        let alice = H160::random();
+
+        let first = Transaction::Deposit(deposit(1, alice, 100));
+        executor.execute(&first).unwrap();
+        executor
+            .execute(&Transaction::Deposit(deposit(2, H160::random(), 100)))
+            .unwrap();
+        let second = withdrawal(alice, 30, 0);
+        executor.execute(&second).unwrap();
+        executor.seal_block(1).unwrap();
+        let third = withdrawal(alice, 30, 1);
+        executor.execute(&third).unwrap();
+        assert_eq!(executor.account_transaction_count(&alice), 2);
+        executor.seal_block(2).unwrap();
+        assert_eq!(executor.account_transaction_count(&alice), 3);
+
+        let (page, cursor) = executor.account_transactions(&alice, None, 2);
+        assert_eq!(page, vec![third.hash(), second.hash()]);
+        assert_eq!(cursor, Some(1));
+        let (page, cursor) = executor.account_transactions(&alice, cursor, 2);
+        assert_eq!(page, vec![first.hash()]);
+        assert_eq!(cursor, None);
+
+        let (page, cursor) = executor.account_transactions(&H160::random(), None, 2);
+        assert!(page.is_empty());
+        assert_eq!(cursor, None);
+    }
+}
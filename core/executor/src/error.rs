@@ -0,0 +1,107 @@
+use ethereum_types::{H160, H256, U256};
+use intmax_verkle::StorageError;
+
+/// Executor Result type.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Executor errors.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Error from the state storage.
+    #[error("Storage error: {}", .0)]
+    Storage(StorageError),
+
+    /// Error from the database.
+    #[error("Database error: {}", .0)]
+    Db(#[from] std::io::Error),
+
+    /// If the deposit has already been applied to the state.
+    #[error("Deposit({}) has already been applied", .id)]
+    DuplicateDeposit {
+        /// Deposit already applied
+        id: H256,
+    },
+
+    /// If a balance would exceed `U256::MAX`.
+    #[error("Balance of {} for token {} overflows", .account, .token)]
+    BalanceOverflow {
+        /// Account of the balance
+        account: H160,
+        /// Token of the balance
+        token: H160,
+    },
+
+    /// If a withdrawal does not use the current nonce of its account.
+    #[error("Nonce of {} is {}, but got {}", .account, .expected, .actual)]
+    InvalidNonce {
+        /// Account of the withdrawal
+        account: H160,
+        /// Current nonce of the account
+        expected: U256,
+        /// Nonce of the withdrawal
+        actual: U256,
+    },
+
+    /// If a withdrawal exceeds the balance.
+    #[error("Balance of {} for token {} is insufficient", .account, .token)]
+    InsufficientBalance {
+        /// Account of the balance
+        account: H160,
+        /// Token of the balance
+        token: H160,
+    },
+
+    /// If there is no block with the number yet.
+    #[error("Block {} is not sealed yet", .number)]
+    UnknownBlockNumber {
+        /// Block number
+        number: u64,
+    },
+
+    /// If the state diff has already been applied to the state.
+    #[error("StateDiff({}) has already been applied", .state_diff)]
+    DuplicateStateDiff {
+        /// State diff already applied
+        state_diff: H256,
+    },
+
+    /// If a state diff does not start from the current state of its user.
+    #[error("State of {} is {} after {} transitions", .user, .root, .nonce)]
+    StaleUserState {
+        /// User of the state diff
+        user: H160,
+        /// Current root of the user state
+        root: H256,
+        /// Current nonce of the user state
+        nonce: u64,
+    },
+
+    /// If a snapshot is imported into a node which already holds blocks.
+    #[error("The node holds blocks up to block {}, a snapshot is imported into a new database", .latest)]
+    StateNotEmpty {
+        /// Latest block of the node
+        latest: u64,
+    },
+
+    /// If a snapshot holds the state of another block than its own.
+    #[error("Snapshot of block {} holds the state of block {}", .number, .state)]
+    SnapshotBlockMismatch {
+        /// Block of the snapshot
+        number: u64,
+        /// Block of the state tree in the snapshot
+        state: u64,
+    },
+
+    /// If the state tree of the block is not kept.
+    #[error("State tree at block {} is pruned", .number)]
+    StateVersionPruned {
+        /// Block number
+        number: u64,
+    },
+}
+
+impl From<StorageError> for Error {
+    fn from(e: StorageError) -> Self {
+        Error::Storage(e)
+    }
+}
@@ -1,8 +1,907 @@
+use std::sync::Mutex;
+
+use codec::Decode;
+use db::{DBKey, DBTx, Database};
+use ethereum_types::{H160, H256, U256};
+use intmax_verkle::{Batch, Proof, SchemeKind, VerkleSMTStorage, VerkleStorage};
+use tracing::info;
+
+pub use error::{Error, Result};
+pub use intmax_zk::StateDiff;
+pub use logs::{deposit_topic, exit_topic, state_update_topic, Log};
+pub use transaction::{Block, Deposit, Transaction, UserState, Withdrawal};
+
+mod account_index;
+mod error;
+mod logs;
+mod snapshot;
+pub mod state;
+mod transaction;
+
+const DEPOSIT_PREFIX: &[u8] = b"deposit";
+const STATE_DIFF_PREFIX: &[u8] = b"state_diff";
+const BLOCK_PREFIX: &[u8] = b"block";
+const BLOCK_HASH_PREFIX: &[u8] = b"block_hash";
+const TX_PREFIX: &[u8] = b"tx";
+const LATEST_KEY: &[u8] = b"latest";
+/// Number of pending transactions.
+const PENDING_COUNT_KEY: &[u8] = b"pending_count";
+/// The pending transactions, by index.
+const PENDING_PREFIX: &[u8] = b"pending";
+/// State writes of each pending transaction, by index, kept by the state tree in memory until
+/// sealed.
+const PENDING_WRITES_PREFIX: &[u8] = b"pending_writes";
+
+pub trait ExecutorTrait {
+    /// Applies `tx` to the L2 state.
+    fn execute(&self, tx: &Transaction) -> Result<()>;
+}
+
+/// Applies transactions to the account state kept in `S`.
+/// Its own bookkeeping (applied deposits, blocks and the transactions waiting for the next
+/// block) lives in `D`, the `db::columns::EXECUTOR` column.
+pub struct Executor<S: VerkleStorage, D: Database> {
+    state: S,
+    db: D,
+    /// Number of past blocks whose state tree is kept besides the latest one. `None` keeps all.
+    version_window: Option<u64>,
+    /// The `db::columns::LOGS` column, `None` if logs are not indexed.
+    logs: Option<D>,
+    /// The `db::columns::ACCOUNT_TXS` column, `None` if transactions are not indexed by account.
+    account_index: Option<D>,
+    write_lock: Mutex<()>,
+}
+
+impl<S: VerkleStorage, D: Database> Executor<S, D> {
+    pub fn new(state: S, db: D) -> Self {
+        Executor {
+            state,
+            db,
+            version_window: None,
+            logs: None,
+            account_index: None,
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    /// Keeps the state tree of the latest `blocks` blocks besides the latest one for proofs.
+    /// Older versions are dropped by `prune_state`.
+    pub fn with_version_window(mut self, blocks: u64) -> Self {
+        self.version_window = Some(blocks);
+        self
+    }
+
+    /// Indexes the logs of the sealed blocks in `logs`, the `db::columns::LOGS` column.
+    pub fn with_log_index(mut self, logs: D) -> Self {
+        self.logs = Some(logs);
+        self
+    }
+
+    /// Indexes the sealed transactions by account in `index`, the `db::columns::ACCOUNT_TXS`
+    /// column.
+    pub fn with_account_index(mut self, index: D) -> Self {
+        self.account_index = Some(index);
+        self
+    }
+
+    /// Balance of `token` held by `account`.
+    pub fn balance(&self, account: &H160, token: &H160) -> Result<U256> {
+        self.get_state(&state::balance_key(account, token))
+    }
+
+    /// Nonce the next withdrawal of `account` must use.
+    pub fn nonce(&self, account: &H160) -> Result<U256> {
+        self.get_state(&state::nonce_key(account))
+    }
+
+    /// The withdrawal made by the transaction `id`.
+    pub fn withdrawal(&self, id: &H256) -> Result<Option<Withdrawal>> {
+        Ok(self
+            .state
+            .get(&state::tree_key(&state::withdrawal_key(id)))?)
+    }
+
+    /// State root of `user` and the number of its transitions.
+    pub fn user_state(&self, user: &H160) -> Result<UserState> {
+        self.get_state(&state::user_state_key(user))
+    }
+
+    /// The value under `key`, the default one if it is absent.
+    fn get_state<V: Decode + Default>(&self, key: &H256) -> Result<V> {
+        Ok(self.state.get(&state::tree_key(key))?.unwrap_or_default())
+    }
+
+    /// Whether the state diff `hash` has already been applied.
+    pub fn is_state_diff_applied(&self, hash: &H256) -> bool {
+        self.db
+            .contains(&prefixed_key(STATE_DIFF_PREFIX, hash.as_bytes()))
+    }
+
+    /// Root of the state tree.
+    pub fn state_root(&self) -> Result<H256> {
+        Ok(H256(self.state.root()?))
+    }
+
+    /// Scheme of the state tree, the one to verify its proofs with.
+    pub fn state_scheme(&self) -> SchemeKind {
+        self.state.scheme()
+    }
+
+    /// Inclusion proof of `key` in the state, under `state_root`.
+    pub fn inclusion_proof(&self, key: &H256) -> Result<Proof> {
+        Ok(self.state.inclusion_proof(&state::tree_key(key))?)
+    }
+
+    /// Root of the state tree at the end of block `number`, recorded when it was sealed.
+    pub fn state_root_at(&self, number: u64) -> Result<H256> {
+        self.block(number)
+            .map(|block| block.state_root)
+            .ok_or(Error::UnknownBlockNumber { number })
+    }
+
+    /// Fails unless the state tree of block `number` is still stored.
+    fn check_state_version(&self, number: u64) -> Result<()> {
+        if number > self.latest_block_number() {
+            return Err(Error::UnknownBlockNumber { number });
+        }
+
+        match self.state.root_at(number)? {
+            Some(_) => Ok(()),
+            None => Err(Error::StateVersionPruned { number }),
+        }
+    }
+
+    /// Balance of `token` held by `account` at the end of block `number`.
+    pub fn balance_at(&self, account: &H160, token: &H160, number: u64) -> Result<U256> {
+        self.value_at(number, &state::balance_key(account, token))
+    }
+
+    /// Nonce of `account` at the end of block `number`.
+    pub fn nonce_at(&self, account: &H160, number: u64) -> Result<U256> {
+        self.value_at(number, &state::nonce_key(account))
+    }
+
+    /// Value of `key` in the state tree of block `number`, zero if it is absent.
+    fn value_at(&self, number: u64, key: &H256) -> Result<U256> {
+        // Block 0 is the empty state before block 1.
+        if number == 0 {
+            return Ok(U256::zero());
+        }
+        self.check_state_version(number)?;
+        Ok(self
+            .state
+            .get_at(number, &state::tree_key(key))?
+            .unwrap_or_default())
+    }
+
+    /// Proof of the value of `key` in the state at the end of block `number`, or of its
+    /// absence, under `state_root_at(number)`.
+    pub fn proof_at(&self, number: u64, key: &H256) -> Result<Proof> {
+        self.check_state_version(number)?;
+        Ok(self.state.proof_at(number, &state::tree_key(key))?)
+    }
+
+    /// Drops the state tree of the blocks out of the version window, but keeps every block from
+    /// `keep_from` on, e.g. the oldest one still to be proven on L1. Returns the number of dropped
+    /// nodes.
+    pub fn prune_state(&self, keep_from: Option<u64>) -> Result<usize> {
+        let _guard = self.write_lock.lock().expect("executor lock poisoned");
+
+        let window = match self.version_window {
+            Some(window) => window,
+            None => return Ok(0),
+        };
+        let oldest = self.latest_block_number().saturating_sub(window);
+        let oldest = keep_from.map_or(oldest, |keep_from| oldest.min(keep_from));
+        Ok(self.state.prune(oldest, None)?)
+    }
+
+    /// Whether the deposit `id` has already been applied.
+    pub fn is_deposit_applied(&self, id: &H256) -> bool {
+        self.db
+            .contains(&prefixed_key(DEPOSIT_PREFIX, id.as_bytes()))
+    }
+
+    pub fn block(&self, number: u64) -> Option<Block> {
+        self.db
+            .get(&prefixed_key(BLOCK_PREFIX, &number.to_be_bytes()))
+    }
+
+    pub fn block_by_hash(&self, hash: &H256) -> Option<Block> {
+        let number = self
+            .db
+            .get(&prefixed_key(BLOCK_HASH_PREFIX, hash.as_bytes()))?;
+        self.block(number)
+    }
+
+    pub fn latest_block(&self) -> Option<Block> {
+        let number = self.db.get(&DBKey::from_slice(LATEST_KEY))?;
+        self.block(number)
+    }
+
+    /// Number of the latest block, 0 before the first one.
+    pub fn latest_block_number(&self) -> u64 {
+        self.db
+            .get(&DBKey::from_slice(LATEST_KEY))
+            .unwrap_or_default()
+    }
+
+    /// Number of the block which includes the transaction `hash`.
+    pub fn transaction_block(&self, hash: &H256) -> Option<u64> {
+        self.db.get(&prefixed_key(TX_PREFIX, hash.as_bytes()))
+    }
+
+    /// Transactions applied since the latest block.
+    pub fn pending_transactions(&self) -> Vec<Transaction> {
+        (0..self.pending_count())
+            .filter_map(|index| self.db.get(&pending_key(PENDING_PREFIX, index)))
+            .collect()
+    }
+
+    fn pending_count(&self) -> u64 {
+        self.db
+            .get(&DBKey::from_slice(PENDING_COUNT_KEY))
+            .unwrap_or_default()
+    }
+
+    /// Applies the state writes of the pending transactions again, as the state tree loses
+    /// them on restart. Called once before executing transactions.
+    pub fn restore_pending_writes(&self) -> Result<()> {
+        let _guard = self.write_lock.lock().expect("executor lock poisoned");
+
+        let mut writes = Batch::default();
+        for index in 0..self.pending_count() {
+            let batch: Batch = self
+                .db
+                .get(&pending_key(PENDING_WRITES_PREFIX, index))
+                .unwrap_or_default();
+            writes.writes.extend(batch.writes);
+        }
+        Ok(self.state.apply(&writes)?)
+    }
+
+    /// Seals the pending transactions into the next block. Returns `None` if there are none.
+    /// Blocks are numbered from 1, as batch 0 on L1 means that nothing is committed yet.
+    pub fn seal_block(&self, timestamp: u64) -> Result<Option<Block>> {
+        let _guard = self.write_lock.lock().expect("executor lock poisoned");
+
+        let transactions = self.pending_transactions();
+        if transactions.is_empty() {
+            return Ok(None);
+        }
+
+        let state_root = self.state_root()?;
+        let block = match self.latest_block() {
+            Some(parent) => Block::new(
+                parent.number + 1,
+                parent.hash,
+                timestamp,
+                state_root,
+                transactions,
+            ),
+            None => Block::new(1, H256::zero(), timestamp, state_root, transactions),
+        };
+
+        self.index_logs(&block)?;
+        self.index_account_transactions(&block)?;
+
+        let mut tx = self.db.make_tx();
+        self.put_block(&block, &mut tx);
+        for index in 0..block.transactions.len() as u64 {
+            tx.delete(&pending_key(PENDING_PREFIX, index));
+            tx.delete(&pending_key(PENDING_WRITES_PREFIX, index));
+        }
+        tx.delete(&PENDING_COUNT_KEY);
+        // If the block is lost to a crash, it is sealed again on the same state version.
+        self.state.commit_version(block.number)?;
+        self.db.commit(tx)?;
+
+        info!(
+            "sealed block {} ({:?}) with {} transactions",
+            block.number,
+            block.hash,
+            block.transactions.len()
+        );
+        Ok(Some(block))
+    }
+
+    /// Records `block` as the latest one.
+    fn put_block(&self, block: &Block, tx: &mut DBTx) {
+        tx.put(
+            &prefixed_key(BLOCK_PREFIX, &block.number.to_be_bytes()),
+            block,
+        );
+        tx.put(
+            &prefixed_key(BLOCK_HASH_PREFIX, block.hash.as_bytes()),
+            &block.number,
+        );
+        for transaction in block.transactions.iter() {
+            tx.put(
+                &prefixed_key(TX_PREFIX, transaction.hash().as_bytes()),
+                &block.number,
+            );
+        }
+        tx.put(&LATEST_KEY, &block.number);
+    }
+
+    fn apply_deposit(&self, deposit: &Deposit, batch: &mut Batch, tx: &mut DBTx) -> Result<()> {
+        if self.is_deposit_applied(&deposit.id) {
+            return Err(Error::DuplicateDeposit { id: deposit.id });
+        }
+
+        let key = state::balance_key(&deposit.recipient, &deposit.token);
+        let balance = self
+            .balance(&deposit.recipient, &deposit.token)?
+            .checked_add(deposit.amount)
+            .ok_or(Error::BalanceOverflow {
+                account: deposit.recipient,
+                token: deposit.token,
+            })?;
+        batch.put(&state::tree_key(&key), &balance);
+
+        tx.put(
+            &prefixed_key(DEPOSIT_PREFIX, deposit.id.as_bytes()),
+            &deposit.l1_block,
+        );
+
+        info!(
+            "applied deposit {:?}: {} of {:?} to {:?}",
+            deposit.id, deposit.amount, deposit.token, deposit.recipient
+        );
+        Ok(())
+    }
+
+    /// Burns the withdrawn amount and records the withdrawal leaf proven on exit.
+    fn apply_withdrawal(
+        &self,
+        id: &H256,
+        withdrawal: &Withdrawal,
+        batch: &mut Batch,
+    ) -> Result<()> {
+        let nonce = self.nonce(&withdrawal.account)?;
+        if nonce != withdrawal.nonce {
+            return Err(Error::InvalidNonce {
+                account: withdrawal.account,
+                expected: nonce,
+                actual: withdrawal.nonce,
+            });
+        }
+
+        let balance = self
+            .balance(&withdrawal.account, &withdrawal.token)?
+            .checked_sub(withdrawal.amount)
+            .ok_or(Error::InsufficientBalance {
+                account: withdrawal.account,
+                token: withdrawal.token,
+            })?;
+        batch.put(
+            &state::tree_key(&state::balance_key(&withdrawal.account, &withdrawal.token)),
+            &balance,
+        );
+        batch.put(
+            &state::tree_key(&state::nonce_key(&withdrawal.account)),
+            &(nonce + 1),
+        );
+        batch.put(&state::tree_key(&state::withdrawal_key(id)), withdrawal);
+
+        info!(
+            "applied withdrawal {:?}: {} of {:?} from {:?}",
+            id, withdrawal.amount, withdrawal.token, withdrawal.account
+        );
+        Ok(())
+    }
+}
+
+impl<S: VerkleStorage, D: Database> Executor<S, D> {
+    /// Moves the state root of the user of `diff`. Its proof is checked by the su-receiver.
+    fn apply_state_diff(&self, diff: &StateDiff, batch: &mut Batch, tx: &mut DBTx) -> Result<()> {
+        let hash = diff.hash();
+        if self.is_state_diff_applied(&hash) {
+            return Err(Error::DuplicateStateDiff { state_diff: hash });
+        }
+
+        let current = self.user_state(&diff.user)?;
+        if current.root != diff.old_root || current.nonce != diff.nonce {
+            return Err(Error::StaleUserState {
+                user: diff.user,
+                root: current.root,
+                nonce: current.nonce,
+            });
+        }
+        batch.put(
+            &state::tree_key(&state::user_state_key(&diff.user)),
+            &UserState {
+                root: diff.new_root,
+                nonce: diff.nonce + 1,
+            },
+        );
+
+        tx.put(
+            &prefixed_key(STATE_DIFF_PREFIX, hash.as_bytes()),
+            &diff.user,
+        );
+
+        info!(
+            "applied state diff {:?} of {:?}: {:?} -> {:?}",
+            hash, diff.user, diff.old_root, diff.new_root
+        );
+        Ok(())
+    }
+}
+
+impl<S: VerkleSMTStorage, D: Database> Executor<S, D> {
+    /// Proof that nothing is stored under `key` in the state.
+    pub fn exclusion_proof(&self, key: &H256) -> Result<Proof> {
+        Ok(self.state.inclusion_empty_proof(&state::tree_key(key))?)
+    }
+}
+
+impl<S: VerkleStorage, D: Database> ExecutorTrait for Executor<S, D> {
+    fn execute(&self, transaction: &Transaction) -> Result<()> {
+        let _guard = self.write_lock.lock().expect("executor lock poisoned");
+
+        let (mut batch, mut tx) = (Batch::default(), self.db.make_tx());
+        match transaction {
+            Transaction::Deposit(deposit) => self.apply_deposit(deposit, &mut batch, &mut tx)?,
+            Transaction::Withdraw(withdrawal) => {
+                self.apply_withdrawal(&transaction.hash(), withdrawal, &mut batch)?
+            }
+            Transaction::UpdateState(diff) => self.apply_state_diff(diff, &mut batch, &mut tx)?,
+        }
+
+        // The writes reach the state tree only once recorded, so that a failed commit leaves
+        // no writes behind for `seal_block` to commit.
+        let index = self.pending_count();
+        tx.put(&pending_key(PENDING_PREFIX, index), transaction);
+        tx.put(&pending_key(PENDING_WRITES_PREFIX, index), &batch);
+        tx.put(&PENDING_COUNT_KEY, &(index + 1));
+        self.db.commit(tx)?;
+        self.state.apply(&batch)?;
+
+        Ok(())
+    }
+}
+
+fn pending_key(prefix: &[u8], index: u64) -> DBKey {
+    prefixed_key(prefix, &index.to_be_bytes())
+}
+
+fn prefixed_key(prefix: &[u8], id: &[u8]) -> DBKey {
+    let mut key = DBKey::from_slice(prefix);
+    key.extend_from_slice(id);
+    key
+}
+
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
+    use super::*;
+    use db::Db;
+    use intmax_verkle::verifier::{Scheme, SparseMerkle, Verkle};
+    use intmax_verkle::{SparseMerkleTree, StateTree, VerkleTree};
+    use kvdb_memorydb::InMemory;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    pub type TestExecutor = Executor<VerkleTree<Db<InMemory>>, Db<InMemory>>;
+
+    pub fn executor() -> TestExecutor {
+        let db = Db::new(kvdb_memorydb::create(db::columns::NUM_COLUMNS), 0);
+        Executor::new(
+            VerkleTree::new(db.with_column(db::columns::STATE)),
+            db.with_column(db::columns::EXECUTOR),
+        )
+    }
+
+    pub fn deposit(id: u64, recipient: H160, amount: u64) -> Deposit {
+        Deposit {
+            id: H256::from_low_u64_be(id),
+            l1_block: 1,
+            recipient,
+            token: H160::zero(),
+            amount: U256::from(amount),
+        }
+    }
+
+    #[test]
+    fn success_execute_deposit() {
+        let executor = executor();
+        let alice = H160::random();
+
+        executor
+            .execute(&Transaction::Deposit(deposit(1, alice, 100)))
+            .expect("deposit ok.");
+        executor
+            .execute(&Transaction::Deposit(deposit(2, alice, 50)))
+            .expect("deposit ok.");
+
+        assert_eq!(
+            executor.balance(&alice, &H160::zero()).unwrap(),
+            U256::from(150)
+        );
+        assert_eq!(
+            executor.balance(&alice, &H160::random()).unwrap(),
+            U256::zero()
+        );
+        assert!(executor.is_deposit_applied(&H256::from_low_u64_be(1)));
+    }
+
+    #[test]
+    fn fail_execute_deposit_twice() {
+        let executor = executor();
+        let alice = H160::random();
+
+        let tx = Transaction::Deposit(deposit(1, alice, 100));
+        executor.execute(&tx).expect("deposit ok.");
+        let err = executor.execute(&tx).unwrap_err();
+
+        assert!(matches!(err, Error::DuplicateDeposit { .. }));
+        assert_eq!(
+            executor.balance(&alice, &H160::zero()).unwrap(),
+            U256::from(100)
+        );
+    }
+
+    #[test]
+    fn fail_execute_deposit_overflow() {
+        let executor = executor();
+        let alice = H160::random();
+
+        let mut tx = deposit(1, alice, 0);
+        tx.amount = U256::MAX;
+        executor
+            .execute(&Transaction::Deposit(tx))
+            .expect("deposit ok.");
+
+        let err = executor
+            .execute(&Transaction::Deposit(deposit(2, alice, 1)))
+            .unwrap_err();
+        assert!(matches!(err, Error::BalanceOverflow { .. }));
+        assert!(!executor.is_deposit_applied(&H256::from_low_u64_be(2)));
+    }
+
+    pub fn withdrawal(account: H160, amount: u64, nonce: u64) -> Transaction {
+        Transaction::Withdraw(Withdrawal {
+            account,
+            token: H160::zero(),
+            amount: U256::from(amount),
+            nonce: U256::from(nonce),
+        })
+    }
+
+    #[test]
+    fn success_execute_withdrawal() {
+        let executor = executor();
+        let alice = H160::random();
+        executor
+            .execute(&Transaction::Deposit(deposit(1, alice, 100)))
+            .expect("deposit ok.");
+
+        let tx = withdrawal(alice, 30, 0);
+        executor.execute(&tx).expect("withdrawal ok.");
+
+        assert_eq!(
+            executor.balance(&alice, &H160::zero()).unwrap(),
+            U256::from(70)
+        );
+        assert_eq!(executor.nonce(&alice).unwrap(), U256::one());
+        assert!(matches!(
+            executor.withdrawal(&tx.hash()).unwrap(),
+            Some(Withdrawal { amount, nonce, .. }) if amount == U256::from(30) && nonce.is_zero()
+        ));
+
+        let proof = executor
+            .inclusion_proof(&state::withdrawal_key(&tx.hash()))
+            .expect("proof ok.");
+        executor.state.verify_proof(&proof).expect("verify ok.");
+    }
+
+    #[test]
+    fn success_execute_on_sparse_merkle_state() {
+        let db = Db::new(kvdb_memorydb::create(db::columns::NUM_COLUMNS), 0);
+        let executor = Executor::new(
+            StateTree::SparseMerkle(SparseMerkleTree::new(db.with_column(db::columns::STATE))),
+            db.with_column(db::columns::EXECUTOR),
+        );
+        let alice = H160::random();
+        executor
+            .execute(&Transaction::Deposit(deposit(1, alice, 100)))
+            .expect("deposit ok.");
+        let tx = withdrawal(alice, 30, 0);
+        executor.execute(&tx).expect("withdrawal ok.");
+
+        assert_eq!(
+            executor.balance(&alice, &H160::zero()).unwrap(),
+            U256::from(70)
+        );
+        let key = state::withdrawal_key(&tx.hash());
+        let proof = executor.inclusion_proof(&key).expect("proof ok.");
+        SparseMerkle::verify(
+            &executor.state_root().unwrap().0,
+            &proof,
+            &key,
+            &executor.withdrawal(&tx.hash()).unwrap().unwrap(),
+        )
+        .expect("verify ok.");
+        let proof = executor
+            .exclusion_proof(&state::withdrawal_key(&H256::random()))
+            .expect("proof ok.");
+        executor.state.verify_proof(&proof).expect("verify ok.");
+    }
+
+    #[test]
+    fn fail_execute_withdrawal() {
+        let executor = executor();
+        let alice = H160::random();
+        executor
+            .execute(&Transaction::Deposit(deposit(1, alice, 100)))
+            .expect("deposit ok.");
+
+        let err = executor.execute(&withdrawal(alice, 30, 1)).unwrap_err();
+        assert!(matches!(err, Error::InvalidNonce { .. }));
+
+        let err = executor.execute(&withdrawal(alice, 101, 0)).unwrap_err();
+        assert!(matches!(err, Error::InsufficientBalance { .. }));
+
+        assert_eq!(
+            executor.balance(&alice, &H160::zero()).unwrap(),
+            U256::from(100)
+        );
+        assert_eq!(executor.nonce(&alice).unwrap(), U256::zero());
+        assert_eq!(executor.pending_transactions().len(), 1);
+    }
+
+    #[test]
+    fn success_seal_block() {
+        let executor = executor();
+        let alice = H160::random();
+        assert_eq!(executor.seal_block(1).unwrap(), None);
+
+        let first = Transaction::Deposit(deposit(1, alice, 100));
+        executor.execute(&first).expect("deposit ok.");
+        let block = executor.seal_block(1).unwrap().expect("block 1.");
+        assert_eq!(block.number, 1);
+        assert_eq!(block.parent_hash, H256::zero());
+        assert_eq!(block.transactions, vec![first.clone()]);
+        assert!(executor.pending_transactions().is_empty());
+        assert_eq!(executor.seal_block(2).unwrap(), None);
+
+        let second = withdrawal(alice, 30, 0);
+        executor.execute(&second).expect("withdrawal ok.");
+        assert_eq!(executor.transaction_block(&second.hash()), None);
+        let next = executor.seal_block(3).unwrap().expect("block 2.");
+        assert_eq!(next.number, 2);
+        assert_eq!(next.parent_hash, block.hash);
+
+        assert_eq!(executor.latest_block(), Some(next.clone()));
+        assert_eq!(executor.block(1), Some(block.clone()));
+        assert_eq!(executor.block_by_hash(&next.hash), Some(next));
+        assert_eq!(executor.transaction_block(&first.hash()), Some(1));
+        assert_eq!(executor.transaction_block(&second.hash()), Some(2));
+    }
+
+    /// Fails every commit once `fail` is set.
+    struct FailingDb {
+        db: Db<InMemory>,
+        fail: AtomicBool,
+    }
+
+    impl Database for FailingDb {
+        fn commit(&self, tx: DBTx) -> std::io::Result<()> {
+            if self.fail.load(Ordering::SeqCst) {
+                return Err(std::io::Error::other("commit failed"));
+            }
+            self.db.commit(tx)
+        }
+
+        fn get_raw(&self, key: &DBKey) -> Option<Vec<u8>> {
+            self.db.get_raw(key)
+        }
+
+        fn make_tx(&self) -> DBTx {
+            self.db.make_tx()
+        }
+
+        fn make_tx_with_capacity(&self, cap: usize) -> DBTx {
+            self.db.make_tx_with_capacity(cap)
+        }
+    }
+
+    #[test]
+    fn fail_execute_commit() {
+        let db = Db::new(kvdb_memorydb::create(db::columns::NUM_COLUMNS), 0);
+        let executor = Executor::new(
+            VerkleTree::new(db.with_column(db::columns::STATE)),
+            FailingDb {
+                db: db.with_column(db::columns::EXECUTOR),
+                fail: Default::default(),
+            },
+        );
+        let alice = H160::random();
+        executor
+            .execute(&Transaction::Deposit(deposit(1, alice, 100)))
+            .unwrap();
+        let root = executor.state_root().unwrap();
+
+        executor.db.fail.store(true, Ordering::SeqCst);
+        let err = executor
+            .execute(&Transaction::Deposit(deposit(2, alice, 50)))
+            .unwrap_err();
+        assert!(matches!(err, Error::Db(_)));
+        assert_eq!(executor.state_root().unwrap(), root);
+        assert_eq!(
+            executor.balance(&alice, &H160::zero()).unwrap(),
+            U256::from(100)
+        );
+
+        executor.db.fail.store(false, Ordering::SeqCst);
+        let block = executor.seal_block(1).unwrap().unwrap();
+        assert_eq!(block.transactions.len(), 1);
+        assert_eq!(block.state_root, root);
+    }
+
+    #[test]
+    fn success_restore_pending_writes() {
+        let db = Db::new(kvdb_memorydb::create(db::columns::NUM_COLUMNS), 0);
+        let open = || {
+            Executor::new(
+                VerkleTree::new(db.with_column(db::columns::STATE)),
+                db.with_column(db::columns::EXECUTOR),
+            )
+        };
+        let alice = H160::random();
+        let executor = open();
+        executor
+            .execute(&Transaction::Deposit(deposit(1, alice, 100)))
+            .unwrap();
+        executor.seal_block(1).unwrap();
+        executor.execute(&withdrawal(alice, 30, 0)).unwrap();
+        let root = executor.state_root().unwrap();
+
+        // The state tree keeps the writes of the pending transactions in memory.
+        let restarted = open();
+        assert_eq!(
+            restarted.balance(&alice, &H160::zero()).unwrap(),
+            U256::from(100)
+        );
+        restarted.restore_pending_writes().unwrap();
+        assert_eq!(
+            restarted.balance(&alice, &H160::zero()).unwrap(),
+            U256::from(70)
+        );
+        assert_eq!(restarted.state_root().unwrap(), root);
+
+        restarted.seal_block(2).unwrap();
+        let sealed = open();
+        sealed.restore_pending_writes().unwrap();
+        assert_eq!(sealed.state_root().unwrap(), root);
+
+        // A crash between the state tree version and the block of a seal.
+        sealed.execute(&withdrawal(alice, 10, 1)).unwrap();
+        sealed.state.commit_version(3).unwrap();
+        let recovered = open();
+        recovered.restore_pending_writes().unwrap();
+        assert_eq!(recovered.pending_transactions().len(), 1);
+        assert_eq!(recovered.seal_block(3).unwrap().unwrap().number, 3);
+        assert_eq!(
+            recovered.balance_at(&alice, &H160::zero(), 3).unwrap(),
+            U256::from(60)
+        );
+        assert!(recovered.pending_transactions().is_empty());
+    }
+
+    #[test]
+    fn success_prune_state() {
+        let executor = executor().with_version_window(1);
+        let alice = H160::random();
+        let key = state::balance_key(&alice, &H160::zero());
+        for id in 1..4 {
+            executor
+                .execute(&Transaction::Deposit(deposit(id, alice, 10)))
+                .unwrap();
+            executor.seal_block(id).unwrap();
+        }
+
+        let proof = executor.proof_at(1, &key).expect("proof ok.");
+        let root = executor.state_root_at(1).unwrap();
+        Verkle::verify(&root.0, &proof, &key, &U256::from(10)).expect("verify ok.");
+        assert!(matches!(
+            executor.state_root_at(4),
+            Err(Error::UnknownBlockNumber { number: 4 })
+        ));
+
+        // Block 1 is out of the window but kept.
+        assert_eq!(executor.prune_state(Some(1)).unwrap(), 0);
+        assert!(executor.proof_at(1, &key).is_ok());
+        assert!(executor.prune_state(None).unwrap() > 0);
+        assert!(matches!(
+            executor.proof_at(1, &key),
+            Err(Error::StateVersionPruned { number: 1 })
+        ));
+        // The root of a pruned block is still known.
+        assert_eq!(executor.state_root_at(1).unwrap(), root);
+        let proof = executor.proof_at(2, &key).expect("proof ok.");
+        let root = executor.state_root_at(2).unwrap();
+        Verkle::verify(&root.0, &proof, &key, &U256::from(20)).expect("verify ok.");
+        assert_eq!(
+            executor.state_root_at(3).unwrap(),
+            executor.state_root().unwrap()
+        );
+    }
+
+    #[test]
+    fn success_state_at() {
+        let executor = executor().with_version_window(1);
+        let alice = H160::random();
+        let token = H160::zero();
+
+        executor
+            .execute(&Transaction::Deposit(deposit(1, alice, 100)))
+            .unwrap();
+        executor.seal_block(1).unwrap();
+        executor.execute(&withdrawal(alice, 30, 0)).unwrap();
+        executor.seal_block(2).unwrap();
+        executor
+            .execute(&Transaction::Deposit(deposit(2, H160::random(), 1)))
+            .unwrap();
+        executor.seal_block(3).unwrap();
+        executor.execute(&withdrawal(alice, 10, 1)).unwrap();
+
+        assert_eq!(executor.balance_at(&alice, &token, 0).unwrap(), 0.into());
+        assert_eq!(executor.balance_at(&alice, &token, 1).unwrap(), 100.into());
+        assert_eq!(executor.balance_at(&alice, &token, 2).unwrap(), 70.into());
+        assert_eq!(executor.balance_at(&alice, &token, 3).unwrap(), 70.into());
+        assert_eq!(executor.balance(&alice, &token).unwrap(), 60.into());
+        assert_eq!(executor.nonce_at(&alice, 1).unwrap(), 0.into());
+        assert_eq!(executor.nonce_at(&alice, 3).unwrap(), 1.into());
+        assert!(matches!(
+            executor.balance_at(&alice, &token, 4),
+            Err(Error::UnknownBlockNumber { number: 4 })
+        ));
+
+        // Balances and nonces are read from the kept state trees.
+        executor.prune_state(None).unwrap();
+        assert!(matches!(
+            executor.balance_at(&alice, &token, 1),
+            Err(Error::StateVersionPruned { number: 1 })
+        ));
+        assert_eq!(executor.balance_at(&alice, &token, 2).unwrap(), 70.into());
+    }
+
     #[test]
-    fn it_works() {
-        let result = 2 + 2;
-        assert_eq!(result, 4);
+    fn success_execute_state_diff() {
+        let executor = executor();
+        let user = H160::random();
+        let diff = StateDiff {
+            user,
+            old_root: H256::zero(),
+            new_root: H256::repeat_byte(1),
+            nonce: 0,
+        };
+
+        executor
+            .execute(&Transaction::UpdateState(diff.clone()))
+            .expect("state diff ok.");
+        assert_eq!(
+            executor.user_state(&user).unwrap(),
+            UserState {
+                root: diff.new_root,
+                nonce: 1
+            }
+        );
+        assert!(executor.is_state_diff_applied(&diff.hash()));
+
+        let err = executor
+            .execute(&Transaction::UpdateState(diff.clone()))
+            .unwrap_err();
+        assert!(matches!(err, Error::DuplicateStateDiff { .. }));
+
+        let stale = StateDiff {
+            old_root: H256::repeat_byte(2),
+            nonce: 1,
+            ..diff
+        };
+        let err = executor
+            .execute(&Transaction::UpdateState(stale))
+            .unwrap_err();
+        assert!(matches!(err, Error::StaleUserState { nonce: 1, .. }));
     }
 }
@@ -0,0 +1,173 @@
+//! Logs emitted by the transactions of the sealed blocks.
+//!
+//! Every deposit or withdrawal emits one log whose `address` is the token it moves, so that
+//! filtering by address works as for token contracts on L1. State updates emit one log whose
+//! `address` is their user. The logs of a block are indexed by its number
+//! in the `db::columns::LOGS` column, if the executor is given one.
+use codec::{Decode, Encode};
+use db::{DBKey, Database};
+use ethereum_types::{H160, H256, U256};
+use intmax_verkle::VerkleStorage;
+
+use crate::error::Result;
+use crate::state::keccak256;
+use crate::{Block, Executor, Transaction};
+
+/// `topics[0]` of the logs of deposits. `topics[1]` is the deposit id and `topics[2]` the
+/// recipient.
+pub fn deposit_topic() -> H256 {
+    keccak256(&[b"Deposit(bytes32,address,uint256)"])
+}
+
+/// `topics[0]` of the logs of withdrawals, i.e. exits. `topics[1]` is the exit id and
+/// `topics[2]` the withdrawing account.
+pub fn exit_topic() -> H256 {
+    keccak256(&[b"Exit(bytes32,address,uint256)"])
+}
+
+/// `topics[0]` of the logs of user state updates. `topics[1]` is the state diff hash and
+/// `topics[2]` the user.
+pub fn state_update_topic() -> H256 {
+    keccak256(&[b"StateUpdate(bytes32,address,bytes32)"])
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct Log {
+    /// Token moved by the transaction, the zero address being the L1 native token, or the user
+    /// of a state update.
+    pub address: H160,
+    pub topics: Vec<H256>,
+    /// The amount as a big endian `uint256`, or the new root of a state update.
+    pub data: Vec<u8>,
+    pub block_number: u64,
+    pub block_hash: H256,
+    pub transaction_hash: H256,
+    /// Position of the transaction in the block.
+    pub transaction_index: u32,
+    /// Position of the log in the block.
+    pub log_index: u32,
+}
+
+impl<S: VerkleStorage, D: Database> Executor<S, D> {
+    /// Logs of block `number`. Empty if there is no log index or the block was sealed before
+    /// the index was enabled.
+    pub fn logs(&self, number: u64) -> Vec<Log> {
+        self.logs
+            .as_ref()
+            .and_then(|logs| logs.get(&DBKey::from_slice(&number.to_be_bytes())))
+            .unwrap_or_default()
+    }
+
+    /// Writes the logs of `block` to the log index.
+    ///
+    /// The index is written before the block is committed. If the node stops in between, the
+    /// block is sealed again under the same number and its logs overwritten.
+    pub(crate) fn index_logs(&self, block: &Block) -> Result<()> {
+        let logs = match self.logs.as_ref() {
+            Some(logs) => logs,
+            None => return Ok(()),
+        };
+
+        let block_logs: Vec<Log> = block
+            .transactions
+            .iter()
+            .enumerate()
+            .map(|(i, transaction)| block_log(block, transaction, i as u32))
+            .collect();
+        let mut tx = logs.make_tx();
+        tx.put(&block.number.to_be_bytes(), &block_logs);
+        logs.commit(tx)?;
+
+        Ok(())
+    }
+}
+
+/// The log of the `index`-th transaction of `block`.
+fn block_log(block: &Block, transaction: &Transaction, index: u32) -> Log {
+    let hash = transaction.hash();
+    let (address, topics, data) = match transaction {
+        Transaction::Deposit(deposit) => (
+            deposit.token,
+            vec![deposit_topic(), deposit.id, deposit.recipient.into()],
+            amount_data(deposit.amount),
+        ),
+        Transaction::Withdraw(withdrawal) => (
+            withdrawal.token,
+            vec![exit_topic(), hash, withdrawal.account.into()],
+            amount_data(withdrawal.amount),
+        ),
+        Transaction::UpdateState(diff) => (
+            diff.user,
+            vec![state_update_topic(), diff.hash(), diff.user.into()],
+            diff.new_root.as_bytes().to_vec(),
+        ),
+    };
+
+    Log {
+        address,
+        topics,
+        data,
+        block_number: block.number,
+        block_hash: block.hash,
+        transaction_hash: hash,
+        transaction_index: index,
+        log_index: index,
+    }
+}
+
+fn amount_data(amount: U256) -> Vec<u8> {
+    let mut data = vec![0u8; 32];
+    amount.to_big_endian(&mut data);
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{deposit, executor, withdrawal};
+    use crate::ExecutorTrait;
+    use db::Db;
+    use kvdb_memorydb::InMemory;
+
+    #[test]
+    fn success_logs() {
+        let db: Db<InMemory> = Db::new(kvdb_memorydb::create(db::columns::NUM_COLUMNS), 0);
+        let executor = executor().with_log_index(db.with_column(db::columns::LOGS));
+        let alice = H160::random();
+
+        executor
+            .execute(&Transaction::Deposit(deposit(1, alice, 100)))
+            .unwrap();
+        let exit = withdrawal(alice, 30, 0);
+        executor.execute(&exit).unwrap();
+        assert!(executor.logs(1).is_empty());
+
+        let block = executor.seal_block(1).unwrap().unwrap();
+        let logs = executor.logs(1);
+        assert_eq!(logs.len(), 2);
+        assert_eq!(
+            logs[0].topics,
+            vec![deposit_topic(), H256::from_low_u64_be(1), alice.into()]
+        );
+        assert_eq!(U256::from_big_endian(&logs[0].data), U256::from(100));
+        assert_eq!(logs[0].block_hash, block.hash);
+        assert_eq!(logs[1].address, H160::zero());
+        assert_eq!(
+            logs[1].topics,
+            vec![exit_topic(), exit.hash(), alice.into()]
+        );
+        assert_eq!(logs[1].transaction_hash, exit.hash());
+        assert_eq!(logs[1].log_index, 1);
+    }
+
+    #[test]
+    fn success_logs_without_index() {
+        let executor = executor();
+        executor
+            .execute(&Transaction::Deposit(deposit(1, H160::random(), 100)))
+            .unwrap();
+        executor.seal_block(1).unwrap();
+
+        assert!(executor.logs(1).is_empty());
+    }
+}
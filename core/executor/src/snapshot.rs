@@ -0,0 +1,204 @@
+//! Snapshots of the node at its latest block, to bootstrap a replica without replaying the
+//! blocks before it.
+//!
+//! A snapshot is a frame of the `ExecutorSnapshot` followed by an `intmax_verkle::snapshot` of
+//! the state tree. The replica keeps the applied deposits and state diffs so that they are not
+//! applied twice, and the state tree of the block as its oldest version.
+use std::io::{Read, Write};
+
+use codec::{Decode, Encode};
+use db::{DBKey, Database};
+use ethereum_types::{H160, H256};
+use intmax_verkle::{snapshot, StorageError, VerkleStorage};
+
+use crate::error::{Error, Result};
+use crate::{prefixed_key, Block, Executor, Transaction, DEPOSIT_PREFIX, STATE_DIFF_PREFIX};
+
+/// Leaves of the state tree per chunk of a snapshot.
+const CHUNK_LEAVES: usize = 4096;
+/// The `ExecutorSnapshot` the node was bootstrapped from.
+const SNAPSHOT_KEY: &[u8] = b"snapshot";
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Encode, Decode)]
+struct ExecutorSnapshot {
+    block: Option<Block>,
+    /// Applied deposits, with their L1 block.
+    deposits: Vec<(H256, u64)>,
+    /// Applied state diffs, with their user.
+    state_diffs: Vec<(H256, H160)>,
+}
+
+impl<S: VerkleStorage, D: Database> Executor<S, D> {
+    /// Writes a snapshot of the latest block to `out`. Returns the block.
+    pub fn export_snapshot<W: Write>(&self, mut out: W) -> Result<Block> {
+        let _guard = self.write_lock.lock().expect("executor lock poisoned");
+
+        let block = self
+            .latest_block()
+            .ok_or(Error::UnknownBlockNumber { number: 1 })?;
+        let mut applied = self.imported_snapshot();
+        let from = applied.block.as_ref().map_or(1, |block| block.number + 1);
+        for transaction in (from..=block.number)
+            .filter_map(|number| self.block(number))
+            .flat_map(|block| block.transactions)
+        {
+            match transaction {
+                Transaction::Deposit(deposit) => {
+                    applied.deposits.push((deposit.id, deposit.l1_block))
+                }
+                Transaction::UpdateState(diff) => {
+                    applied.state_diffs.push((diff.hash(), diff.user))
+                }
+                Transaction::Withdraw(_) => (),
+            }
+        }
+        applied.block = Some(block.clone());
+
+        snapshot::write_frame(&mut out, &applied.encode())?;
+        snapshot::export(&self.state, block.number, CHUNK_LEAVES, out)?;
+        Ok(block)
+    }
+
+    /// Imports a snapshot written by `export_snapshot` into a node without blocks. Returns the
+    /// block of the snapshot, the latest one of the node afterwards.
+    pub fn import_snapshot<R: Read>(&self, mut input: R) -> Result<Block> {
+        let _guard = self.write_lock.lock().expect("executor lock poisoned");
+
+        let latest = self.latest_block_number();
+        if latest != 0 {
+            return Err(Error::StateNotEmpty { latest });
+        }
+        // Without blocks nor pending transactions, the state tree only holds what an import
+        // interrupted before its block was written left.
+        if self.pending_count() == 0 {
+            self.state.reset()?;
+        }
+        let applied = ExecutorSnapshot::decode(&mut &snapshot::read_frame(&mut input)?[..])
+            .map_err(|e| StorageError::InvalidSnapshot {
+                reason: e.to_string(),
+            })?;
+        let block = applied
+            .block
+            .clone()
+            .ok_or_else(|| StorageError::InvalidSnapshot {
+                reason: "no block".to_string(),
+            })?;
+
+        let header = snapshot::import(&self.state, input)?;
+        if header.number != block.number {
+            return Err(Error::SnapshotBlockMismatch {
+                number: block.number,
+                state: header.number,
+            });
+        }
+
+        let mut tx = self.db.make_tx();
+        self.put_block(&block, &mut tx);
+        for (id, l1_block) in applied.deposits.iter() {
+            tx.put(&prefixed_key(DEPOSIT_PREFIX, id.as_bytes()), l1_block);
+        }
+        for (hash, user) in applied.state_diffs.iter() {
+            tx.put(&prefixed_key(STATE_DIFF_PREFIX, hash.as_bytes()), user);
+        }
+        tx.put(&SNAPSHOT_KEY, &applied);
+        self.db.commit(tx)?;
+
+        Ok(block)
+    }
+
+    fn imported_snapshot(&self) -> ExecutorSnapshot {
+        self.db
+            .get(&DBKey::from_slice(SNAPSHOT_KEY))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state;
+    use crate::tests::{deposit, executor, withdrawal};
+    use crate::ExecutorTrait;
+    use ethereum_types::U256;
+
+    #[test]
+    fn success_import_snapshot() {
+        let executor = executor();
+        let alice = H160::random();
+        let token = H160::zero();
+        executor
+            .execute(&Transaction::Deposit(deposit(1, alice, 100)))
+            .unwrap();
+        executor.seal_block(1).unwrap();
+        executor.execute(&withdrawal(alice, 30, 0)).unwrap();
+        executor.seal_block(2).unwrap();
+        let mut out = vec![];
+        let block = executor.export_snapshot(&mut out).unwrap();
+        assert_eq!(block.number, 2);
+
+        let replica = crate::tests::executor();
+        assert_eq!(replica.import_snapshot(&out[..]).unwrap(), block);
+        assert_eq!(replica.latest_block(), Some(block.clone()));
+        assert_eq!(
+            replica.state_root().unwrap(),
+            executor.state_root().unwrap()
+        );
+        assert_eq!(
+            replica.state_root_at(2).unwrap(),
+            executor.state_root_at(2).unwrap()
+        );
+        assert_eq!(replica.balance(&alice, &token).unwrap(), U256::from(70));
+        assert_eq!(
+            replica.balance_at(&alice, &token, 2).unwrap(),
+            U256::from(70)
+        );
+        assert_eq!(replica.nonce_at(&alice, 2).unwrap(), U256::one());
+        assert!(matches!(
+            replica.balance_at(&alice, &token, 1),
+            Err(Error::StateVersionPruned { number: 1 })
+        ));
+        assert!(replica
+            .proof_at(2, &state::balance_key(&alice, &token))
+            .is_ok());
+        assert!(matches!(
+            replica.import_snapshot(&out[..]),
+            Err(Error::StateNotEmpty { latest: 2 })
+        ));
+
+        // An import whose state tree was written but not its block is imported again.
+        let interrupted = crate::tests::executor();
+        let mut input = &out[..];
+        snapshot::read_frame(&mut input).unwrap();
+        snapshot::import(&interrupted.state, input).unwrap();
+        assert_eq!(interrupted.latest_block_number(), 0);
+        assert_eq!(interrupted.import_snapshot(&out[..]).unwrap(), block);
+        assert_eq!(
+            interrupted.balance_at(&alice, &token, 2).unwrap(),
+            U256::from(70)
+        );
+
+        // The deposit is not applied twice, and the replica goes on from the block.
+        assert!(matches!(
+            replica.execute(&Transaction::Deposit(deposit(1, alice, 100))),
+            Err(Error::DuplicateDeposit { .. })
+        ));
+        replica.execute(&withdrawal(alice, 10, 1)).unwrap();
+        assert_eq!(replica.seal_block(3).unwrap().unwrap().number, 3);
+        assert_eq!(
+            replica.balance_at(&alice, &token, 3).unwrap(),
+            U256::from(60)
+        );
+        assert_eq!(
+            replica.balance_at(&alice, &token, 2).unwrap(),
+            U256::from(70)
+        );
+
+        // A snapshot of the replica still holds the deposits before its own snapshot.
+        let mut out = vec![];
+        replica.export_snapshot(&mut out).unwrap();
+        let next = crate::tests::executor();
+        next.import_snapshot(&out[..]).unwrap();
+        assert!(next.is_deposit_applied(&deposit(1, alice, 100).id));
+        assert_eq!(next.balance(&alice, &token).unwrap(), U256::from(60));
+    }
+}
@@ -0,0 +1,112 @@
+//! Keys of the account state in the `VerkleStorage`.
+//!
+//! The nonce, native token balance and `UserState` of an account are values of the stem of the
+//! account, so that they share its leaf. The balances of other tokens and the withdrawals each
+//! have a stem of their own.
+use ethereum_types::{H160, H256};
+use intmax_verkle::{Key, STEM_LEN};
+use tiny_keccak::{Hasher, Keccak};
+
+const ACCOUNT_PREFIX: &[u8] = b"account";
+const BALANCE_PREFIX: &[u8] = b"balance";
+const WITHDRAWAL_PREFIX: &[u8] = b"withdrawal";
+
+/// Suffixes of the values of the stem of an account.
+const NONCE_SUFFIX: u8 = 0;
+const NATIVE_BALANCE_SUFFIX: u8 = 1;
+const USER_STATE_SUFFIX: u8 = 2;
+
+pub(crate) fn keccak256(data: &[&[u8]]) -> H256 {
+    let mut hasher = Keccak::v256();
+    for d in data {
+        hasher.update(d);
+    }
+    let mut out = [0u8; 32];
+    hasher.finalize(&mut out);
+
+    H256(out)
+}
+
+/// The state tree key of `key`, its first 31 bytes being the stem.
+pub fn tree_key(key: &H256) -> Key {
+    Key::from(key.0)
+}
+
+/// The key of `suffix` in the stem of the first bytes of `hash`.
+fn stem_key(hash: &H256, suffix: u8) -> H256 {
+    let stem = hash[..STEM_LEN]
+        .try_into()
+        .expect("a hash is longer than a stem");
+    H256(*Key::new(stem, suffix).as_bytes())
+}
+
+fn account_key(account: &H160, suffix: u8) -> H256 {
+    stem_key(&keccak256(&[ACCOUNT_PREFIX, account.as_bytes()]), suffix)
+}
+
+/// Key of the balance of `token` held by `account`. The zero address is the L1 native token.
+pub fn balance_key(account: &H160, token: &H160) -> H256 {
+    if token.is_zero() {
+        return account_key(account, NATIVE_BALANCE_SUFFIX);
+    }
+
+    stem_key(
+        &keccak256(&[BALANCE_PREFIX, account.as_bytes(), token.as_bytes()]),
+        0,
+    )
+}
+
+/// Key of the nonce of `account`, the number of its withdrawals so far.
+pub fn nonce_key(account: &H160) -> H256 {
+    account_key(account, NONCE_SUFFIX)
+}
+
+/// Key of the withdrawal leaf of the `Transaction::Withdraw` whose hash is `id`. Exit proofs
+/// prove this leaf.
+pub fn withdrawal_key(id: &H256) -> H256 {
+    stem_key(&keccak256(&[WITHDRAWAL_PREFIX, id.as_bytes()]), 0)
+}
+
+/// Key of the `UserState` of `user`.
+pub fn user_state_key(user: &H160) -> H256 {
+    account_key(user, USER_STATE_SUFFIX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn success_balance_key_is_unique() {
+        let a = H160::from_low_u64_be(1);
+        let b = H160::from_low_u64_be(2);
+
+        assert_eq!(balance_key(&a, &b), balance_key(&a, &b));
+        assert_ne!(balance_key(&a, &b), balance_key(&b, &a));
+        assert_ne!(balance_key(&a, &a), balance_key(&a, &b));
+        assert_ne!(nonce_key(&a), nonce_key(&b));
+    }
+
+    #[test]
+    fn success_account_values_share_stem() {
+        let (a, token) = (H160::from_low_u64_be(1), H160::from_low_u64_be(2));
+        let stem = |key: H256| tree_key(&key).stem().to_vec();
+
+        let nonce = stem(nonce_key(&a));
+        assert_eq!(nonce, stem(balance_key(&a, &H160::zero())));
+        assert_eq!(nonce, stem(user_state_key(&a)));
+        assert_ne!(nonce_key(&a), balance_key(&a, &H160::zero()));
+        assert_ne!(nonce_key(&a), user_state_key(&a));
+        assert_ne!(nonce, stem(balance_key(&a, &token)));
+        assert_ne!(nonce, stem(nonce_key(&token)));
+    }
+
+    #[test]
+    fn success_withdrawal_key_is_unique() {
+        let a = H256::from_low_u64_be(1);
+        let b = H256::from_low_u64_be(2);
+
+        assert_eq!(withdrawal_key(&a), withdrawal_key(&a));
+        assert_ne!(withdrawal_key(&a), withdrawal_key(&b));
+    }
+}
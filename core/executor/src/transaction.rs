@@ -0,0 +1,87 @@
+use codec::{Decode, Encode};
+use ethereum_types::{H160, H256, U256};
+use intmax_zk::StateDiff;
+
+use crate::state::keccak256;
+
+/// Funds that entered the rollup on L1.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct Deposit {
+    /// Unique id of the L1 log the deposit comes from.
+    pub id: H256,
+    /// L1 block the deposit was included in.
+    pub l1_block: u64,
+    pub recipient: H160,
+    /// The zero address is the L1 native token.
+    pub token: H160,
+    pub amount: U256,
+}
+
+/// Funds leaving the rollup: burnt on L2, then claimable by `account` on L1.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct Withdrawal {
+    pub account: H160,
+    /// The zero address is the L1 native token.
+    pub token: H160,
+    pub amount: U256,
+    /// Must be the current nonce of `account`.
+    pub nonce: U256,
+}
+
+/// Root of the state a user holds, as of its latest accepted transition.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Encode, Decode)]
+pub struct UserState {
+    pub root: H256,
+    /// Number of the accepted transitions.
+    pub nonce: u64,
+}
+
+/// Transactions the executor applies to the L2 state.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub enum Transaction {
+    Deposit(Deposit),
+    Withdraw(Withdrawal),
+    /// A user state transition whose proof was accepted by the su-receiver.
+    UpdateState(StateDiff),
+}
+
+impl Transaction {
+    pub fn hash(&self) -> H256 {
+        keccak256(&[&self.encode()])
+    }
+}
+
+/// Transactions sealed together. Batch `n` committed to L1 is block `n`, starting from 1.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct Block {
+    pub number: u64,
+    pub hash: H256,
+    pub parent_hash: H256,
+    /// Unix time in seconds.
+    pub timestamp: u64,
+    /// Root of the state tree at the end of the block, kept after its version is pruned.
+    pub state_root: H256,
+    pub transactions: Vec<Transaction>,
+}
+
+impl Block {
+    pub fn new(
+        number: u64,
+        parent_hash: H256,
+        timestamp: u64,
+        state_root: H256,
+        transactions: Vec<Transaction>,
+    ) -> Self {
+        let tx_hashes: Vec<H256> = transactions.iter().map(Transaction::hash).collect();
+        let hash = keccak256(&[&(number, parent_hash, timestamp, state_root, tx_hashes).encode()]);
+
+        Block {
+            number,
+            hash,
+            parent_hash,
+            timestamp,
+            state_root,
+            transactions,
+        }
+    }
+}
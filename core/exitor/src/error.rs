@@ -0,0 +1,171 @@
+use ethereum_types::{H160, H256};
+use executor::Error as ExecutorError;
+use jsonrpc_core as rpc;
+
+/// Exitor Result type.
+pub type Result<T> = std::result::Result<T, Error>;
+
+pub fn internal<E: ::std::fmt::Debug>(e: E) -> rpc::Error {
+    jsonrpc_core::Error {
+        code: rpc::ErrorCode::InternalError,
+        message: "Unknown error occurred".into(),
+        data: Some(format!("{:?}", e).into()),
+    }
+}
+
+/// Exitor errors.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Error from the executor.
+    #[error("Executor error: {}", .0)]
+    Executor(#[from] ExecutorError),
+
+    /// Error from the database.
+    #[error("Database error: {}", .0)]
+    Db(#[from] std::io::Error),
+
+    /// If no exit has been requested with the id.
+    #[error("Exit({}) is unknown", .id)]
+    UnknownExit {
+        /// Exit id
+        id: H256,
+    },
+
+    /// If the exit has not been sealed into a block yet.
+    #[error("Exit({}) is not included in a block yet", .id)]
+    NotIncluded {
+        /// Exit id
+        id: H256,
+    },
+
+    /// If the batch of the exit has not been finalized on L1 yet.
+    #[error("Exit({}) is in batch {}, which is not finalized on L1 yet", .id, .batch)]
+    NotFinalized {
+        /// Exit id
+        id: H256,
+        /// Batch including the exit
+        batch: u64,
+    },
+
+    /// If the exit is not in a withdrawal batch yet.
+    #[error("Exit({}) is not in a withdrawal batch yet", .id)]
+    NotBatched {
+        /// Exit id
+        id: H256,
+    },
+
+    /// If the root of the withdrawal batch of the exit has not been submitted to L1 yet.
+    #[error(
+        "Exit({}) is in withdrawal batch {}, which is not submitted to L1 yet",
+        .id,
+        .withdrawal_batch
+    )]
+    NotSubmitted {
+        /// Exit id
+        id: H256,
+        /// Withdrawal batch including the exit
+        withdrawal_batch: u64,
+    },
+
+    /// If there is no withdrawal batch with the number.
+    #[error("Withdrawal batch {} is unknown", .number)]
+    UnknownWithdrawalBatch {
+        /// Withdrawal batch number
+        number: u64,
+    },
+
+    /// If the local state of a batch is not the state committed on L1.
+    #[error(
+        "Local state root of batch {} is {}, but {} is committed on L1",
+        .batch,
+        .local,
+        .committed
+    )]
+    StateRootMismatch {
+        /// Batch number
+        batch: u64,
+        /// Local state root of the batch
+        local: H256,
+        /// State root committed on L1
+        committed: H256,
+    },
+
+    /// If the exit request is not signed by its account.
+    #[error("Exit request is not signed by {}", .account)]
+    InvalidSignature {
+        /// Account to exit
+        account: H160,
+    },
+
+    /// If there is no balance to exit.
+    #[error("{} has no balance of token {}", .account, .token)]
+    NothingToExit {
+        /// Account to exit
+        account: H160,
+        /// Token to exit
+        token: H160,
+    },
+}
+
+impl From<Error> for rpc::Error {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Executor(ExecutorError::InvalidNonce { .. })
+            | Error::Executor(ExecutorError::InsufficientBalance { .. })
+            | Error::UnknownExit { .. }
+            | Error::NotIncluded { .. }
+            | Error::NotFinalized { .. }
+            | Error::NotBatched { .. }
+            | Error::NotSubmitted { .. }
+            | Error::NothingToExit { .. }
+            | Error::InvalidSignature { .. } => rpc::Error {
+                code: rpc::ErrorCode::InvalidParams,
+                message: format!("{}", e),
+                data: None,
+            },
+            e => internal(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethereum_types::U256;
+
+    #[test]
+    fn success_type_conversion_into_core_all() {
+        let e: rpc::Error = Error::NotFinalized {
+            id: H256::zero(),
+            batch: 3,
+        }
+        .into();
+        assert_eq!(e.code, rpc::ErrorCode::InvalidParams);
+        assert_eq!(
+            e.message,
+            "Exit(0x0000…0000) is in batch 3, which is not finalized on L1 yet"
+        );
+
+        let e: rpc::Error = Error::Executor(ExecutorError::InsufficientBalance {
+            account: H160::zero(),
+            token: H160::zero(),
+        })
+        .into();
+        assert_eq!(e.code, rpc::ErrorCode::InvalidParams);
+
+        let e: rpc::Error = Error::Executor(ExecutorError::BalanceOverflow {
+            account: H160::zero(),
+            token: H160::zero(),
+        })
+        .into();
+        assert_eq!(e.code, rpc::ErrorCode::InternalError);
+
+        let e: rpc::Error = Error::Executor(ExecutorError::InvalidNonce {
+            account: H160::zero(),
+            expected: U256::one(),
+            actual: U256::zero(),
+        })
+        .into();
+        assert_eq!(e.code, rpc::ErrorCode::InvalidParams);
+    }
+}
@@ -0,0 +1,214 @@
+//! Exits without the operator: a user proves their balance against the state committed on L1
+//! and calls `Rollup.forceExit` themselves.
+use codec::Decode;
+use db::{DBKey, Database};
+use ethereum_types::{H160, H256, U256};
+use executor::{state, Error as ExecutorError};
+use intmax_verkle::{Proof, StorageError, VerkleStorage};
+
+use crate::error::{Error, Result};
+use crate::{prefixed_key, Exitor};
+
+const COMMITTED_PREFIX: &[u8] = b"committed";
+const LATEST_COMMITTED_KEY: &[u8] = b"latest_committed";
+
+/// The latest batch committed on L1, as read from `Rollup.latestBatchNumber()` and
+/// `Rollup.stateRoots(number)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CommittedBatch {
+    pub number: u64,
+    pub state_root: H256,
+}
+
+/// Everything `Rollup.forceExit` needs to release a balance on L1.
+pub struct ForcedExitProof {
+    pub batch: CommittedBatch,
+    pub account: H160,
+    pub token: H160,
+    pub amount: U256,
+    /// Inclusion proof of `executor::state::balance_key(account, token)`.
+    pub proof: Proof,
+}
+
+impl<S: VerkleStorage, D: Database> Exitor<S, D> {
+    /// The batch `number` committed on L1, once recorded.
+    pub fn committed_batch(&self, number: u64) -> Option<CommittedBatch> {
+        let state_root = self
+            .db
+            .get(&prefixed_key(COMMITTED_PREFIX, &number.to_be_bytes()))?;
+
+        Some(CommittedBatch { number, state_root })
+    }
+
+    /// The latest batch committed on L1 recorded so far.
+    pub fn latest_committed_batch(&self) -> Option<u64> {
+        self.db.get(&DBKey::from_slice(LATEST_COMMITTED_KEY))
+    }
+
+    /// Records the state root of a batch committed on L1.
+    pub fn record_committed_batch(&self, batch: &CommittedBatch) -> Result<()> {
+        let mut tx = self.db.make_tx();
+        tx.put(
+            &prefixed_key(COMMITTED_PREFIX, &batch.number.to_be_bytes()),
+            &batch.state_root,
+        );
+        if !matches!(self.latest_committed_batch(), Some(latest) if latest >= batch.number) {
+            tx.put(&LATEST_COMMITTED_KEY, &batch.number);
+        }
+        self.db.commit(tx)?;
+
+        Ok(())
+    }
+
+    /// Proves the whole balance of `token` held by `account` in the local state of `batch`,
+    /// which must have the state root committed on L1.
+    pub fn forced_exit_proof(
+        &self,
+        account: &H160,
+        token: &H160,
+        batch: &CommittedBatch,
+    ) -> Result<ForcedExitProof> {
+        let local = self.executor.state_root_at(batch.number)?;
+        if local != batch.state_root {
+            return Err(Error::StateRootMismatch {
+                batch: batch.number,
+                local,
+                committed: batch.state_root,
+            });
+        }
+
+        let proof = self
+            .executor
+            .proof_at(batch.number, &state::balance_key(account, token))?;
+        let amount = match &proof.value {
+            Some(value) => U256::decode(&mut &value[..])
+                .map_err(|_| ExecutorError::Storage(StorageError::Decode))?,
+            None => U256::zero(),
+        };
+        if amount.is_zero() {
+            return Err(Error::NothingToExit {
+                account: *account,
+                token: *token,
+            });
+        }
+
+        Ok(ForcedExitProof {
+            batch: *batch,
+            account: *account,
+            token: *token,
+            amount,
+            proof,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::tests::account;
+    use crate::tests::{exitor, request_exit, withdrawal, TestExitor};
+    use codec::Encode;
+    use executor::Withdrawal;
+    use intmax_verkle::verifier::{Scheme, Verkle};
+
+    /// Batch `number` as committed from the local state.
+    fn committed(exitor: &TestExitor, number: u64) -> CommittedBatch {
+        CommittedBatch {
+            number,
+            state_root: exitor.executor.state_root_at(number).unwrap(),
+        }
+    }
+
+    #[test]
+    fn success_forced_exit_proof() {
+        let (key, alice) = account();
+        let (executor, exitor) = exitor(alice);
+        let batch = committed(&exitor, 1);
+
+        // The state moves on after the committed batch.
+        request_exit(&exitor, &key, withdrawal(alice, 10)).unwrap();
+        executor.seal_block(2).unwrap();
+        request_exit(
+            &exitor,
+            &key,
+            Withdrawal {
+                nonce: 1.into(),
+                ..withdrawal(alice, 10)
+            },
+        )
+        .unwrap();
+
+        let proof = exitor
+            .forced_exit_proof(&alice, &H160::zero(), &batch)
+            .expect("proof ok.");
+        assert_eq!(proof.batch, batch);
+        assert_eq!(proof.account, alice);
+        assert_eq!(proof.amount, U256::from(100));
+        assert_eq!(proof.proof.value, Some(U256::from(100).encode()));
+        Verkle::verify(
+            &batch.state_root.0,
+            &proof.proof,
+            &state::balance_key(&alice, &H160::zero()),
+            &proof.amount,
+        )
+        .expect("verify ok.");
+
+        let proof = exitor
+            .forced_exit_proof(&alice, &H160::zero(), &committed(&exitor, 2))
+            .expect("proof ok.");
+        assert_eq!(proof.amount, U256::from(90));
+    }
+
+    #[test]
+    fn success_record_committed_batch() {
+        let (_, exitor) = exitor(H160::random());
+        let batch = |number| CommittedBatch {
+            number,
+            state_root: H256::repeat_byte(1),
+        };
+        assert_eq!(exitor.committed_batch(1), None);
+        assert_eq!(exitor.latest_committed_batch(), None);
+
+        exitor.record_committed_batch(&batch(2)).unwrap();
+        exitor.record_committed_batch(&batch(1)).unwrap();
+        assert_eq!(exitor.committed_batch(1), Some(batch(1)));
+        assert_eq!(exitor.committed_batch(2), Some(batch(2)));
+        assert_eq!(exitor.latest_committed_batch(), Some(2));
+    }
+
+    #[test]
+    fn fail_forced_exit_proof() {
+        let (_, alice) = account();
+        let (_, exitor) = exitor(alice);
+
+        let err = exitor
+            .forced_exit_proof(&H160::random(), &H160::zero(), &committed(&exitor, 1))
+            .err()
+            .unwrap();
+        assert!(matches!(err, Error::NothingToExit { .. }));
+
+        let forged = CommittedBatch {
+            number: 1,
+            state_root: H256::repeat_byte(1),
+        };
+        let err = exitor
+            .forced_exit_proof(&alice, &H160::zero(), &forged)
+            .err()
+            .unwrap();
+        assert!(matches!(err, Error::StateRootMismatch { batch: 1, .. }));
+
+        // Not synced up to the batch yet.
+        let ahead = CommittedBatch {
+            number: 2,
+            ..forged
+        };
+        let err = exitor
+            .forced_exit_proof(&alice, &H160::zero(), &ahead)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err,
+            Error::Executor(ExecutorError::UnknownBlockNumber { number: 2 })
+        ));
+    }
+}
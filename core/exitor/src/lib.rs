@@ -1,8 +1,384 @@
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use codec::{Decode, Encode};
+use db::{DBKey, Database};
+use ethereum_types::{H160, H256};
+use executor::{state, Executor, ExecutorTrait, Transaction, Withdrawal};
+use intmax_verkle::{Proof, VerkleStorage};
+use tracing::info;
+
+pub use error::{Error, Result};
+pub use forced_exit::{CommittedBatch, ForcedExitProof};
+pub use status::ExitStatus;
+pub use withdrawal_batch::{ClaimProof, ClaimStatus, WithdrawalBatch};
+
+mod error;
+mod forced_exit;
+pub mod merkle;
+pub mod signature;
+mod status;
+mod withdrawal_batch;
+
+const EXIT_PREFIX: &[u8] = b"exit";
+const FINALIZED_BATCH_KEY: &[u8] = b"finalized_batch";
+/// Exits not in a withdrawal batch yet.
+const QUEUED_KEY: &[u8] = b"queued";
+
+/// An exit requested by a user.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct Exit {
+    /// Hash of the `Transaction::Withdraw` burning the funds.
+    pub id: H256,
+    pub withdrawal: Withdrawal,
+    /// Unix time in seconds.
+    pub requested_at: u64,
+}
+
+/// Everything `Rollup.withdraw` needs to release an exit on L1.
+pub struct ExitProof {
+    pub id: H256,
+    /// Batch, i.e. L2 block, including the exit.
+    pub batch_number: u64,
+    /// State root of the batch, `Rollup.stateRoots(batch_number)`.
+    pub state_root: H256,
+    pub withdrawal: Withdrawal,
+    /// Inclusion proof of `executor::state::withdrawal_key(id)` under `state_root`.
+    pub proof: Proof,
+}
+
+pub trait ExitorTrait {
+    /// Burns the withdrawn funds on L2 and returns the exit id.
+    /// `signature` is the account's signature of `signature::signing_hash` of `withdrawal` in
+    /// the domain of the exitor.
+    fn request_exit(&self, withdrawal: Withdrawal, signature: &[u8]) -> Result<H256>;
+
+    /// Proof of the exit `id`, available once its batch is finalized on L1.
+    fn exit_proof(&self, id: &H256) -> Result<ExitProof>;
+
+    /// Records that every batch up to `batch` is finalized on L1.
+    fn finalize_batch(&self, batch: u64) -> Result<()>;
+
+    /// Proof to claim the exit `id` on L1, available once its withdrawal batch is submitted.
+    fn claim_proof(&self, id: &H256) -> Result<ClaimProof>;
+
+    fn exit_status(&self, id: &H256) -> Result<ExitStatus>;
+
+    /// Statuses of the exits requested by `account`, oldest first.
+    fn list_exits(&self, account: &H160) -> Result<Vec<ExitStatus>>;
+}
+
+/// Turns exit requests into withdrawals and proves them once they are final.
+/// Exit requests, withdrawal batches and the latest finalized batch live in `D`,
+/// the `db::columns::EXITOR` column.
+pub struct Exitor<S: VerkleStorage, D: Database> {
+    executor: Arc<Executor<S, D>>,
+    db: D,
+    /// Deployment the exit requests are signed for.
+    domain: signature::Domain,
+    write_lock: Mutex<()>,
+}
+
+impl<S: VerkleStorage, D: Database> Exitor<S, D> {
+    pub fn new(executor: Arc<Executor<S, D>>, db: D) -> Self {
+        Exitor {
+            executor,
+            db,
+            domain: signature::Domain::default(),
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    /// Accepts the exit requests signed for `domain` only.
+    pub fn with_domain(mut self, domain: signature::Domain) -> Self {
+        self.domain = domain;
+        self
+    }
+
+    pub fn exit(&self, id: &H256) -> Option<Exit> {
+        self.db.get(&prefixed_key(EXIT_PREFIX, id.as_bytes()))
+    }
+
+    /// Exits not in a withdrawal batch yet, oldest first.
+    pub fn queued_exits(&self) -> Vec<H256> {
+        self.db
+            .get(&DBKey::from_slice(QUEUED_KEY))
+            .unwrap_or_default()
+    }
+
+    /// The latest batch finalized on L1.
+    pub fn finalized_batch(&self) -> Option<u64> {
+        self.db.get(&DBKey::from_slice(FINALIZED_BATCH_KEY))
+    }
+}
+
+impl<S: VerkleStorage, D: Database> ExitorTrait for Exitor<S, D> {
+    fn request_exit(&self, withdrawal: Withdrawal, signature: &[u8]) -> Result<H256> {
+        let signer = signature::recover(
+            &signature::signing_hash(&self.domain, &withdrawal),
+            signature,
+        );
+        if signer != Some(withdrawal.account) {
+            return Err(Error::InvalidSignature {
+                account: withdrawal.account,
+            });
+        }
+
+        let _guard = self.write_lock.lock().expect("exitor lock poisoned");
+
+        let transaction = Transaction::Withdraw(withdrawal.clone());
+        let id = transaction.hash();
+        self.executor.execute(&transaction)?;
+
+        let exit = Exit {
+            id,
+            withdrawal,
+            requested_at: now(),
+        };
+        let mut queued = self.queued_exits();
+        queued.push(id);
+        let account = exit.withdrawal.account;
+        let mut account_exits = self.account_exits(&account);
+        account_exits.push(id);
+        let mut tx = self.db.make_tx();
+        tx.put(&prefixed_key(EXIT_PREFIX, id.as_bytes()), &exit);
+        tx.put(&QUEUED_KEY, &queued);
+        tx.put(
+            &prefixed_key(status::ACCOUNT_EXITS_PREFIX, account.as_bytes()),
+            &account_exits,
+        );
+        self.db.commit(tx)?;
+
+        info!("requested exit {:?}", id);
+        Ok(id)
+    }
+
+    fn exit_proof(&self, id: &H256) -> Result<ExitProof> {
+        let exit = self.exit(id).ok_or(Error::UnknownExit { id: *id })?;
+        let batch_number = self
+            .executor
+            .transaction_block(id)
+            .ok_or(Error::NotIncluded { id: *id })?;
+        match self.finalized_batch() {
+            Some(finalized) if finalized >= batch_number => (),
+            _ => {
+                return Err(Error::NotFinalized {
+                    id: *id,
+                    batch: batch_number,
+                })
+            }
+        }
+
+        Ok(ExitProof {
+            id: *id,
+            batch_number,
+            state_root: self.executor.state_root_at(batch_number)?,
+            withdrawal: exit.withdrawal,
+            proof: self
+                .executor
+                .proof_at(batch_number, &state::withdrawal_key(id))?,
+        })
+    }
+
+    fn finalize_batch(&self, batch: u64) -> Result<()> {
+        if matches!(self.finalized_batch(), Some(finalized) if finalized >= batch) {
+            return Ok(());
+        }
+
+        let mut tx = self.db.make_tx();
+        tx.put(&FINALIZED_BATCH_KEY, &batch);
+        self.db.commit(tx)?;
+
+        info!("batch {} is finalized on L1", batch);
+        Ok(())
+    }
+
+    fn claim_proof(&self, id: &H256) -> Result<ClaimProof> {
+        self.prove_claim(id)
+    }
+
+    fn exit_status(&self, id: &H256) -> Result<ExitStatus> {
+        self.status(id)
+    }
+
+    fn list_exits(&self, account: &H160) -> Result<Vec<ExitStatus>> {
+        self.account_exits(account)
+            .iter()
+            .map(|id| self.status(id))
+            .collect()
+    }
+}
+
+fn prefixed_key(prefix: &[u8], id: &[u8]) -> DBKey {
+    let mut key = DBKey::from_slice(prefix);
+    key.extend_from_slice(id);
+    key
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time is after the unix epoch")
+        .as_secs()
+}
+
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
+    use super::*;
+    use db::Db;
+    use ethereum_types::U256;
+    use executor::Deposit;
+    use intmax_verkle::verifier::{Scheme, Verkle};
+    use intmax_verkle::VerkleTree;
+    use kvdb_memorydb::InMemory;
+
+    use crate::signature::tests::{account, domain};
+    use crate::signature::{sign, Domain, SecretKey};
+
+    type TestExecutor = Executor<VerkleTree<Db<InMemory>>, Db<InMemory>>;
+    pub type TestExitor = Exitor<VerkleTree<Db<InMemory>>, Db<InMemory>>;
+
+    pub fn exitor(alice: H160) -> (Arc<TestExecutor>, TestExitor) {
+        let db = Db::new(kvdb_memorydb::create(db::columns::NUM_COLUMNS), 0);
+        let executor = Arc::new(Executor::new(
+            VerkleTree::new(db.with_column(db::columns::STATE)),
+            db.with_column(db::columns::EXECUTOR),
+        ));
+        executor
+            .execute(&Transaction::Deposit(Deposit {
+                id: H256::from_low_u64_be(1),
+                l1_block: 1,
+                recipient: alice,
+                token: H160::zero(),
+                amount: U256::from(100),
+            }))
+            .expect("deposit ok.");
+        executor.seal_block(1).expect("seal ok.");
+
+        let exitor = Exitor::new(executor.clone(), db.with_column(db::columns::EXITOR))
+            .with_domain(domain());
+        (executor, exitor)
+    }
+
+    pub fn withdrawal(account: H160, amount: u64) -> Withdrawal {
+        Withdrawal {
+            account,
+            token: H160::zero(),
+            amount: U256::from(amount),
+            nonce: U256::zero(),
+        }
+    }
+
+    /// Requests the exit signed by `key`.
+    pub fn request_exit(
+        exitor: &TestExitor,
+        key: &SecretKey,
+        withdrawal: Withdrawal,
+    ) -> Result<H256> {
+        let signature = sign(key, &domain(), &withdrawal);
+        exitor.request_exit(withdrawal, &signature)
+    }
+
+    #[test]
+    fn success_exit_proof() {
+        let (key, alice) = account();
+        let (executor, exitor) = exitor(alice);
+
+        let id = request_exit(&exitor, &key, withdrawal(alice, 40)).expect("exit ok.");
+        assert_eq!(
+            executor.balance(&alice, &H160::zero()).unwrap(),
+            U256::from(60)
+        );
+        assert_eq!(exitor.exit(&id).unwrap().withdrawal, withdrawal(alice, 40));
+        assert!(matches!(
+            exitor.exit_proof(&id),
+            Err(Error::NotIncluded { .. })
+        ));
+
+        let block = executor.seal_block(2).unwrap().unwrap();
+        assert!(matches!(
+            exitor.exit_proof(&id),
+            Err(Error::NotFinalized { batch: 2, .. })
+        ));
+
+        exitor.finalize_batch(block.number).expect("finalize ok.");
+        let proof = exitor.exit_proof(&id).expect("proof ok.");
+        assert_eq!(proof.batch_number, 2);
+        assert_eq!(proof.withdrawal, withdrawal(alice, 40));
+        assert!(proof.proof.value.is_some());
+    }
+
+    #[test]
+    fn success_exit_proof_is_against_the_batch_root() {
+        let (key, alice) = account();
+        let (executor, exitor) = exitor(alice);
+        let id = request_exit(&exitor, &key, withdrawal(alice, 40)).unwrap();
+        executor.seal_block(2).unwrap();
+        exitor.finalize_batch(2).unwrap();
+        let batch_root = executor.state_root().unwrap();
+
+        // The state moves on after the batch.
+        executor
+            .execute(&Transaction::Deposit(Deposit {
+                id: H256::from_low_u64_be(2),
+                l1_block: 2,
+                recipient: alice,
+                token: H160::zero(),
+                amount: U256::from(1),
+            }))
+            .unwrap();
+        executor.seal_block(3).unwrap();
+        assert_ne!(executor.state_root().unwrap(), batch_root);
+
+        let proof = exitor.exit_proof(&id).expect("proof ok.");
+        assert_eq!(proof.state_root, batch_root);
+        Verkle::verify(
+            &batch_root.0,
+            &proof.proof,
+            &state::withdrawal_key(&id),
+            &proof.withdrawal,
+        )
+        .expect("verify ok.");
+    }
+
+    #[test]
+    fn fail_request_exit() {
+        let (key, alice) = account();
+        let (_, exitor) = exitor(alice);
+
+        let err = request_exit(&exitor, &key, withdrawal(alice, 101)).unwrap_err();
+        assert!(matches!(err, Error::Executor(_)));
+
+        // Signed by someone else.
+        let (mallory, _) = account();
+        let err = request_exit(&exitor, &mallory, withdrawal(alice, 40)).unwrap_err();
+        assert!(matches!(err, Error::InvalidSignature { .. }));
+        let err = exitor
+            .request_exit(withdrawal(alice, 40), &[0u8; 65])
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidSignature { .. }));
+        // Signed for another deployment.
+        let other = Domain {
+            chain_id: 1,
+            ..domain()
+        };
+        let signature = sign(&key, &other, &withdrawal(alice, 40));
+        let err = exitor
+            .request_exit(withdrawal(alice, 40), &signature)
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidSignature { .. }));
+
+        let err = exitor.exit_proof(&H256::random()).err().unwrap();
+        assert!(matches!(err, Error::UnknownExit { .. }));
+    }
+
     #[test]
-    fn it_works() {
-        let result = 2 + 2;
-        assert_eq!(result, 4);
+    fn success_finalize_batch_never_goes_back() {
+        let (_, exitor) = exitor(H160::random());
+        assert_eq!(exitor.finalized_batch(), None);
+
+        exitor.finalize_batch(3).unwrap();
+        exitor.finalize_batch(2).unwrap();
+        assert_eq!(exitor.finalized_batch(), Some(3));
     }
 }
@@ -0,0 +1,129 @@
+//! Keccak Merkle tree of a withdrawal batch, verifiable by OpenZeppelin's `MerkleProof`:
+//! pairs are hashed in sorted order, and the last node of an odd level is carried up as is.
+use ethereum_types::{H160, H256, U256};
+use tiny_keccak::{Hasher, Keccak};
+
+fn keccak256(data: &[&[u8]]) -> H256 {
+    let mut hasher = Keccak::v256();
+    for d in data {
+        hasher.update(d);
+    }
+    let mut out = [0u8; 32];
+    hasher.finalize(&mut out);
+
+    H256(out)
+}
+
+/// `keccak256(abi.encodePacked(id, recipient, token, amount))`
+pub fn leaf(id: &H256, recipient: &H160, token: &H160, amount: &U256) -> H256 {
+    let mut amount_bytes = [0u8; 32];
+    amount.to_big_endian(&mut amount_bytes);
+
+    keccak256(&[
+        id.as_bytes(),
+        recipient.as_bytes(),
+        token.as_bytes(),
+        &amount_bytes,
+    ])
+}
+
+fn hash_pair(a: &H256, b: &H256) -> H256 {
+    if a <= b {
+        keccak256(&[a.as_bytes(), b.as_bytes()])
+    } else {
+        keccak256(&[b.as_bytes(), a.as_bytes()])
+    }
+}
+
+fn next_level(level: &[H256]) -> Vec<H256> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [a, b] => hash_pair(a, b),
+            [a] => *a,
+            _ => unreachable!("chunks(2) yields one or two nodes"),
+        })
+        .collect()
+}
+
+/// Root of `leaves`. The root of no leaves is zero.
+pub fn root(leaves: &[H256]) -> H256 {
+    if leaves.is_empty() {
+        return H256::zero();
+    }
+
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+
+    level[0]
+}
+
+/// Siblings from the leaf at `index` up to the root.
+pub fn proof(leaves: &[H256], index: usize) -> Vec<H256> {
+    let mut siblings = Vec::new();
+    let mut level = leaves.to_vec();
+    let mut index = index;
+    while level.len() > 1 {
+        if let Some(sibling) = level.get(index ^ 1) {
+            siblings.push(*sibling);
+        }
+        level = next_level(&level);
+        index /= 2;
+    }
+
+    siblings
+}
+
+pub fn verify(root: &H256, leaf: &H256, proof: &[H256]) -> bool {
+    let computed = proof
+        .iter()
+        .fold(*leaf, |node, sibling| hash_pair(&node, sibling));
+
+    computed == *root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(n: u64) -> Vec<H256> {
+        (0..n).map(H256::from_low_u64_be).collect()
+    }
+
+    #[test]
+    fn success_proof_of_every_leaf() {
+        for n in 1..=9 {
+            let leaves = leaves(n);
+            let root = root(&leaves);
+            for (i, leaf) in leaves.iter().enumerate() {
+                assert!(verify(&root, leaf, &proof(&leaves, i)), "{} of {}", i, n);
+            }
+        }
+    }
+
+    #[test]
+    fn fail_verify_other_leaf() {
+        let leaves = leaves(5);
+        let root = root(&leaves);
+
+        assert!(!verify(&root, &leaves[1], &proof(&leaves, 2)));
+        assert!(!verify(&root, &H256::random(), &proof(&leaves, 2)));
+        assert_eq!(super::root(&[]), H256::zero());
+        assert_eq!(super::root(&leaves[..1]), leaves[0]);
+    }
+
+    #[test]
+    fn success_leaf_is_abi_encode_packed() {
+        // 32 + 20 + 20 + 32 bytes, without padding.
+        assert_eq!(
+            leaf(&H256::zero(), &H160::zero(), &H160::zero(), &U256::zero()),
+            keccak256(&[&[0u8; 104]])
+        );
+        assert_ne!(
+            leaf(&H256::zero(), &H160::zero(), &H160::zero(), &U256::one()),
+            keccak256(&[&[0u8; 104]])
+        );
+    }
+}
@@ -0,0 +1,193 @@
+//! Exit requests are signed by their account as the EIP-712 typed data
+//! `Withdrawal(address account,address token,uint256 amount,uint256 nonce)` of the `Domain` of
+//! the deployment, e.g. with `eth_signTypedData_v4`, as 65 bytes `r ‖ s ‖ v`.
+use ethereum_types::{H160, H256, U256};
+use executor::Withdrawal;
+use secp256k1::recovery::{RecoverableSignature, RecoveryId};
+pub use secp256k1::SecretKey;
+use secp256k1::{Message, PublicKey, Secp256k1};
+use tiny_keccak::{Hasher, Keccak};
+
+pub const SIGNATURE_LEN: usize = 65;
+
+const DOMAIN_TYPE: &[u8] =
+    b"EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+const DOMAIN_NAME: &[u8] = b"Intmax";
+const DOMAIN_VERSION: &[u8] = b"1";
+const WITHDRAWAL_TYPE: &[u8] =
+    b"Withdrawal(address account,address token,uint256 amount,uint256 nonce)";
+
+/// The deployment an exit request is signed for, so that it cannot be replayed on another one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Domain {
+    /// Chain id of the rollup, returned by `eth_chainId`.
+    pub chain_id: u64,
+    /// Address of the `Rollup` contract on L1.
+    pub verifying_contract: H160,
+}
+
+impl Domain {
+    /// EIP-712 domain separator of name `Intmax` and version `1`.
+    pub fn separator(&self) -> H256 {
+        keccak256(&[
+            keccak256(&[DOMAIN_TYPE]).as_bytes(),
+            keccak256(&[DOMAIN_NAME]).as_bytes(),
+            keccak256(&[DOMAIN_VERSION]).as_bytes(),
+            &be_bytes(&self.chain_id.into()),
+            H256::from(self.verifying_contract).as_bytes(),
+        ])
+    }
+}
+
+fn keccak256(data: &[&[u8]]) -> H256 {
+    let mut hasher = Keccak::v256();
+    for d in data {
+        hasher.update(d);
+    }
+    let mut out = [0u8; 32];
+    hasher.finalize(&mut out);
+
+    H256(out)
+}
+
+fn be_bytes(value: &U256) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    bytes
+}
+
+/// Hash the account signs, the EIP-712 hash of `withdrawal` in `domain`.
+pub fn signing_hash(domain: &Domain, withdrawal: &Withdrawal) -> H256 {
+    let message = keccak256(&[
+        keccak256(&[WITHDRAWAL_TYPE]).as_bytes(),
+        H256::from(withdrawal.account).as_bytes(),
+        H256::from(withdrawal.token).as_bytes(),
+        &be_bytes(&withdrawal.amount),
+        &be_bytes(&withdrawal.nonce),
+    ]);
+
+    keccak256(&[
+        b"\x19\x01",
+        domain.separator().as_bytes(),
+        message.as_bytes(),
+    ])
+}
+
+fn public_address(public: &PublicKey) -> H160 {
+    let public = public.serialize_uncompressed();
+    H160::from_slice(&keccak256(&[&public[1..]]).as_bytes()[12..])
+}
+
+/// Address of `key`.
+pub fn address(key: &SecretKey) -> H160 {
+    public_address(&PublicKey::from_secret_key(&Secp256k1::signing_only(), key))
+}
+
+/// Signature of `withdrawal` in `domain` by `key`, with `v` of 27/28.
+pub fn sign(key: &SecretKey, domain: &Domain, withdrawal: &Withdrawal) -> Vec<u8> {
+    let message =
+        Message::from_slice(signing_hash(domain, withdrawal).as_bytes()).expect("32 bytes");
+    let (id, rs) = Secp256k1::signing_only()
+        .sign_recoverable(&message, key)
+        .serialize_compact();
+    let mut signature = rs.to_vec();
+    signature.push(id.to_i32() as u8 + 27);
+    signature
+}
+
+/// Address which signed `hash`, `None` if the signature is malformed.
+/// `v` may be 0/1 or 27/28.
+pub fn recover(hash: &H256, signature: &[u8]) -> Option<H160> {
+    if signature.len() != SIGNATURE_LEN {
+        return None;
+    }
+    let v = match signature[64] {
+        v @ 0..=1 => v,
+        v @ 27..=28 => v - 27,
+        _ => return None,
+    };
+    let id = RecoveryId::from_i32(v as i32).ok()?;
+    let signature = RecoverableSignature::from_compact(&signature[..64], id).ok()?;
+    let message = Message::from_slice(hash.as_bytes()).ok()?;
+    let public = Secp256k1::verification_only()
+        .recover(&message, &signature)
+        .ok()?;
+
+    Some(public_address(&public))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A random key and its address.
+    pub fn account() -> (SecretKey, H160) {
+        let key = SecretKey::from_slice(H256::random().as_bytes()).expect("key ok.");
+        let address = address(&key);
+        (key, address)
+    }
+
+    pub fn domain() -> Domain {
+        Domain {
+            chain_id: 20220,
+            verifying_contract: H160::repeat_byte(7),
+        }
+    }
+
+    fn withdrawal(account: H160) -> Withdrawal {
+        Withdrawal {
+            account,
+            token: H160::zero(),
+            amount: U256::from(40),
+            nonce: U256::zero(),
+        }
+    }
+
+    #[test]
+    fn success_recover() {
+        let (key, alice) = account();
+        let withdrawal = withdrawal(alice);
+        let hash = signing_hash(&domain(), &withdrawal);
+        let mut signature = sign(&key, &domain(), &withdrawal);
+        assert_eq!(recover(&hash, &signature), Some(alice));
+
+        signature[64] -= 27;
+        assert_eq!(recover(&hash, &signature), Some(alice));
+    }
+
+    #[test]
+    fn fail_recover() {
+        let (key, alice) = account();
+        let signature = sign(&key, &domain(), &withdrawal(alice));
+
+        let mut other = withdrawal(alice);
+        other.amount = U256::from(41);
+        let hash = signing_hash(&domain(), &other);
+        assert_ne!(recover(&hash, &signature), Some(alice));
+
+        assert_eq!(recover(&hash, &signature[..64]), None);
+        let mut bad_v = signature.clone();
+        bad_v[64] = 5;
+        assert_eq!(recover(&hash, &bad_v), None);
+    }
+
+    #[test]
+    fn fail_recover_other_domain() {
+        let (key, alice) = account();
+        let signature = sign(&key, &domain(), &withdrawal(alice));
+
+        let other_chain = Domain {
+            chain_id: 1,
+            ..domain()
+        };
+        let other_contract = Domain {
+            verifying_contract: H160::repeat_byte(8),
+            ..domain()
+        };
+        for other in [other_chain, other_contract] {
+            assert_ne!(other.separator(), domain().separator());
+            let hash = signing_hash(&other, &withdrawal(alice));
+            assert_ne!(recover(&hash, &signature), Some(alice));
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! Where an exit is on its way from the L2 burn to the L1 claim.
+use db::{DBKey, Database};
+use ethereum_types::{H160, H256};
+use executor::Withdrawal;
+use intmax_verkle::VerkleStorage;
+
+use crate::error::{Error, Result};
+use crate::{prefixed_key, ClaimStatus, Exitor};
+
+pub(crate) const ACCOUNT_EXITS_PREFIX: &[u8] = b"account_exits";
+const CHALLENGE_PERIOD_KEY: &[u8] = b"challenge_period";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExitStatus {
+    pub id: H256,
+    pub withdrawal: Withdrawal,
+    /// Unix time in seconds.
+    pub requested_at: u64,
+    /// Block, i.e. batch, including the exit.
+    pub block: Option<u64>,
+    /// Unix time in seconds at which the challenge window of `block` ends at the earliest.
+    /// Estimated from the time `block` was sealed, since it is committed to L1 afterwards.
+    pub challenge_window_end: Option<u64>,
+    /// Whether `block` is finalized on L1, i.e. the exit proof is available.
+    pub proof_available: bool,
+    pub withdrawal_batch: Option<u64>,
+    /// L1 transaction which submitted the root of `withdrawal_batch`, after which the exit can
+    /// be claimed.
+    pub finalization_tx: Option<H256>,
+    /// `None` until the exit is in a withdrawal batch.
+    pub claim_status: Option<ClaimStatus>,
+}
+
+impl<S: VerkleStorage, D: Database> Exitor<S, D> {
+    /// Exits requested by `account`, oldest first.
+    pub fn account_exits(&self, account: &H160) -> Vec<H256> {
+        self.db
+            .get(&prefixed_key(ACCOUNT_EXITS_PREFIX, account.as_bytes()))
+            .unwrap_or_default()
+    }
+
+    /// `Rollup.challengePeriod()` in seconds, once known.
+    pub fn challenge_period(&self) -> Option<u64> {
+        self.db.get(&DBKey::from_slice(CHALLENGE_PERIOD_KEY))
+    }
+
+    pub fn set_challenge_period(&self, secs: u64) -> Result<()> {
+        let mut tx = self.db.make_tx();
+        tx.put(&CHALLENGE_PERIOD_KEY, &secs);
+        self.db.commit(tx)?;
+
+        Ok(())
+    }
+
+    pub(crate) fn status(&self, id: &H256) -> Result<ExitStatus> {
+        let exit = self.exit(id).ok_or(Error::UnknownExit { id: *id })?;
+        let block = self.executor.transaction_block(id);
+        let challenge_window_end = match (block, self.challenge_period()) {
+            (Some(number), Some(period)) => self
+                .executor
+                .block(number)
+                .map(|block| block.timestamp + period),
+            _ => None,
+        };
+        let proof_available = matches!(
+            (block, self.finalized_batch()),
+            (Some(block), Some(finalized)) if finalized >= block
+        );
+        let withdrawal_batch = self.exit_withdrawal_batch(id);
+        let finalization_tx = withdrawal_batch
+            .and_then(|number| self.withdrawal_batch(number))
+            .and_then(|batch| batch.submission);
+
+        Ok(ExitStatus {
+            id: *id,
+            withdrawal: exit.withdrawal,
+            requested_at: exit.requested_at,
+            block,
+            challenge_window_end,
+            proof_available,
+            withdrawal_batch,
+            finalization_tx,
+            claim_status: self.claim_status(id),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::tests::account;
+    use crate::tests::{exitor, request_exit, withdrawal};
+    use crate::ExitorTrait;
+
+    #[test]
+    fn success_exit_status() {
+        let (key, alice) = account();
+        let (executor, exitor) = exitor(alice);
+        exitor.set_challenge_period(100).unwrap();
+
+        let id = request_exit(&exitor, &key, withdrawal(alice, 40)).unwrap();
+        let status = exitor.exit_status(&id).unwrap();
+        assert_eq!(status.withdrawal, withdrawal(alice, 40));
+        assert_eq!(status.block, None);
+        assert_eq!(status.challenge_window_end, None);
+        assert!(!status.proof_available);
+        assert_eq!(status.claim_status, None);
+
+        executor.seal_block(2).unwrap();
+        exitor.finalize_batch(2).unwrap();
+        let status = exitor.exit_status(&id).unwrap();
+        assert_eq!(status.block, Some(2));
+        assert_eq!(status.challenge_window_end, Some(102));
+        assert!(status.proof_available);
+
+        exitor.build_withdrawal_batch().unwrap();
+        exitor.mark_submitted(1, H256::repeat_byte(1)).unwrap();
+        exitor.record_claims(&[id], 10).unwrap();
+        let status = exitor.exit_status(&id).unwrap();
+        assert_eq!(status.withdrawal_batch, Some(1));
+        assert_eq!(status.finalization_tx, Some(H256::repeat_byte(1)));
+        assert_eq!(status.claim_status, Some(ClaimStatus::Claimed));
+
+        assert!(matches!(
+            exitor.exit_status(&H256::random()),
+            Err(Error::UnknownExit { .. })
+        ));
+    }
+
+    #[test]
+    fn success_list_exits() {
+        let (key, alice) = account();
+        let (executor, exitor) = exitor(alice);
+
+        let first = request_exit(&exitor, &key, withdrawal(alice, 10)).unwrap();
+        executor.seal_block(2).unwrap();
+        let mut second = withdrawal(alice, 20);
+        second.nonce = 1.into();
+        let second = request_exit(&exitor, &key, second).unwrap();
+
+        let exits = exitor.list_exits(&alice).unwrap();
+        assert_eq!(
+            exits.iter().map(|exit| exit.id).collect::<Vec<_>>(),
+            vec![first, second]
+        );
+        assert_eq!(exits[0].block, Some(2));
+        assert_eq!(exits[1].block, None);
+        assert!(exitor.list_exits(&H160::random()).unwrap().is_empty());
+    }
+}
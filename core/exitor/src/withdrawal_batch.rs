@@ -0,0 +1,370 @@
+//! Finalized exits are released on L1 in withdrawal batches: the operator submits a single
+//! Merkle root per batch, then every user claims their exit with a Merkle proof.
+use codec::{Decode, Encode};
+use db::{DBKey, Database};
+use ethereum_types::H256;
+use executor::Withdrawal;
+use intmax_verkle::VerkleStorage;
+use tracing::info;
+
+use crate::error::{Error, Result};
+use crate::{merkle, prefixed_key, Exitor, QUEUED_KEY};
+
+const WITHDRAWAL_BATCH_PREFIX: &[u8] = b"withdrawal_batch";
+const EXIT_BATCH_PREFIX: &[u8] = b"exit_batch";
+const CLAIMED_PREFIX: &[u8] = b"claimed";
+const LATEST_WITHDRAWAL_BATCH_KEY: &[u8] = b"latest_withdrawal_batch";
+/// Every withdrawal batch up to this one has been submitted to L1.
+const SUBMITTED_WITHDRAWAL_BATCH_KEY: &[u8] = b"submitted_withdrawal_batch";
+/// L1 block up to which `Claimed` events have been recorded.
+const CLAIM_CURSOR_KEY: &[u8] = b"claim_cursor";
+
+/// Finalized exits released on L1 together. Numbered from 1.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct WithdrawalBatch {
+    pub number: u64,
+    /// `merkle::root` of the leaves of `exits`.
+    pub root: H256,
+    pub exits: Vec<H256>,
+    /// L1 transaction which submitted `root`.
+    pub submission: Option<H256>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Encode, Decode)]
+pub enum ClaimStatus {
+    Unclaimed,
+    Claimed,
+}
+
+/// Everything `Rollup.claim` needs to release an exit of a submitted withdrawal batch.
+pub struct ClaimProof {
+    pub id: H256,
+    pub withdrawal_batch: u64,
+    pub root: H256,
+    pub withdrawal: Withdrawal,
+    /// `merkle::proof` of the exit leaf.
+    pub proof: Vec<H256>,
+    pub status: ClaimStatus,
+}
+
+impl<S: VerkleStorage, D: Database> Exitor<S, D> {
+    pub fn withdrawal_batch(&self, number: u64) -> Option<WithdrawalBatch> {
+        self.db.get(&prefixed_key(
+            WITHDRAWAL_BATCH_PREFIX,
+            &number.to_be_bytes(),
+        ))
+    }
+
+    pub fn latest_withdrawal_batch(&self) -> Option<u64> {
+        self.db.get(&DBKey::from_slice(LATEST_WITHDRAWAL_BATCH_KEY))
+    }
+
+    /// Withdrawal batch including the exit `id`.
+    pub fn exit_withdrawal_batch(&self, id: &H256) -> Option<u64> {
+        self.db.get(&prefixed_key(EXIT_BATCH_PREFIX, id.as_bytes()))
+    }
+
+    /// `None` until the exit `id` is in a withdrawal batch.
+    pub fn claim_status(&self, id: &H256) -> Option<ClaimStatus> {
+        self.exit_withdrawal_batch(id)?;
+        if self
+            .db
+            .contains(&prefixed_key(CLAIMED_PREFIX, id.as_bytes()))
+        {
+            Some(ClaimStatus::Claimed)
+        } else {
+            Some(ClaimStatus::Unclaimed)
+        }
+    }
+
+    /// L1 block up to which claims have been recorded.
+    pub fn claim_cursor(&self) -> Option<u64> {
+        self.db.get(&DBKey::from_slice(CLAIM_CURSOR_KEY))
+    }
+
+    /// The oldest batch including an exit not claimed on L1 yet, whose state must stay
+    /// available for `ExitorTrait::exit_proof`.
+    pub fn oldest_unclaimed_batch(&self) -> Option<u64> {
+        let batched = (1..=self.latest_withdrawal_batch().unwrap_or_default())
+            .filter_map(|number| self.withdrawal_batch(number))
+            .flat_map(|batch| batch.exits);
+        self.queued_exits()
+            .into_iter()
+            .chain(batched)
+            .filter(|id| self.claim_status(id) != Some(ClaimStatus::Claimed))
+            .filter_map(|id| self.executor.transaction_block(&id))
+            .min()
+    }
+
+    /// Puts the queued exits whose block is finalized on L1 into a new withdrawal batch.
+    /// Returns `None` if there are none.
+    pub fn build_withdrawal_batch(&self) -> Result<Option<WithdrawalBatch>> {
+        let _guard = self.write_lock.lock().expect("exitor lock poisoned");
+
+        let finalized = match self.finalized_batch() {
+            Some(finalized) => finalized,
+            None => return Ok(None),
+        };
+        let (ready, queued): (Vec<H256>, Vec<H256>) = self.queued_exits().into_iter().partition(
+            |id| matches!(self.executor.transaction_block(id), Some(block) if block <= finalized),
+        );
+        if ready.is_empty() {
+            return Ok(None);
+        }
+
+        let leaves = ready
+            .iter()
+            .map(|id| self.exit_leaf(id))
+            .collect::<Result<Vec<_>>>()?;
+        let batch = WithdrawalBatch {
+            number: self.latest_withdrawal_batch().unwrap_or_default() + 1,
+            root: merkle::root(&leaves),
+            exits: ready,
+            submission: None,
+        };
+
+        let mut tx = self.db.make_tx();
+        tx.put(
+            &prefixed_key(WITHDRAWAL_BATCH_PREFIX, &batch.number.to_be_bytes()),
+            &batch,
+        );
+        for id in batch.exits.iter() {
+            tx.put(
+                &prefixed_key(EXIT_BATCH_PREFIX, id.as_bytes()),
+                &batch.number,
+            );
+        }
+        tx.put(&LATEST_WITHDRAWAL_BATCH_KEY, &batch.number);
+        tx.put(&QUEUED_KEY, &queued);
+        self.db.commit(tx)?;
+
+        info!(
+            "built withdrawal batch {} of {} exits, root {:?}",
+            batch.number,
+            batch.exits.len(),
+            batch.root
+        );
+        Ok(Some(batch))
+    }
+
+    /// Withdrawal batches whose root still has to be submitted to L1, oldest first.
+    pub fn unsubmitted_withdrawal_batches(&self) -> Vec<WithdrawalBatch> {
+        let submitted: u64 = self
+            .db
+            .get(&DBKey::from_slice(SUBMITTED_WITHDRAWAL_BATCH_KEY))
+            .unwrap_or_default();
+        let latest = self.latest_withdrawal_batch().unwrap_or_default();
+
+        (submitted + 1..=latest)
+            .filter_map(|number| self.withdrawal_batch(number))
+            .filter(|batch| batch.submission.is_none())
+            .collect()
+    }
+
+    /// Records the L1 transaction which submitted the root of the withdrawal batch `number`.
+    pub fn mark_submitted(&self, number: u64, l1_tx: H256) -> Result<()> {
+        let _guard = self.write_lock.lock().expect("exitor lock poisoned");
+
+        let mut batch = self
+            .withdrawal_batch(number)
+            .ok_or(Error::UnknownWithdrawalBatch { number })?;
+        batch.submission = Some(l1_tx);
+
+        let mut tx = self.db.make_tx();
+        tx.put(
+            &prefixed_key(WITHDRAWAL_BATCH_PREFIX, &number.to_be_bytes()),
+            &batch,
+        );
+
+        let mut submitted: u64 = self
+            .db
+            .get(&DBKey::from_slice(SUBMITTED_WITHDRAWAL_BATCH_KEY))
+            .unwrap_or_default();
+        while submitted + 1 == number
+            || matches!(
+                self.withdrawal_batch(submitted + 1),
+                Some(WithdrawalBatch {
+                    submission: Some(_),
+                    ..
+                })
+            )
+        {
+            submitted += 1;
+        }
+        tx.put(&SUBMITTED_WITHDRAWAL_BATCH_KEY, &submitted);
+        self.db.commit(tx)?;
+
+        info!("submitted withdrawal batch {} in {:?}", number, l1_tx);
+        Ok(())
+    }
+
+    /// Marks the exits `ids` claimed on L1, and moves the claim cursor to `l1_block`.
+    pub fn record_claims(&self, ids: &[H256], l1_block: u64) -> Result<()> {
+        let mut tx = self.db.make_tx();
+        for id in ids {
+            tx.put(&prefixed_key(CLAIMED_PREFIX, id.as_bytes()), &l1_block);
+        }
+        tx.put(&CLAIM_CURSOR_KEY, &l1_block);
+        self.db.commit(tx)?;
+
+        Ok(())
+    }
+
+    pub(crate) fn prove_claim(&self, id: &H256) -> Result<ClaimProof> {
+        let exit = self.exit(id).ok_or(Error::UnknownExit { id: *id })?;
+        let number = self
+            .exit_withdrawal_batch(id)
+            .ok_or(Error::NotBatched { id: *id })?;
+        let batch = self
+            .withdrawal_batch(number)
+            .ok_or(Error::UnknownWithdrawalBatch { number })?;
+        if batch.submission.is_none() {
+            return Err(Error::NotSubmitted {
+                id: *id,
+                withdrawal_batch: number,
+            });
+        }
+
+        let leaves = batch
+            .exits
+            .iter()
+            .map(|id| self.exit_leaf(id))
+            .collect::<Result<Vec<_>>>()?;
+        let index = batch
+            .exits
+            .iter()
+            .position(|exit| exit == id)
+            .expect("the batch of an exit includes it");
+
+        Ok(ClaimProof {
+            id: *id,
+            withdrawal_batch: number,
+            root: batch.root,
+            withdrawal: exit.withdrawal,
+            proof: merkle::proof(&leaves, index),
+            status: self.claim_status(id).unwrap_or(ClaimStatus::Unclaimed),
+        })
+    }
+
+    fn exit_leaf(&self, id: &H256) -> Result<H256> {
+        let exit = self.exit(id).ok_or(Error::UnknownExit { id: *id })?;
+        let withdrawal = exit.withdrawal;
+
+        Ok(merkle::leaf(
+            id,
+            &withdrawal.account,
+            &withdrawal.token,
+            &withdrawal.amount,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::tests::account;
+    use crate::tests::{exitor, request_exit, withdrawal};
+    use crate::ExitorTrait;
+    use ethereum_types::H160;
+    use executor::Withdrawal;
+
+    #[test]
+    fn success_claim_proof() {
+        let (key, alice) = account();
+        let (executor, exitor) = exitor(alice);
+
+        let first = request_exit(&exitor, &key, withdrawal(alice, 10)).unwrap();
+        executor.seal_block(2).unwrap();
+        let second = request_exit(
+            &exitor,
+            &key,
+            Withdrawal {
+                nonce: 1.into(),
+                ..withdrawal(alice, 20)
+            },
+        )
+        .unwrap();
+        executor.seal_block(3).unwrap();
+
+        // nothing is finalized yet.
+        assert_eq!(exitor.build_withdrawal_batch().unwrap(), None);
+        assert_eq!(exitor.oldest_unclaimed_batch(), Some(2));
+        assert!(matches!(
+            exitor.claim_proof(&first),
+            Err(Error::NotBatched { .. })
+        ));
+
+        exitor.finalize_batch(2).unwrap();
+        let batch = exitor.build_withdrawal_batch().unwrap().expect("batch 1.");
+        assert_eq!(batch.number, 1);
+        assert_eq!(batch.exits, vec![first]);
+        assert_eq!(exitor.queued_exits(), vec![second]);
+        assert_eq!(exitor.claim_status(&first), Some(ClaimStatus::Unclaimed));
+        assert_eq!(exitor.claim_status(&second), None);
+        assert!(matches!(
+            exitor.claim_proof(&first),
+            Err(Error::NotSubmitted {
+                withdrawal_batch: 1,
+                ..
+            })
+        ));
+
+        assert_eq!(exitor.unsubmitted_withdrawal_batches(), vec![batch.clone()]);
+        exitor.mark_submitted(1, H256::repeat_byte(1)).unwrap();
+        assert!(exitor.unsubmitted_withdrawal_batches().is_empty());
+
+        let proof = exitor.claim_proof(&first).expect("proof ok.");
+        assert_eq!(proof.withdrawal_batch, 1);
+        assert_eq!(proof.root, batch.root);
+        assert_eq!(proof.status, ClaimStatus::Unclaimed);
+        let leaf = merkle::leaf(&first, &alice, &H160::zero(), &10.into());
+        assert!(merkle::verify(&proof.root, &leaf, &proof.proof));
+
+        assert_eq!(exitor.oldest_unclaimed_batch(), Some(2));
+        exitor.record_claims(&[first], 42).unwrap();
+        assert_eq!(exitor.claim_status(&first), Some(ClaimStatus::Claimed));
+        assert_eq!(exitor.oldest_unclaimed_batch(), Some(3));
+        assert_eq!(exitor.claim_cursor(), Some(42));
+        assert_eq!(
+            exitor.claim_proof(&first).unwrap().status,
+            ClaimStatus::Claimed
+        );
+    }
+
+    #[test]
+    fn success_unsubmitted_withdrawal_batches_in_order() {
+        let (key, alice) = account();
+        let (executor, exitor) = exitor(alice);
+
+        for nonce in 0..3u64 {
+            request_exit(
+                &exitor,
+                &key,
+                Withdrawal {
+                    nonce: nonce.into(),
+                    ..withdrawal(alice, 10)
+                },
+            )
+            .unwrap();
+            let block = executor.seal_block(nonce + 2).unwrap().unwrap();
+            exitor.finalize_batch(block.number).unwrap();
+            exitor.build_withdrawal_batch().unwrap().unwrap();
+        }
+
+        exitor.mark_submitted(2, H256::repeat_byte(2)).unwrap();
+        let numbers = |exitor: &crate::tests::TestExitor| -> Vec<u64> {
+            exitor
+                .unsubmitted_withdrawal_batches()
+                .iter()
+                .map(|batch| batch.number)
+                .collect()
+        };
+        assert_eq!(numbers(&exitor), vec![1, 3]);
+
+        exitor.mark_submitted(1, H256::repeat_byte(1)).unwrap();
+        assert_eq!(numbers(&exitor), vec![3]);
+
+        let err = exitor.mark_submitted(4, H256::zero()).unwrap_err();
+        assert!(matches!(err, Error::UnknownWithdrawalBatch { number: 4 }));
+    }
+}
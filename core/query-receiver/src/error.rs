@@ -0,0 +1,120 @@
+use ethereum_types::{H160, H256};
+use executor::Error as ExecutorError;
+use jsonrpc_core as rpc;
+
+/// Query Result type.
+pub type Result<T> = std::result::Result<T, Error>;
+
+pub fn internal<E: ::std::fmt::Debug>(e: E) -> rpc::Error {
+    jsonrpc_core::Error {
+        code: rpc::ErrorCode::InternalError,
+        message: "Unknown error occurred".into(),
+        data: Some(format!("{:?}", e).into()),
+    }
+}
+
+/// Query errors.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Error from the executor.
+    #[error("Executor error: {}", .0)]
+    Executor(#[from] ExecutorError),
+
+    /// If there is no block with the hash.
+    #[error("Block({}) is unknown", .hash)]
+    UnknownBlock {
+        /// Block hash
+        hash: H256,
+    },
+
+    /// If a proof is requested for a block whose state tree is not kept.
+    #[error("Proofs are not available for the state of block {}", .number)]
+    ProofUnavailable {
+        /// Block number
+        number: u64,
+    },
+
+    /// If the balance has never been set, so there is no leaf to prove.
+    #[error("{} has no balance of token {} in the state", .account, .token)]
+    NotInState {
+        /// Account of the balance
+        account: H160,
+        /// Token of the balance
+        token: H160,
+    },
+
+    /// If a non-membership proof is requested for a key which is in the state.
+    #[error("{} is in the state", .key)]
+    InState {
+        /// State key
+        key: H256,
+    },
+
+    /// If a log query ends before it starts.
+    #[error("Block range {}..={} is invalid", .from, .to)]
+    InvalidBlockRange {
+        /// First block
+        from: u64,
+        /// Last block
+        to: u64,
+    },
+
+    /// If a log query spans more blocks than allowed.
+    #[error("Block range {}..={} exceeds the limit of {} blocks", .from, .to, .limit)]
+    BlockRangeTooLarge {
+        /// First block
+        from: u64,
+        /// Last block
+        to: u64,
+        /// Largest number of blocks
+        limit: u64,
+    },
+
+    /// If there is no installed filter with the id.
+    #[error("Filter {} is not installed", .id)]
+    UnknownFilter {
+        /// Filter id
+        id: u64,
+    },
+}
+
+impl From<Error> for rpc::Error {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Executor(ExecutorError::UnknownBlockNumber { .. })
+            | Error::Executor(ExecutorError::StateVersionPruned { .. })
+            | Error::UnknownBlock { .. }
+            | Error::ProofUnavailable { .. }
+            | Error::NotInState { .. }
+            | Error::InState { .. }
+            | Error::InvalidBlockRange { .. }
+            | Error::BlockRangeTooLarge { .. }
+            | Error::UnknownFilter { .. } => rpc::Error {
+                code: rpc::ErrorCode::InvalidParams,
+                message: format!("{}", e),
+                data: None,
+            },
+            e => internal(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn success_type_conversion_into_core_all() {
+        let e: rpc::Error = Error::Executor(ExecutorError::StateVersionPruned { number: 2 }).into();
+        assert_eq!(e.code, rpc::ErrorCode::InvalidParams);
+        assert_eq!(e.message, "Executor error: State tree at block 2 is pruned");
+
+        let e: rpc::Error =
+            Error::Executor(ExecutorError::DuplicateDeposit { id: H256::zero() }).into();
+        assert_eq!(e.code, rpc::ErrorCode::InternalError);
+
+        let e: rpc::Error = Error::UnknownBlock { hash: H256::zero() }.into();
+        assert_eq!(e.code, rpc::ErrorCode::InvalidParams);
+        assert_eq!(e.message, "Block(0x0000…0000) is unknown");
+    }
+}
@@ -0,0 +1,138 @@
+//! Log queries: `eth_getLogs` and the filters polled with `eth_getFilterChanges`.
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use ethereum_types::{H160, H256};
+use executor::Log;
+
+use crate::BlockId;
+
+/// Filters not polled for this long are dropped.
+const FILTER_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Logs matching every given criterion.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogFilter {
+    pub from_block: BlockId,
+    pub to_block: BlockId,
+    /// Only the logs of this block. Overrides `from_block` and `to_block`.
+    pub block_hash: Option<H256>,
+    /// Any of the addresses, any address if empty.
+    pub address: Vec<H160>,
+    /// For every position, any of the topics, or any topic if `None`.
+    pub topics: Vec<Option<Vec<H256>>>,
+}
+
+impl Default for LogFilter {
+    fn default() -> Self {
+        LogFilter {
+            from_block: BlockId::Latest,
+            to_block: BlockId::Latest,
+            block_hash: None,
+            address: vec![],
+            topics: vec![],
+        }
+    }
+}
+
+impl LogFilter {
+    pub fn matches(&self, log: &Log) -> bool {
+        if !self.address.is_empty() && !self.address.contains(&log.address) {
+            return false;
+        }
+
+        self.topics
+            .iter()
+            .enumerate()
+            .all(|(i, topics)| match topics {
+                Some(topics) => log
+                    .topics
+                    .get(i)
+                    .map(|topic| topics.contains(topic))
+                    .unwrap_or_default(),
+                None => true,
+            })
+    }
+}
+
+struct InstalledFilter {
+    filter: LogFilter,
+    /// First block not polled yet.
+    next_block: u64,
+    last_poll: Instant,
+}
+
+/// Filters installed with `eth_newFilter`.
+#[derive(Default)]
+pub(crate) struct Filters {
+    filters: HashMap<u64, InstalledFilter>,
+    last_id: u64,
+}
+
+impl Filters {
+    pub fn install(&mut self, filter: LogFilter, next_block: u64) -> u64 {
+        self.filters
+            .retain(|_, installed| installed.last_poll.elapsed() < FILTER_TIMEOUT);
+
+        self.last_id += 1;
+        self.filters.insert(
+            self.last_id,
+            InstalledFilter {
+                filter,
+                next_block,
+                last_poll: Instant::now(),
+            },
+        );
+        self.last_id
+    }
+
+    /// The filter `id` and the first block it has not seen yet.
+    pub fn get(&self, id: u64) -> Option<(LogFilter, u64)> {
+        let installed = self.filters.get(&id)?;
+        Some((installed.filter.clone(), installed.next_block))
+    }
+
+    /// Records that the filter `id` has seen the blocks before `next_block`.
+    pub fn polled(&mut self, id: u64, next_block: u64) {
+        if let Some(installed) = self.filters.get_mut(&id) {
+            installed.next_block = next_block;
+            installed.last_poll = Instant::now();
+        }
+    }
+
+    pub fn uninstall(&mut self, id: u64) -> bool {
+        self.filters.remove(&id).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn success_matches() {
+        let token = H160::repeat_byte(1);
+        let log = Log {
+            address: token,
+            topics: vec![H256::repeat_byte(1), H256::repeat_byte(2)],
+            data: vec![],
+            block_number: 1,
+            block_hash: H256::zero(),
+            transaction_hash: H256::zero(),
+            transaction_index: 0,
+            log_index: 0,
+        };
+
+        assert!(LogFilter::default().matches(&log));
+        let filter = |address: Vec<H160>, topics: Vec<Option<Vec<H256>>>| LogFilter {
+            address,
+            topics,
+            ..LogFilter::default()
+        };
+        assert!(filter(vec![H160::zero(), token], vec![]).matches(&log));
+        assert!(!filter(vec![H160::zero()], vec![]).matches(&log));
+        assert!(filter(vec![], vec![None, Some(vec![H256::repeat_byte(2)])]).matches(&log));
+        assert!(!filter(vec![], vec![Some(vec![H256::repeat_byte(2)])]).matches(&log));
+        assert!(!filter(vec![], vec![None, None, Some(vec![H256::zero()])]).matches(&log));
+    }
+}
@@ -1,8 +1,794 @@
+use std::sync::{Arc, Mutex};
+
+use codec::Decode;
+use db::Database;
+use ethereum_types::{H160, H256, U256};
+use executor::{state, Block, Executor, Log, Transaction};
+use exitor::Exitor;
+use intmax_verkle::{Proof, SchemeKind, StorageError, VerkleSMTStorage, VerkleStorage};
+
+use filter::Filters;
+
+pub use error::{Error, Result};
+pub use filter::LogFilter;
+
+mod error;
+mod filter;
+
+/// Largest page of `account_transactions`, and its size if no limit is given.
+pub const MAX_ACCOUNT_TRANSACTIONS: u64 = 100;
+
+/// Block whose state or header is queried.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockId {
+    /// The latest sealed block.
+    Latest,
+    /// The state after the transactions not sealed yet. There is no pending block.
+    Pending,
+    /// The latest block finalized on L1.
+    Finalized,
+    /// The empty state before block 1.
+    Earliest,
+    Number(u64),
+    Hash(H256),
+}
+
+/// Proof of a balance, to be checked with `intmax_verkle::verifier`.
+pub struct BalanceProof {
+    pub account: H160,
+    pub token: H160,
+    pub balance: U256,
+    /// Block whose state is proven, `None` for the pending state.
+    pub block: Option<u64>,
+    /// State root of `block` committed on L1, `None` until it is committed.
+    pub state_root: Option<H256>,
+    /// Scheme of the state tree, to verify `proof` with.
+    pub scheme: SchemeKind,
+    /// Inclusion proof of `executor::state::balance_key(account, token)`.
+    pub proof: Proof,
+}
+
+/// Proof that a key is absent from the state, to be checked with
+/// `intmax_verkle::verifier::verify_absence`.
+pub struct NonMembershipProof {
+    pub key: H256,
+    /// Block whose state is proven, `None` for the pending state.
+    pub block: Option<u64>,
+    /// State root of `block` committed on L1, `None` until it is committed.
+    pub state_root: Option<H256>,
+    /// Scheme of the state tree, to verify `proof` with.
+    pub scheme: SchemeKind,
+    pub proof: Proof,
+}
+
+/// A sealed transaction and the block including it.
+pub struct AccountTransaction {
+    pub transaction: Transaction,
+    pub block: u64,
+}
+
+/// A page of the transactions of an account, newest first.
+pub struct AccountTransactions {
+    pub transactions: Vec<AccountTransaction>,
+    /// Cursor of the next page, `None` if this is the last one.
+    pub next_cursor: Option<u64>,
+}
+
+/// Read side of the L2 state and blocks.
+pub trait QueryReceiverTrait {
+    /// Balance of the L1 native token, i.e. of the zero address token.
+    fn balance(&self, account: &H160, block: BlockId) -> Result<U256>;
+
+    fn transaction_count(&self, account: &H160, block: BlockId) -> Result<U256>;
+
+    /// The latest sealed block, 0 before the first one.
+    fn block_number(&self) -> u64;
+
+    /// `None` if there is no such block.
+    fn block_by_number(&self, block: BlockId) -> Option<Block>;
+
+    fn block_by_hash(&self, hash: &H256) -> Option<Block>;
+
+    fn chain_id(&self) -> u64;
+
+    /// Proof of the balance of `token` held by `account`, in the pending state or in the state
+    /// of a block whose state tree is kept.
+    fn balance_proof(&self, account: &H160, token: &H160, block: BlockId) -> Result<BalanceProof>;
+
+    /// Proof that nothing is stored under the state key `key`, e.g. that an account never held
+    /// a token. The states which can be proven are the ones of `balance_proof`.
+    fn non_membership_proof(&self, key: &H256, block: BlockId) -> Result<NonMembershipProof>;
+
+    /// Logs matching `filter`. `Pending` is the latest block, as there is no pending block.
+    fn logs(&self, filter: &LogFilter) -> Result<Vec<Log>>;
+
+    /// Installs `filter` and returns its id. Its changes start from the next block.
+    fn new_filter(&self, filter: LogFilter) -> u64;
+
+    /// Logs matching the filter `id` in the blocks sealed since the previous poll. At most
+    /// the block range limit is scanned per poll, the rest is returned by the next polls.
+    fn filter_changes(&self, id: u64) -> Result<Vec<Log>>;
+
+    /// Whether the filter `id` was installed.
+    fn uninstall_filter(&self, id: u64) -> bool;
+
+    /// Sealed transactions of `account`, newest first, from `cursor` returned by the previous
+    /// page. At most `limit` and `MAX_ACCOUNT_TRANSACTIONS` transactions are returned.
+    fn account_transactions(
+        &self,
+        account: &H160,
+        cursor: Option<u64>,
+        limit: Option<u64>,
+    ) -> AccountTransactions;
+}
+
+pub struct QueryReceiver<S: VerkleStorage, D: Database> {
+    executor: Arc<Executor<S, D>>,
+    exitor: Arc<Exitor<S, D>>,
+    chain_id: u64,
+    /// Largest block range scanned by a log query. `None` scans any range.
+    max_log_blocks: Option<u64>,
+    filters: Mutex<Filters>,
+}
+
+impl<S: VerkleStorage, D: Database> QueryReceiver<S, D> {
+    pub fn new(executor: Arc<Executor<S, D>>, exitor: Arc<Exitor<S, D>>, chain_id: u64) -> Self {
+        QueryReceiver {
+            executor,
+            exitor,
+            chain_id,
+            max_log_blocks: None,
+            filters: Mutex::new(Filters::default()),
+        }
+    }
+
+    /// Rejects log queries over more than `blocks` blocks.
+    pub fn with_max_log_blocks(mut self, blocks: u64) -> Self {
+        self.max_log_blocks = Some(blocks);
+        self
+    }
+
+    /// Number of the block `block` refers to, `None` for the pending state.
+    fn resolve(&self, block: BlockId) -> Result<Option<u64>> {
+        let number = match block {
+            BlockId::Pending => return Ok(None),
+            BlockId::Latest => self.executor.latest_block_number(),
+            BlockId::Finalized => self.exitor.finalized_batch().unwrap_or_default(),
+            BlockId::Earliest => 0,
+            BlockId::Number(number) => number,
+            BlockId::Hash(hash) => {
+                self.executor
+                    .block_by_hash(&hash)
+                    .ok_or(Error::UnknownBlock { hash })?
+                    .number
+            }
+        };
+
+        Ok(Some(number))
+    }
+
+    /// Proof of `key` in the state of block `number`, or of its absence.
+    fn proof_at(&self, number: u64, key: &H256) -> Result<Proof> {
+        self.executor.proof_at(number, key).map_err(|e| match e {
+            executor::Error::StateVersionPruned { number } => Error::ProofUnavailable { number },
+            e => e.into(),
+        })
+    }
+
+    /// Logs of the blocks `from..=to` matching `filter`.
+    fn range_logs(&self, filter: &LogFilter, from: u64, to: u64) -> Vec<Log> {
+        (from..=to)
+            .flat_map(|number| self.executor.logs(number))
+            .filter(|log| filter.matches(log))
+            .collect()
+    }
+
+    /// Number of the block `block` refers to as a log query bound. There are no logs in the
+    /// pending state, so it is the latest block.
+    fn log_bound(&self, block: BlockId) -> Result<u64> {
+        let latest = self.executor.latest_block_number();
+        Ok(self.resolve(block)?.unwrap_or(latest).min(latest))
+    }
+
+    fn committed_state_root(&self, number: Option<u64>) -> Option<H256> {
+        let batch = self.exitor.committed_batch(number?)?;
+        Some(batch.state_root)
+    }
+}
+
+impl<S: VerkleSMTStorage, D: Database> QueryReceiverTrait for QueryReceiver<S, D> {
+    fn balance(&self, account: &H160, block: BlockId) -> Result<U256> {
+        let token = H160::zero();
+        match self.resolve(block)? {
+            Some(number) => Ok(self.executor.balance_at(account, &token, number)?),
+            None => Ok(self.executor.balance(account, &token)?),
+        }
+    }
+
+    fn transaction_count(&self, account: &H160, block: BlockId) -> Result<U256> {
+        match self.resolve(block)? {
+            Some(number) => Ok(self.executor.nonce_at(account, number)?),
+            None => Ok(self.executor.nonce(account)?),
+        }
+    }
+
+    fn block_number(&self) -> u64 {
+        self.executor.latest_block_number()
+    }
+
+    fn block_by_number(&self, block: BlockId) -> Option<Block> {
+        match block {
+            BlockId::Hash(hash) => self.executor.block_by_hash(&hash),
+            // Block 0 is not a block, the earliest one is block 1.
+            BlockId::Earliest => self.executor.block(1),
+            block => self.executor.block(self.resolve(block).ok()??),
+        }
+    }
+
+    fn block_by_hash(&self, hash: &H256) -> Option<Block> {
+        self.executor.block_by_hash(hash)
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    fn balance_proof(&self, account: &H160, token: &H160, block: BlockId) -> Result<BalanceProof> {
+        let key = state::balance_key(account, token);
+        let not_in_state = || Error::NotInState {
+            account: *account,
+            token: *token,
+        };
+        let number = self.resolve(block)?;
+        let (proof, balance) = match number {
+            Some(number) => {
+                let proof = self.proof_at(number, &key)?;
+                let value = proof.value.as_ref().ok_or_else(not_in_state)?;
+                let balance = U256::decode(&mut &value[..])
+                    .map_err(|_| executor::Error::Storage(StorageError::Decode))?;
+                (proof, balance)
+            }
+            None => {
+                let proof = self.executor.inclusion_proof(&key).map_err(|e| match e {
+                    executor::Error::Storage(StorageError::KeyNotFound) => not_in_state(),
+                    e => e.into(),
+                })?;
+                (proof, self.executor.balance(account, token)?)
+            }
+        };
+
+        Ok(BalanceProof {
+            account: *account,
+            token: *token,
+            balance,
+            block: number,
+            state_root: self.committed_state_root(number),
+            scheme: self.executor.state_scheme(),
+            proof,
+        })
+    }
+
+    fn non_membership_proof(&self, key: &H256, block: BlockId) -> Result<NonMembershipProof> {
+        let number = self.resolve(block)?;
+        let proof = match number {
+            Some(number) => {
+                let proof = self.proof_at(number, key)?;
+                if proof.value.is_some() {
+                    return Err(Error::InState { key: *key });
+                }
+                proof
+            }
+            None => self.executor.exclusion_proof(key).map_err(|e| match e {
+                executor::Error::Storage(StorageError::KeyExists) => Error::InState { key: *key },
+                e => e.into(),
+            })?,
+        };
+
+        Ok(NonMembershipProof {
+            key: *key,
+            block: number,
+            state_root: self.committed_state_root(number),
+            scheme: self.executor.state_scheme(),
+            proof,
+        })
+    }
+
+    fn logs(&self, filter: &LogFilter) -> Result<Vec<Log>> {
+        let (from, to) = match filter.block_hash {
+            Some(hash) => {
+                let number = self.log_bound(BlockId::Hash(hash))?;
+                (number, number)
+            }
+            None => (
+                self.log_bound(filter.from_block)?,
+                self.log_bound(filter.to_block)?,
+            ),
+        };
+        if from > to {
+            return Err(Error::InvalidBlockRange { from, to });
+        }
+        if let Some(limit) = self.max_log_blocks {
+            if to - from >= limit {
+                return Err(Error::BlockRangeTooLarge { from, to, limit });
+            }
+        }
+
+        Ok(self.range_logs(filter, from, to))
+    }
+
+    fn new_filter(&self, filter: LogFilter) -> u64 {
+        let next_block = self.executor.latest_block_number() + 1;
+        self.filters
+            .lock()
+            .expect("filters lock poisoned")
+            .install(filter, next_block)
+    }
+
+    fn filter_changes(&self, id: u64) -> Result<Vec<Log>> {
+        let (filter, next_block) = self
+            .filters
+            .lock()
+            .expect("filters lock poisoned")
+            .get(id)
+            .ok_or(Error::UnknownFilter { id })?;
+
+        let (from, to) = match filter.block_hash {
+            Some(hash) => {
+                let number = self.log_bound(BlockId::Hash(hash))?;
+                (number, number)
+            }
+            None => {
+                let from = match filter.from_block {
+                    BlockId::Latest | BlockId::Pending => next_block,
+                    block => self.log_bound(block)?,
+                };
+                (from, self.log_bound(filter.to_block)?)
+            }
+        };
+        let from = from.max(next_block);
+        if from > to {
+            return Ok(vec![]);
+        }
+        let to = match self.max_log_blocks {
+            Some(limit) => to.min(from + limit.max(1) - 1),
+            None => to,
+        };
+
+        let logs = self.range_logs(&filter, from, to);
+        self.filters
+            .lock()
+            .expect("filters lock poisoned")
+            .polled(id, to + 1);
+
+        Ok(logs)
+    }
+
+    fn uninstall_filter(&self, id: u64) -> bool {
+        self.filters
+            .lock()
+            .expect("filters lock poisoned")
+            .uninstall(id)
+    }
+
+    fn account_transactions(
+        &self,
+        account: &H160,
+        cursor: Option<u64>,
+        limit: Option<u64>,
+    ) -> AccountTransactions {
+        let limit = limit
+            .unwrap_or(MAX_ACCOUNT_TRANSACTIONS)
+            .clamp(1, MAX_ACCOUNT_TRANSACTIONS);
+        let (hashes, next_cursor) = self.executor.account_transactions(account, cursor, limit);
+
+        let transactions = hashes
+            .iter()
+            .filter_map(|hash| {
+                let block = self
+                    .executor
+                    .block(self.executor.transaction_block(hash)?)?;
+                let transaction = block
+                    .transactions
+                    .into_iter()
+                    .find(|transaction| transaction.hash() == *hash)?;
+                Some(AccountTransaction {
+                    transaction,
+                    block: block.number,
+                })
+            })
+            .collect();
+
+        AccountTransactions {
+            transactions,
+            next_cursor,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use db::Db;
+    use executor::{Deposit, ExecutorTrait, Transaction, Withdrawal};
+    use exitor::signature::{self, SecretKey};
+    use exitor::{CommittedBatch, ExitorTrait};
+    use intmax_verkle::VerkleTree;
+    use kvdb_memorydb::InMemory;
+
+    type TestExecutor = Executor<VerkleTree<Db<InMemory>>, Db<InMemory>>;
+    type TestExitor = Exitor<VerkleTree<Db<InMemory>>, Db<InMemory>>;
+    type TestQueryReceiver = QueryReceiver<VerkleTree<Db<InMemory>>, Db<InMemory>>;
+
+    fn query_receiver() -> (Arc<TestExecutor>, Arc<TestExitor>, TestQueryReceiver) {
+        let db = Db::new(kvdb_memorydb::create(db::columns::NUM_COLUMNS), 0);
+        let executor = Arc::new(
+            Executor::new(
+                VerkleTree::new(db.with_column(db::columns::STATE)),
+                db.with_column(db::columns::EXECUTOR),
+            )
+            .with_log_index(db.with_column(db::columns::LOGS))
+            .with_account_index(db.with_column(db::columns::ACCOUNT_TXS)),
+        );
+        let exitor = Arc::new(Exitor::new(
+            executor.clone(),
+            db.with_column(db::columns::EXITOR),
+        ));
+        let query_receiver = QueryReceiver::new(executor.clone(), exitor.clone(), 42);
+
+        (executor, exitor, query_receiver)
+    }
+
+    fn deposit(id: u64, recipient: H160, amount: u64) -> Transaction {
+        Transaction::Deposit(Deposit {
+            id: H256::from_low_u64_be(id),
+            l1_block: 1,
+            recipient,
+            token: H160::zero(),
+            amount: U256::from(amount),
+        })
+    }
+
+    #[test]
+    fn success_balance() {
+        let key = SecretKey::from_slice(H256::random().as_bytes()).unwrap();
+        let alice = signature::address(&key);
+        let (executor, exitor, query_receiver) = query_receiver();
+        assert_eq!(
+            query_receiver.balance(&alice, BlockId::Latest).unwrap(),
+            U256::zero()
+        );
+
+        executor.execute(&deposit(1, alice, 100)).unwrap();
+        let first = executor.seal_block(1).unwrap().unwrap();
+        exitor.finalize_batch(1).unwrap();
+        let withdrawal = Withdrawal {
+            account: alice,
+            token: H160::zero(),
+            amount: U256::from(30),
+            nonce: U256::zero(),
+        };
+        exitor
+            .request_exit(
+                withdrawal.clone(),
+                &signature::sign(&key, &signature::Domain::default(), &withdrawal),
+            )
+            .unwrap();
+
+        let balance = |block| query_receiver.balance(&alice, block).unwrap();
+        assert_eq!(balance(BlockId::Pending), U256::from(70));
+        assert_eq!(balance(BlockId::Latest), U256::from(100));
+        assert_eq!(balance(BlockId::Finalized), U256::from(100));
+        assert_eq!(balance(BlockId::Earliest), U256::zero());
+        assert_eq!(balance(BlockId::Hash(first.hash)), U256::from(100));
+
+        executor.seal_block(2).unwrap();
+        assert_eq!(balance(BlockId::Latest), U256::from(70));
+        assert_eq!(balance(BlockId::Number(1)), U256::from(100));
+        assert_eq!(
+            query_receiver
+                .transaction_count(&alice, BlockId::Latest)
+                .unwrap(),
+            U256::one()
+        );
+        assert_eq!(
+            query_receiver
+                .transaction_count(&alice, BlockId::Number(1))
+                .unwrap(),
+            U256::zero()
+        );
+
+        assert!(matches!(
+            query_receiver.balance(&alice, BlockId::Number(3)),
+            Err(Error::Executor(executor::Error::UnknownBlockNumber {
+                number: 3
+            }))
+        ));
+        assert!(matches!(
+            query_receiver.balance(&alice, BlockId::Hash(H256::zero())),
+            Err(Error::UnknownBlock { .. })
+        ));
+    }
+
+    #[test]
+    fn success_blocks() {
+        let (executor, exitor, query_receiver) = query_receiver();
+        assert_eq!(query_receiver.block_number(), 0);
+        assert_eq!(query_receiver.chain_id(), 42);
+        assert_eq!(query_receiver.block_by_number(BlockId::Latest), None);
+
+        executor.execute(&deposit(1, H160::random(), 10)).unwrap();
+        let first = executor.seal_block(1).unwrap().unwrap();
+        executor.execute(&deposit(2, H160::random(), 10)).unwrap();
+        let second = executor.seal_block(2).unwrap().unwrap();
+        exitor.finalize_batch(1).unwrap();
+
+        assert_eq!(query_receiver.block_number(), 2);
+        assert_eq!(
+            query_receiver.block_by_number(BlockId::Latest),
+            Some(second.clone())
+        );
+        assert_eq!(
+            query_receiver.block_by_number(BlockId::Finalized),
+            Some(first.clone())
+        );
+        assert_eq!(
+            query_receiver.block_by_number(BlockId::Earliest),
+            Some(first.clone())
+        );
+        assert_eq!(
+            query_receiver.block_by_number(BlockId::Hash(second.hash)),
+            Some(second)
+        );
+        assert_eq!(query_receiver.block_by_number(BlockId::Number(3)), None);
+        assert_eq!(query_receiver.block_by_number(BlockId::Pending), None);
+        assert_eq!(query_receiver.block_by_hash(&first.hash), Some(first));
+        assert_eq!(query_receiver.block_by_hash(&H256::zero()), None);
+    }
+
+    #[test]
+    fn success_balance_proof() {
+        let alice = H160::random();
+        let token = H160::zero();
+        let (executor, exitor, query_receiver) = query_receiver();
+
+        executor.execute(&deposit(1, alice, 100)).unwrap();
+        let proof = query_receiver
+            .balance_proof(&alice, &token, BlockId::Pending)
+            .unwrap();
+        assert_eq!(proof.block, None);
+        assert_eq!(proof.balance, U256::from(100));
+        intmax_verkle::verifier::verify(
+            proof.scheme,
+            &executor.state_root().unwrap().0,
+            &proof.proof,
+            &state::balance_key(&alice, &token),
+            &proof.balance,
+        )
+        .expect("verify ok.");
+        assert!(matches!(
+            query_receiver.balance_proof(&alice, &token, BlockId::Latest),
+            Err(Error::ProofUnavailable { number: 0 })
+        ));
+
+        executor.seal_block(1).unwrap();
+        let proof = query_receiver
+            .balance_proof(&alice, &token, BlockId::Latest)
+            .unwrap();
+        assert_eq!(proof.block, Some(1));
+        assert_eq!(proof.state_root, None);
+
+        let batch = CommittedBatch {
+            number: 1,
+            state_root: H256::repeat_byte(1),
+        };
+        exitor.record_committed_batch(&batch).unwrap();
+        let proof = query_receiver
+            .balance_proof(&alice, &token, BlockId::Number(1))
+            .unwrap();
+        assert_eq!(proof.state_root, Some(batch.state_root));
+
+        executor.execute(&deposit(2, alice, 10)).unwrap();
+        executor.seal_block(2).unwrap();
+        let proof = query_receiver
+            .balance_proof(&alice, &token, BlockId::Number(1))
+            .unwrap();
+        assert_eq!(proof.balance, U256::from(100));
+        intmax_verkle::verifier::verify(
+            proof.scheme,
+            &executor.state_root_at(1).unwrap().0,
+            &proof.proof,
+            &state::balance_key(&alice, &token),
+            &proof.balance,
+        )
+        .expect("verify ok.");
+
+        assert!(matches!(
+            query_receiver.balance_proof(&H160::random(), &token, BlockId::Latest),
+            Err(Error::NotInState { .. })
+        ));
+    }
+
+    #[test]
+    fn success_non_membership_proof() {
+        let alice = H160::random();
+        let (executor, exitor, query_receiver) = query_receiver();
+        let key = state::balance_key(&alice, &H160::zero());
+
+        executor.execute(&deposit(1, H160::random(), 100)).unwrap();
+        executor.seal_block(1).unwrap();
+        exitor
+            .record_committed_batch(&CommittedBatch {
+                number: 1,
+                state_root: H256::repeat_byte(1),
+            })
+            .unwrap();
+        let proof = query_receiver
+            .non_membership_proof(&key, BlockId::Latest)
+            .unwrap();
+        assert_eq!(proof.block, Some(1));
+        assert_eq!(proof.state_root, Some(H256::repeat_byte(1)));
+        intmax_verkle::verifier::verify_absence(
+            proof.scheme,
+            &executor.state_root().unwrap().0,
+            &proof.proof,
+            &key,
+        )
+        .expect("verify ok.");
+
+        executor.execute(&deposit(2, alice, 100)).unwrap();
+        assert!(matches!(
+            query_receiver.non_membership_proof(&key, BlockId::Pending),
+            Err(Error::InState { .. })
+        ));
+
+        // Block 1 can still be proven, against its own state root.
+        let proof = query_receiver
+            .non_membership_proof(&key, BlockId::Latest)
+            .unwrap();
+        intmax_verkle::verifier::verify_absence(
+            proof.scheme,
+            &executor.state_root_at(1).unwrap().0,
+            &proof.proof,
+            &key,
+        )
+        .expect("verify ok.");
+        executor.seal_block(2).unwrap();
+        assert!(matches!(
+            query_receiver.non_membership_proof(&key, BlockId::Number(2)),
+            Err(Error::InState { .. })
+        ));
+    }
+
+    #[test]
+    fn success_logs() {
+        let key = SecretKey::from_slice(H256::random().as_bytes()).unwrap();
+        let alice = signature::address(&key);
+        let (executor, exitor, query_receiver) = query_receiver();
+
+        executor.execute(&deposit(1, alice, 100)).unwrap();
+        executor.seal_block(1).unwrap();
+        executor.execute(&deposit(2, H160::random(), 10)).unwrap();
+        let withdrawal = Withdrawal {
+            account: alice,
+            token: H160::zero(),
+            amount: U256::from(30),
+            nonce: U256::zero(),
+        };
+        let exit = exitor
+            .request_exit(
+                withdrawal.clone(),
+                &signature::sign(&key, &signature::Domain::default(), &withdrawal),
+            )
+            .unwrap();
+        let second = executor.seal_block(2).unwrap().unwrap();
+
+        let alice_filter = LogFilter {
+            from_block: BlockId::Earliest,
+            topics: vec![None, None, Some(vec![alice.into()])],
+            ..LogFilter::default()
+        };
+        let logs = query_receiver.logs(&alice_filter).unwrap();
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].topics[0], executor::deposit_topic());
+        assert_eq!(logs[1].topics[..2], [executor::exit_topic(), exit]);
+
+        let logs = query_receiver
+            .logs(&LogFilter {
+                block_hash: Some(second.hash),
+                ..LogFilter::default()
+            })
+            .unwrap();
+        assert_eq!(logs.len(), 2);
+        assert!(query_receiver
+            .logs(&LogFilter {
+                address: vec![H160::repeat_byte(1)],
+                ..LogFilter::default()
+            })
+            .unwrap()
+            .is_empty());
+
+        assert!(matches!(
+            query_receiver.logs(&LogFilter {
+                from_block: BlockId::Number(2),
+                to_block: BlockId::Number(1),
+                ..LogFilter::default()
+            }),
+            Err(Error::InvalidBlockRange { from: 2, to: 1 })
+        ));
+        let limited = QueryReceiver::new(executor, exitor, 42).with_max_log_blocks(1);
+        assert!(matches!(
+            limited.logs(&alice_filter),
+            Err(Error::BlockRangeTooLarge { limit: 1, .. })
+        ));
+        assert_eq!(
+            limited
+                .logs(&LogFilter {
+                    from_block: BlockId::Number(2),
+                    ..alice_filter
+                })
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn success_filter_changes() {
+        let alice = H160::random();
+        let (executor, exitor, _) = query_receiver();
+        let query_receiver =
+            QueryReceiver::new(executor.clone(), exitor, 42).with_max_log_blocks(1);
+
+        executor.execute(&deposit(1, alice, 100)).unwrap();
+        executor.seal_block(1).unwrap();
+        let id = query_receiver.new_filter(LogFilter {
+            address: vec![H160::zero()],
+            ..LogFilter::default()
+        });
+        assert!(query_receiver.filter_changes(id).unwrap().is_empty());
+
+        executor.execute(&deposit(2, alice, 100)).unwrap();
+        executor.seal_block(2).unwrap();
+        executor.execute(&deposit(3, alice, 100)).unwrap();
+        executor.seal_block(3).unwrap();
+        // The limit of 1 block per poll.
+        let changes = query_receiver.filter_changes(id).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].block_number, 2);
+        let changes = query_receiver.filter_changes(id).unwrap();
+        assert_eq!(changes[0].block_number, 3);
+        assert!(query_receiver.filter_changes(id).unwrap().is_empty());
+
+        assert!(query_receiver.uninstall_filter(id));
+        assert!(!query_receiver.uninstall_filter(id));
+        assert!(matches!(
+            query_receiver.filter_changes(id),
+            Err(Error::UnknownFilter { .. })
+        ));
+    }
+
     #[test]
-    fn it_works() {
-        let result = 2 + 2;
-        assert_eq!(result, 4);
+    fn success_account_transactions() {
+        let alice = H160::random();
+        let (executor, _exitor, query_receiver) = query_receiver();
+
+        for id in 1..4 {
+            executor.execute(&deposit(id, alice, 10)).unwrap();
+            executor.seal_block(id).unwrap();
+        }
+        executor.execute(&deposit(4, alice, 10)).unwrap();
+
+        let page = query_receiver.account_transactions(&alice, None, Some(2));
+        let blocks: Vec<u64> = page.transactions.iter().map(|tx| tx.block).collect();
+        assert_eq!(blocks, vec![3, 2]);
+        assert_eq!(page.transactions[0].transaction, deposit(3, alice, 10));
+        let page = query_receiver.account_transactions(&alice, page.next_cursor, None);
+        assert_eq!(page.transactions.len(), 1);
+        assert_eq!(page.transactions[0].block, 1);
+        assert_eq!(page.next_cursor, None);
+
+        // A limit of 0 still returns a transaction.
+        let page = query_receiver.account_transactions(&alice, None, Some(0));
+        assert_eq!(page.transactions.len(), 1);
     }
 }
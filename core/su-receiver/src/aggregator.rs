@@ -0,0 +1,175 @@
+//! Aggregation of the state updates of every sealed block into one proof of its diff root. The
+//! commiter posts both with the batch in `Rollup.commitBatch`.
+use db::{DBKey, Database};
+use ethereum_types::H256;
+use executor::{Block, Transaction};
+use intmax_zk::{AggregatedProof, ProofSystem, UserStateProof};
+use tracing::info;
+
+use crate::da::{DaBlock, DaStore};
+use crate::error::{Error, Result};
+
+const AGGREGATED_PREFIX: &[u8] = b"aggregated";
+const LATEST_AGGREGATED_KEY: &[u8] = b"latest_aggregated";
+
+/// Aggregates the proofs accepted by the `SuReceiver` with `P`. Both live in `D`, the
+/// `db::columns::SU_RECEIVER` column, and the aggregated state diffs are kept in `da`.
+pub struct Aggregator<D: Database, P: ProofSystem> {
+    db: D,
+    da: DaStore<D>,
+    proof_system: P,
+}
+
+impl<D: Database, P: ProofSystem> Aggregator<D, P> {
+    pub fn new(db: D, da: DaStore<D>, proof_system: P) -> Self {
+        Aggregator {
+            db,
+            da,
+            proof_system,
+        }
+    }
+
+    /// The state diffs of the aggregated blocks.
+    pub fn da(&self) -> &DaStore<D> {
+        &self.da
+    }
+
+    /// The aggregated proof of the block `number`.
+    pub fn aggregated_proof(&self, number: u64) -> Option<AggregatedProof> {
+        self.db.get(&aggregated_key(number))
+    }
+
+    /// The accepted proof of the state diff `hash`.
+    pub fn user_state_proof(&self, hash: &H256) -> Option<UserStateProof> {
+        self.db.get(&DBKey::from_slice(hash.as_bytes()))
+    }
+
+    /// The latest aggregated block, 0 if none.
+    pub fn latest_aggregated_block(&self) -> u64 {
+        self.db
+            .get(&DBKey::from_slice(LATEST_AGGREGATED_KEY))
+            .unwrap_or_default()
+    }
+
+    /// Aggregates the proofs of the state diffs of `block`. A block is aggregated once, later
+    /// calls return the stored proof.
+    pub fn aggregate(&self, block: &Block) -> Result<AggregatedProof> {
+        if let Some(aggregated) = self.aggregated_proof(block.number) {
+            return Ok(aggregated);
+        }
+
+        let proofs = state_diffs(block)
+            .into_iter()
+            .map(|hash| {
+                self.user_state_proof(&hash)
+                    .ok_or(Error::MissingUserStateProof { state_diff: hash })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let aggregated = self.proof_system.aggregate(&proofs)?;
+        let proofs = DaBlock {
+            number: block.number,
+            proofs,
+        };
+        // Made available before the block counts as aggregated.
+        self.da.put_block(&proofs)?;
+
+        let mut tx = self.db.make_tx();
+        tx.put(&aggregated_key(block.number), &aggregated);
+        if block.number > self.latest_aggregated_block() {
+            tx.put(&LATEST_AGGREGATED_KEY, &block.number);
+        }
+        self.db.commit(tx)?;
+
+        info!(
+            "aggregated {} state updates of block {}: diff root {:?}",
+            proofs.proofs.len(),
+            block.number,
+            aggregated.diff_root
+        );
+        Ok(aggregated)
+    }
+}
+
+/// Hashes of the state diffs of `block` in order, the leaves of its `diff_tree`.
+pub fn state_diffs(block: &Block) -> Vec<H256> {
+    block
+        .transactions
+        .iter()
+        .filter_map(|transaction| match transaction {
+            Transaction::UpdateState(diff) => Some(diff.hash()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn aggregated_key(number: u64) -> DBKey {
+    let mut key = DBKey::from_slice(AGGREGATED_PREFIX);
+    key.extend_from_slice(&number.to_be_bytes());
+    key
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::tests::{diff, new_db, su_receiver};
+    use db::Db;
+    use ethereum_types::H160;
+    use executor::ExecutorTrait;
+    use intmax_zk::{diff_tree, DevProofSystem};
+    use kvdb_memorydb::InMemory;
+
+    pub fn aggregator(db: &Db<InMemory>) -> Aggregator<Db<InMemory>, DevProofSystem> {
+        Aggregator::new(
+            db.with_column(db::columns::SU_RECEIVER),
+            DaStore::new(db.with_column(db::columns::DA)),
+            DevProofSystem,
+        )
+    }
+
+    #[test]
+    fn success_aggregate() {
+        let db = new_db();
+        let su_receiver = su_receiver(&db);
+        let aggregator = aggregator(&db);
+        let user = H160::random();
+
+        for nonce in 0..2 {
+            su_receiver
+                .accept(&DevProofSystem.prove(&diff(user, nonce)))
+                .expect("accept ok.");
+        }
+        let block = su_receiver.executor.seal_block(1).unwrap().unwrap();
+
+        let aggregated = aggregator.aggregate(&block).expect("aggregate ok.");
+        let hashes = state_diffs(&block);
+        assert_eq!(hashes, vec![diff(user, 0).hash(), diff(user, 1).hash()]);
+        assert_eq!(aggregated.diff_root, diff_tree::root(&hashes));
+        DevProofSystem
+            .verify_aggregated(&aggregated)
+            .expect("verify ok.");
+        assert_eq!(aggregator.aggregated_proof(1), Some(aggregated.clone()));
+        assert_eq!(aggregator.latest_aggregated_block(), 1);
+        assert_eq!(aggregator.da().block_diffs(1), Some(hashes));
+        assert_eq!(aggregator.aggregate(&block).unwrap(), aggregated);
+    }
+
+    #[test]
+    fn fail_aggregate_without_proof() {
+        let db = new_db();
+        let su_receiver = su_receiver(&db);
+        let aggregator = aggregator(&db);
+
+        // Applied without going through the su-receiver.
+        su_receiver
+            .executor
+            .execute(&Transaction::UpdateState(diff(H160::random(), 0)))
+            .unwrap();
+        let block = su_receiver.executor.seal_block(1).unwrap().unwrap();
+
+        assert!(matches!(
+            aggregator.aggregate(&block),
+            Err(Error::MissingUserStateProof { .. })
+        ));
+        assert_eq!(aggregator.latest_aggregated_block(), 0);
+    }
+}
@@ -0,0 +1,237 @@
+//! Data availability of the state diffs: every sealed state diff and its witness, the proof
+//! submitted with it, stored by state diff hash so that users can always rebuild their state.
+//!
+//! An export is `EXPORT_MAGIC`, then the SCALE encoded `DaBlock`s in order.
+use codec::{Decode, Encode};
+use db::{DBKey, Database};
+use ethereum_types::H256;
+use intmax_zk::{diff_tree, UserStateProof};
+
+use crate::error::{Error, Result};
+
+const BLOCK_PREFIX: &[u8] = b"block";
+const EXPORT_MAGIC: &[u8] = b"intmax-da-v1";
+
+/// The state diffs of a block and their witnesses, in order.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct DaBlock {
+    pub number: u64,
+    pub proofs: Vec<UserStateProof>,
+}
+
+impl DaBlock {
+    /// Hashes of the state diffs in order, the leaves of the `diff_tree` of the block.
+    pub fn diff_hashes(&self) -> Vec<H256> {
+        self.proofs.iter().map(|proof| proof.diff.hash()).collect()
+    }
+}
+
+/// Content addressed store of the state diffs in `D`, the `db::columns::DA` column.
+pub struct DaStore<D: Database> {
+    db: D,
+}
+
+impl<D: Database> DaStore<D> {
+    pub fn new(db: D) -> Self {
+        DaStore { db }
+    }
+
+    /// The state diff `hash` and its witness.
+    pub fn get(&self, hash: &H256) -> Option<UserStateProof> {
+        self.db.get(&DBKey::from_slice(hash.as_bytes()))
+    }
+
+    /// Hashes of the state diffs of the block `number` in order, `None` if it is not stored.
+    pub fn block_diffs(&self, number: u64) -> Option<Vec<H256>> {
+        self.db.get(&block_key(number))
+    }
+
+    /// The block `number` with its state diffs, `None` if it is not stored.
+    pub fn block(&self, number: u64) -> Option<DaBlock> {
+        let proofs = self
+            .block_diffs(number)?
+            .iter()
+            .map(|hash| self.get(hash))
+            .collect::<Option<_>>()?;
+
+        Some(DaBlock { number, proofs })
+    }
+
+    /// Stores the state diffs of `block`, each under its own hash.
+    pub fn put_block(&self, block: &DaBlock) -> Result<()> {
+        let mut tx = self.db.make_tx();
+        let hashes = block.diff_hashes();
+        for (hash, proof) in hashes.iter().zip(block.proofs.iter()) {
+            tx.put(&hash.as_bytes(), proof);
+        }
+        tx.put(&block_key(block.number), &hashes);
+        self.db.commit(tx)?;
+
+        Ok(())
+    }
+
+    /// Exports the stored blocks from `from` to `to`, both included. Blocks without stored
+    /// state diffs are skipped.
+    pub fn export(&self, from: u64, to: u64) -> Vec<u8> {
+        let mut out = EXPORT_MAGIC.to_vec();
+        for number in from..=to {
+            if let Some(block) = self.block(number) {
+                block.encode_to(&mut out);
+            }
+        }
+
+        out
+    }
+
+    /// Imports the blocks of an `export`, each checked against `diff_root`, the diff root
+    /// committed on L1 of a block number. Blocks already stored are skipped, and nothing is
+    /// written unless every block checks out. Returns the number of imported blocks.
+    pub fn import<F>(&self, data: &[u8], diff_root: F) -> Result<u64>
+    where
+        F: Fn(u64) -> Option<H256>,
+    {
+        let mut input = data
+            .strip_prefix(EXPORT_MAGIC)
+            .ok_or_else(|| Error::InvalidDaExport {
+                reason: "unknown format".to_string(),
+            })?;
+
+        let mut blocks = Vec::new();
+        while !input.is_empty() {
+            let block = DaBlock::decode(&mut input).map_err(|e| Error::InvalidDaExport {
+                reason: e.to_string(),
+            })?;
+            let hashes = block.diff_hashes();
+            let actual = diff_tree::root(&hashes);
+            let committed = diff_root(block.number);
+            if committed != Some(actual) {
+                return Err(Error::DiffRootMismatch {
+                    number: block.number,
+                    committed,
+                    actual,
+                });
+            }
+
+            match self.block_diffs(block.number) {
+                Some(stored) if stored == hashes => (),
+                Some(_) => {
+                    return Err(Error::ConflictingDaBlock {
+                        number: block.number,
+                    })
+                }
+                None => blocks.push(block),
+            }
+        }
+
+        for block in blocks.iter() {
+            self.put_block(block)?;
+        }
+
+        Ok(blocks.len() as u64)
+    }
+}
+
+fn block_key(number: u64) -> DBKey {
+    let mut key = DBKey::from_slice(BLOCK_PREFIX);
+    key.extend_from_slice(&number.to_be_bytes());
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{diff, new_db};
+    use ethereum_types::H160;
+    use intmax_zk::DevProofSystem;
+
+    fn da_block(number: u64, user: H160) -> DaBlock {
+        DaBlock {
+            number,
+            proofs: vec![DevProofSystem.prove(&diff(user, number))],
+        }
+    }
+
+    #[test]
+    fn success_export_import() {
+        let user = H160::random();
+        let da = DaStore::new(new_db().with_column(db::columns::DA));
+        for number in [1, 3] {
+            da.put_block(&da_block(number, user)).unwrap();
+        }
+
+        let proof = DevProofSystem.prove(&diff(user, 3));
+        assert_eq!(da.get(&proof.diff.hash()), Some(proof.clone()));
+        assert_eq!(da.block_diffs(3), Some(vec![proof.diff.hash()]));
+        assert_eq!(da.block(2), None);
+
+        let export = da.export(1, 3);
+        let other = DaStore::new(new_db().with_column(db::columns::DA));
+        let committed = |number| Some(diff_tree::root(&da_block(number, user).diff_hashes()));
+        assert_eq!(other.import(&export, committed).unwrap(), 2);
+        assert_eq!(other.block(1), Some(da_block(1, user)));
+        assert_eq!(other.get(&proof.diff.hash()), Some(proof));
+        assert_eq!(other.export(1, 3), export);
+
+        // Stored blocks are skipped.
+        assert_eq!(other.import(&export, committed).unwrap(), 0);
+    }
+
+    #[test]
+    fn fail_import_not_committed() {
+        let user = H160::random();
+        let da = DaStore::new(new_db().with_column(db::columns::DA));
+        for number in [1, 2] {
+            da.put_block(&da_block(number, user)).unwrap();
+        }
+        let export = da.export(1, 2);
+        let committed = |number| Some(diff_tree::root(&da_block(number, user).diff_hashes()));
+
+        let other = DaStore::new(new_db().with_column(db::columns::DA));
+        let err = other
+            .import(
+                &export,
+                |number| if number == 2 { None } else { committed(number) },
+            )
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::DiffRootMismatch {
+                number: 2,
+                committed: None,
+                ..
+            }
+        ));
+        let err = other
+            .import(&export, |number| {
+                if number == 2 {
+                    Some(H256::repeat_byte(1))
+                } else {
+                    committed(number)
+                }
+            })
+            .unwrap_err();
+        assert!(matches!(err, Error::DiffRootMismatch { number: 2, .. }));
+        // Nothing is written.
+        assert_eq!(other.block(1), None);
+
+        other.put_block(&da_block(2, H160::random())).unwrap();
+        let err = other.import(&export, committed).unwrap_err();
+        assert!(matches!(err, Error::ConflictingDaBlock { number: 2 }));
+    }
+
+    #[test]
+    fn fail_import_malformed() {
+        let da = DaStore::new(new_db().with_column(db::columns::DA));
+
+        assert!(matches!(
+            da.import(b"intmax-da-v0", |_| None),
+            Err(Error::InvalidDaExport { .. })
+        ));
+        let mut export = EXPORT_MAGIC.to_vec();
+        export.extend_from_slice(&[1, 2, 3]);
+        assert!(matches!(
+            da.import(&export, |_| None),
+            Err(Error::InvalidDaExport { .. })
+        ));
+    }
+}
@@ -0,0 +1,127 @@
+use ethereum_types::H256;
+use executor::Error as ExecutorError;
+use jsonrpc_core as rpc;
+
+/// Su Receiver Result type.
+pub type Result<T> = std::result::Result<T, Error>;
+
+pub fn internal<E: ::std::fmt::Debug>(e: E) -> rpc::Error {
+    jsonrpc_core::Error {
+        code: rpc::ErrorCode::InternalError,
+        message: "Unknown error occurred".into(),
+        data: Some(format!("{:?}", e).into()),
+    }
+}
+
+/// Su Receiver errors.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Error from the executor.
+    #[error("Executor error: {}", .0)]
+    Executor(#[from] ExecutorError),
+
+    /// Error from the database.
+    #[error("Database error: {}", .0)]
+    Db(#[from] std::io::Error),
+
+    /// If StateDiff has already been applied
+    #[error("StateDiff({}) has already been used", .state_diff)]
+    InvalidStateDiff {
+        /// State diff already used
+        state_diff: H256,
+    },
+
+    /// Error when user_state_proof is invalid.
+    #[error("UserStateProof({}) is invalid", .user_state_proof)]
+    InvalidUserStateZKP {
+        /// Invalid user state proof
+        user_state_proof: String,
+    },
+
+    /// Error from the proof system.
+    #[error("Proof system error: {}", .0)]
+    Zk(#[from] intmax_zk::Error),
+
+    /// If a sealed state diff was not accepted by the su-receiver.
+    #[error("Proof of StateDiff({}) is not found", .state_diff)]
+    MissingUserStateProof {
+        /// State diff without proof
+        state_diff: H256,
+    },
+
+    /// If data to import is not a state diff export.
+    #[error("Invalid state diff export: {}", .reason)]
+    InvalidDaExport {
+        /// Why the data cannot be imported
+        reason: String,
+    },
+
+    /// If a block to import does not have the diff root committed on L1.
+    #[error(
+        "Diff root of block {} is {}, but {:?} is committed on L1",
+        .number,
+        .actual,
+        .committed
+    )]
+    DiffRootMismatch {
+        /// Block number
+        number: u64,
+        /// Diff root committed on L1, `None` if the block is not committed
+        committed: Option<H256>,
+        /// Diff root of the imported state diffs
+        actual: H256,
+    },
+
+    /// If a block to import is already stored with other state diffs.
+    #[error("Block {} is already stored with other state diffs", .number)]
+    ConflictingDaBlock {
+        /// Block number
+        number: u64,
+    },
+
+    /// If the su-receiver task is not running.
+    #[error("Su receiver is stopped")]
+    Stopped,
+}
+
+impl From<Error> for rpc::Error {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::InvalidStateDiff { .. }
+            | Error::InvalidUserStateZKP { .. }
+            | Error::Executor(ExecutorError::StaleUserState { .. }) => rpc::Error {
+                code: rpc::ErrorCode::InvalidParams,
+                message: format!("{}", e),
+                data: None,
+            },
+            e => internal(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethereum_types::H160;
+
+    #[test]
+    fn success_type_conversion_into_core_all() {
+        let e: rpc::Error = Error::InvalidStateDiff {
+            state_diff: H256::zero(),
+        }
+        .into();
+        assert_eq!(e.code, rpc::ErrorCode::InvalidParams);
+        assert_eq!(e.message, "StateDiff(0x0000…0000) has already been used");
+
+        let e: rpc::Error = Error::Executor(ExecutorError::StaleUserState {
+            user: H160::zero(),
+            root: H256::zero(),
+            nonce: 1,
+        })
+        .into();
+        assert_eq!(e.code, rpc::ErrorCode::InvalidParams);
+
+        let e: rpc::Error = Error::Stopped.into();
+        assert_eq!(e.code, rpc::ErrorCode::InternalError);
+    }
+}
@@ -1,16 +1,230 @@
-pub struct SuReceiver;
+//! Receives the state updates of the users, who hold their own state.
+//!
+//! Submissions are verified one at a time by the `SuReceiver::run` task, so that proof
+//! verification does not run on the RPC workers. Accepted state diffs are handed to the
+//! executor and sealed by the block producer like any other transaction. Once sealed, the
+//! `Aggregator` proves the state diffs of each block at once and keeps them in the `DaStore`,
+//! and the `StateSyncer` serves them back to their users.
+use std::sync::Arc;
 
-impl SuReceiver {
-    pub fn run() {
-        println!("TODO:: Su Receiver")
+use db::{DBKey, Database};
+use ethereum_types::H256;
+use executor::{Executor, ExecutorTrait, Transaction};
+use intmax_verkle::VerkleStorage;
+use intmax_zk::ProofSystem;
+use jsonrpc_core::BoxFuture;
+use tokio::sync::{mpsc, oneshot};
+use tracing::info;
+
+pub use aggregator::{state_diffs, Aggregator};
+pub use da::{DaBlock, DaStore};
+pub use error::{Error, Result};
+pub use intmax_zk::{AggregatedProof, StateDiff, UserStateProof};
+pub use sync::{StateSync, StateSyncTrait, StateSyncer, SyncBlock, SyncUpdate, MAX_SYNC_BLOCKS};
+
+mod aggregator;
+mod da;
+mod error;
+mod sync;
+
+/// A submitted proof and where to send the result of its verification.
+pub type Submission = (UserStateProof, oneshot::Sender<Result<H256>>);
+
+pub trait SuReceiverTrait {
+    /// Verifies `proof` and hands its state diff to the block producer. Returns the hash of
+    /// the state diff.
+    fn submit_state_update(&self, proof: UserStateProof) -> BoxFuture<Result<H256>>;
+}
+
+/// Verifies the proofs of the state updates with `P` and keeps the accepted ones in `D`, the
+/// `db::columns::SU_RECEIVER` column, by state diff hash.
+pub struct SuReceiver<S: VerkleStorage, D: Database, P: ProofSystem> {
+    executor: Arc<Executor<S, D>>,
+    db: D,
+    proof_system: P,
+}
+
+impl<S: VerkleStorage, D: Database, P: ProofSystem> SuReceiver<S, D, P> {
+    pub fn new(executor: Arc<Executor<S, D>>, db: D, proof_system: P) -> Self {
+        SuReceiver {
+            executor,
+            db,
+            proof_system,
+        }
+    }
+
+    /// The accepted proof of the state diff `hash`.
+    pub fn user_state_proof(&self, hash: &H256) -> Option<UserStateProof> {
+        self.db.get(&DBKey::from_slice(hash.as_bytes()))
+    }
+
+    /// Verifies `proof` and applies its state diff. Returns the hash of the state diff.
+    pub fn accept(&self, proof: &UserStateProof) -> Result<H256> {
+        let hash = proof.diff.hash();
+        if self.executor.is_state_diff_applied(&hash) {
+            return Err(Error::InvalidStateDiff { state_diff: hash });
+        }
+
+        self.proof_system
+            .verify_user_state(proof)
+            .map_err(|e| Error::InvalidUserStateZKP {
+                user_state_proof: e.to_string(),
+            })?;
+
+        self.executor
+            .execute(&Transaction::UpdateState(proof.diff.clone()))
+            .map_err(|e| match e {
+                executor::Error::DuplicateStateDiff { state_diff } => {
+                    Error::InvalidStateDiff { state_diff }
+                }
+                e => e.into(),
+            })?;
+
+        let mut tx = self.db.make_tx();
+        tx.put(&hash.as_bytes(), proof);
+        self.db.commit(tx)?;
+
+        info!("accepted state update {:?} of {:?}", hash, proof.diff.user);
+        Ok(hash)
+    }
+
+    /// Verifies the submissions received from `SuReceiverClient`s until they are all dropped.
+    pub async fn run(self, mut submissions: mpsc::Receiver<Submission>) {
+        while let Some((proof, reply)) = submissions.recv().await {
+            // The submitter may have given up waiting, the update is applied anyway.
+            let _ = reply.send(self.accept(&proof));
+        }
+    }
+}
+
+/// Submits state updates to the `SuReceiver::run` task.
+#[derive(Clone)]
+pub struct SuReceiverClient {
+    sender: mpsc::Sender<Submission>,
+}
+
+/// A client and the submissions it sends, to be given to `SuReceiver::run`. At most `buffer`
+/// submissions wait for verification.
+pub fn channel(buffer: usize) -> (SuReceiverClient, mpsc::Receiver<Submission>) {
+    let (sender, submissions) = mpsc::channel(buffer);
+    (SuReceiverClient { sender }, submissions)
+}
+
+impl SuReceiverTrait for SuReceiverClient {
+    fn submit_state_update(&self, proof: UserStateProof) -> BoxFuture<Result<H256>> {
+        let sender = self.sender.clone();
+        Box::pin(async move {
+            let (reply, result) = oneshot::channel();
+            sender
+                .send((proof, reply))
+                .await
+                .map_err(|_| Error::Stopped)?;
+            result.await.map_err(|_| Error::Stopped)?
+        })
     }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
+    use super::*;
+    use db::Db;
+    use ethereum_types::H160;
+    use intmax_verkle::VerkleTree;
+    use intmax_zk::DevProofSystem;
+    use kvdb_memorydb::InMemory;
+
+    type TestSuReceiver = SuReceiver<VerkleTree<Db<InMemory>>, Db<InMemory>, DevProofSystem>;
+
+    pub fn su_receiver(db: &Db<InMemory>) -> TestSuReceiver {
+        let executor = Arc::new(Executor::new(
+            VerkleTree::new(db.with_column(db::columns::STATE)),
+            db.with_column(db::columns::EXECUTOR),
+        ));
+
+        SuReceiver::new(
+            executor,
+            db.with_column(db::columns::SU_RECEIVER),
+            DevProofSystem,
+        )
+    }
+
+    pub fn diff(user: H160, nonce: u64) -> StateDiff {
+        StateDiff {
+            user,
+            old_root: H256::from_low_u64_be(nonce),
+            new_root: H256::from_low_u64_be(nonce + 1),
+            nonce,
+        }
+    }
+
+    pub fn new_db() -> Db<InMemory> {
+        Db::new(kvdb_memorydb::create(db::columns::NUM_COLUMNS), 0)
+    }
+
     #[test]
-    fn it_works() {
-        let result = 2 + 2;
-        assert_eq!(result, 4);
+    fn success_accept() {
+        let su_receiver = su_receiver(&new_db());
+        let user = H160::random();
+        let first = StateDiff {
+            old_root: H256::zero(),
+            ..diff(user, 0)
+        };
+
+        let proof = DevProofSystem.prove(&first);
+        let hash = su_receiver.accept(&proof).expect("accept ok.");
+        assert_eq!(hash, first.hash());
+        assert_eq!(su_receiver.user_state_proof(&hash), Some(proof.clone()));
+        assert_eq!(
+            su_receiver.executor.pending_transactions(),
+            vec![Transaction::UpdateState(first)]
+        );
+
+        assert!(matches!(
+            su_receiver.accept(&proof),
+            Err(Error::InvalidStateDiff { .. })
+        ));
+
+        let mut forged = DevProofSystem.prove(&diff(user, 1));
+        forged.proof[0] ^= 1;
+        assert!(matches!(
+            su_receiver.accept(&forged),
+            Err(Error::InvalidUserStateZKP { .. })
+        ));
+        assert!(matches!(
+            su_receiver.accept(&DevProofSystem.prove(&diff(user, 2))),
+            Err(Error::Executor(executor::Error::StaleUserState { .. }))
+        ));
+        su_receiver
+            .accept(&DevProofSystem.prove(&diff(user, 1)))
+            .expect("accept ok.");
+    }
+
+    #[tokio::test]
+    async fn success_submit_state_update() {
+        let su_receiver = su_receiver(&new_db());
+        let (client, submissions) = channel(1);
+        let task = tokio::spawn(su_receiver.run(submissions));
+
+        let first = StateDiff {
+            old_root: H256::zero(),
+            ..diff(H160::random(), 0)
+        };
+        let proof = DevProofSystem.prove(&first);
+        let hash = client
+            .submit_state_update(proof.clone())
+            .await
+            .expect("submit ok.");
+        assert_eq!(hash, first.hash());
+        assert!(matches!(
+            client.submit_state_update(proof.clone()).await,
+            Err(Error::InvalidStateDiff { .. })
+        ));
+
+        task.abort();
+        let _ = task.await;
+        assert!(matches!(
+            client.submit_state_update(proof).await,
+            Err(Error::Stopped)
+        ));
     }
 }
@@ -0,0 +1,259 @@
+//! What a user holding its own state fetches after each block: the aggregated blocks with its
+//! state updates, and the proofs of their inclusion under the diff root of the block.
+use std::sync::Arc;
+
+use db::Database;
+use ethereum_types::{H160, H256};
+use executor::{Executor, Transaction};
+use intmax_verkle::VerkleStorage;
+use intmax_zk::{diff_tree, AggregatedProof, ProofSystem, UserStateProof};
+
+use crate::aggregator::Aggregator;
+use crate::error::{Error, Result};
+
+/// Most blocks scanned by a `StateSyncTrait::state_sync` call.
+pub const MAX_SYNC_BLOCKS: u64 = 100;
+
+/// A state update of the user in a block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyncUpdate {
+    /// Position of the state diff in the `diff_tree` of the block.
+    pub index: u64,
+    /// The state diff and the proof submitted with it.
+    pub proof: UserStateProof,
+    /// Siblings of the state diff up to the diff root.
+    pub diff_proof: Vec<Option<H256>>,
+}
+
+/// A block with state updates of the user.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyncBlock {
+    pub number: u64,
+    pub hash: H256,
+    pub parent_hash: H256,
+    pub timestamp: u64,
+    pub aggregated: AggregatedProof,
+    pub updates: Vec<SyncUpdate>,
+}
+
+/// A page of blocks and where to resume.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateSync {
+    pub blocks: Vec<SyncBlock>,
+    /// The first block not scanned yet.
+    pub next_block: u64,
+}
+
+pub trait StateSyncTrait {
+    /// The blocks with state updates of `user` among at most `limit` aggregated blocks from
+    /// `from` on. `limit` is capped by `MAX_SYNC_BLOCKS`.
+    fn state_sync(&self, user: &H160, from: u64, limit: u64) -> Result<StateSync>;
+
+    /// The sealed state diff `hash` and its witness.
+    fn state_diff(&self, hash: &H256) -> Option<UserStateProof>;
+}
+
+/// Serves the blocks aggregated by the `Aggregator`.
+pub struct StateSyncer<S: VerkleStorage, D: Database, P: ProofSystem> {
+    executor: Arc<Executor<S, D>>,
+    aggregator: Aggregator<D, P>,
+}
+
+impl<S: VerkleStorage, D: Database, P: ProofSystem> StateSyncer<S, D, P> {
+    pub fn new(executor: Arc<Executor<S, D>>, aggregator: Aggregator<D, P>) -> Self {
+        StateSyncer {
+            executor,
+            aggregator,
+        }
+    }
+
+    /// The state updates of `user` in the block `number`, `None` if the block is not aggregated.
+    fn sync_block(&self, user: &H160, number: u64) -> Result<Option<SyncBlock>> {
+        let (block, aggregated) = match (
+            self.executor.block(number),
+            self.aggregator.aggregated_proof(number),
+        ) {
+            (Some(block), Some(aggregated)) => (block, aggregated),
+            _ => return Ok(None),
+        };
+
+        let diffs: Vec<_> = block
+            .transactions
+            .iter()
+            .filter_map(|transaction| match transaction {
+                Transaction::UpdateState(diff) => Some(diff),
+                _ => None,
+            })
+            .collect();
+        let hashes: Vec<H256> = diffs.iter().map(|diff| diff.hash()).collect();
+        let mut updates = Vec::new();
+        for (index, diff) in diffs.iter().enumerate() {
+            if diff.user != *user {
+                continue;
+            }
+
+            let proof =
+                self.aggregator
+                    .da()
+                    .get(&hashes[index])
+                    .ok_or(Error::MissingUserStateProof {
+                        state_diff: hashes[index],
+                    })?;
+            updates.push(SyncUpdate {
+                index: index as u64,
+                proof,
+                diff_proof: diff_tree::proof(&hashes, index),
+            });
+        }
+
+        Ok(Some(SyncBlock {
+            number,
+            hash: block.hash,
+            parent_hash: block.parent_hash,
+            timestamp: block.timestamp,
+            aggregated,
+            updates,
+        }))
+    }
+}
+
+impl<S: VerkleStorage, D: Database, P: ProofSystem> StateSyncTrait for StateSyncer<S, D, P> {
+    fn state_sync(&self, user: &H160, from: u64, limit: u64) -> Result<StateSync> {
+        let from = from.max(1);
+        let end = self
+            .aggregator
+            .latest_aggregated_block()
+            .min(from.saturating_add(limit.clamp(1, MAX_SYNC_BLOCKS) - 1));
+
+        let mut blocks = Vec::new();
+        for number in from..=end {
+            match self.sync_block(user, number)? {
+                Some(block) if block.updates.is_empty() => (),
+                Some(block) => blocks.push(block),
+                None => {
+                    return Ok(StateSync {
+                        blocks,
+                        next_block: number,
+                    })
+                }
+            }
+        }
+
+        Ok(StateSync {
+            blocks,
+            next_block: end.max(from - 1) + 1,
+        })
+    }
+
+    fn state_diff(&self, hash: &H256) -> Option<UserStateProof> {
+        self.aggregator.da().get(hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aggregator::tests::aggregator;
+    use crate::tests::{diff, new_db, su_receiver};
+    use executor::ExecutorTrait;
+    use intmax_zk::DevProofSystem;
+
+    #[test]
+    fn success_state_sync() {
+        let db = new_db();
+        let su_receiver = su_receiver(&db);
+        let aggregator = || aggregator(&db);
+        let executor = su_receiver.executor.clone();
+        let syncer = StateSyncer::new(executor.clone(), aggregator());
+        let (alice, bob) = (H160::random(), H160::random());
+
+        su_receiver
+            .accept(&DevProofSystem.prove(&diff(bob, 0)))
+            .unwrap();
+        su_receiver
+            .accept(&DevProofSystem.prove(&diff(alice, 0)))
+            .unwrap();
+        let first = executor.seal_block(1).unwrap().unwrap();
+        su_receiver
+            .accept(&DevProofSystem.prove(&diff(bob, 1)))
+            .unwrap();
+        executor.seal_block(2).unwrap();
+        su_receiver
+            .accept(&DevProofSystem.prove(&diff(alice, 1)))
+            .unwrap();
+        executor.seal_block(3).unwrap();
+
+        // Nothing is served before aggregation.
+        assert_eq!(
+            syncer.state_sync(&alice, 0, 10).unwrap(),
+            StateSync {
+                blocks: vec![],
+                next_block: 1,
+            }
+        );
+        for number in 1..=2 {
+            aggregator()
+                .aggregate(&executor.block(number).unwrap())
+                .unwrap();
+        }
+
+        let page = syncer.state_sync(&alice, 0, 10).unwrap();
+        assert_eq!(page.next_block, 3);
+        assert_eq!(page.blocks.len(), 1);
+        let block = &page.blocks[0];
+        assert_eq!((block.number, block.hash), (1, first.hash));
+        assert_eq!(block.updates.len(), 1);
+        let update = &block.updates[0];
+        assert_eq!(update.index, 1);
+        assert_eq!(update.proof, DevProofSystem.prove(&diff(alice, 0)));
+        assert_eq!(
+            syncer.state_diff(&diff(alice, 0).hash()),
+            Some(update.proof.clone())
+        );
+        assert!(diff_tree::verify(
+            &block.aggregated.diff_root,
+            &diff(alice, 0).hash(),
+            update.index as usize,
+            &update.diff_proof
+        ));
+
+        // Resumes from `next_block` once block 3 is aggregated.
+        aggregator().aggregate(&executor.block(3).unwrap()).unwrap();
+        let page = syncer.state_sync(&alice, page.next_block, 10).unwrap();
+        assert_eq!(page.next_block, 4);
+        assert_eq!(page.blocks[0].number, 3);
+
+        let page = syncer.state_sync(&bob, 1, 1).unwrap();
+        assert_eq!(page.next_block, 2);
+        assert_eq!(page.blocks[0].updates[0].index, 0);
+    }
+
+    #[test]
+    fn success_state_sync_skips_blocks_without_updates() {
+        let db = new_db();
+        let su_receiver = su_receiver(&db);
+        let aggregator = aggregator(&db);
+        let executor = su_receiver.executor.clone();
+
+        executor
+            .execute(&Transaction::Deposit(executor::Deposit {
+                id: H256::random(),
+                l1_block: 1,
+                recipient: H160::random(),
+                token: H160::zero(),
+                amount: 100.into(),
+            }))
+            .unwrap();
+        let block = executor.seal_block(1).unwrap().unwrap();
+        aggregator.aggregate(&block).unwrap();
+
+        let syncer = StateSyncer::new(executor, aggregator);
+        assert_eq!(
+            syncer.state_sync(&H160::random(), 1, 10).unwrap(),
+            StateSync {
+                blocks: vec![],
+                next_block: 2,
+            }
+        );
+    }
+}
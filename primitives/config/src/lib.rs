@@ -8,7 +8,7 @@ pub struct RpcServerConfig {
     pub ip: String,
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, PartialEq, Clone)]
 pub enum Scheme {
     Http,
     Https,
@@ -33,12 +33,14 @@ impl Display for Scheme {
     }
 }
 
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Deserialize, Default, Clone)]
 pub struct EthConfig {
     pub committer_key: String,
     pub port: u16,
     pub host: String,
     pub scheme: Scheme,
+    /// Network under `service/eth-provider/src/artifacts` whose contract addresses are used.
+    pub network: String,
 }
 
 impl EthConfig {
@@ -55,11 +57,80 @@ impl EthConfig {
     }
 }
 
+/// Scheme of the state tree, see `intmax_verkle::verifier`.
+#[derive(Debug, Deserialize, PartialEq, Clone)]
+pub enum StateTree {
+    Verkle,
+    SparseMerkle,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DbConfig {
+    /// Directory of the node database.
+    pub path: String,
+    /// Cannot change once the database holds a state.
+    pub state_tree: StateTree,
+    /// Number of state tree nodes read from the database kept in memory.
+    pub state_cache_nodes: usize,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct DepositConfig {
+    /// L1 block to start watching from when there is no saved cursor, e.g. the rollup deployment.
+    pub from_block: u64,
+    /// Number of L1 blocks on top of a deposit before it is applied.
+    pub confirmations: u64,
+    /// Polling interval when the L1 endpoint is http(s).
+    pub poll_interval_secs: u64,
+    /// Contract names of the ERC-20 tokens whose direct transfers to the rollup are deposits.
+    pub tokens: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct ChainConfig {
+    /// Returned by `eth_chainId`. Distinct from the chain id of the L1 network.
+    pub chain_id: u64,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct BlockConfig {
+    /// Interval at which pending transactions are sealed into a block.
+    pub interval_secs: u64,
+    /// Number of past blocks whose state tree is kept for proofs, besides the blocks from the
+    /// latest batch committed on L1 or the oldest batch with an unclaimed exit.
+    pub state_version_blocks: u64,
+    /// Interval at which the state tree of the blocks out of `state_version_blocks` is pruned.
+    pub prune_interval_secs: u64,
+    /// Largest block range scanned by `eth_getLogs`, and by one `eth_getFilterChanges` poll.
+    pub max_log_blocks: u64,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct ExitConfig {
+    /// Interval at which the latest batches committed and finalized on L1 are polled.
+    pub finality_poll_interval_secs: u64,
+    /// Interval at which finalized exits are put into a withdrawal batch submitted to L1.
+    pub withdrawal_batch_interval_secs: u64,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct ProofConfig {
+    /// Verifies the user state proofs with `intmax_zk::DevProofSystem`, whose proofs anyone can
+    /// build. There is no production proof system yet, so the node does not start without it.
+    pub dev_proof_system: bool,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Config {
     pub http_server: RpcServerConfig,
     pub ws_server: RpcServerConfig,
     pub eth_server: EthConfig,
+    pub db: DbConfig,
+    pub chain: ChainConfig,
+    pub deposit: DepositConfig,
+    pub block: BlockConfig,
+    pub exit: ExitConfig,
+    pub proof: ProofConfig,
 }
 
 pub enum ConfigKind {
@@ -108,6 +179,22 @@ mod tests {
         assert_eq!(config.eth_server.port, 8545);
         assert_eq!(config.eth_server.host, "127.0.0.1");
         assert_eq!(config.eth_server.scheme, Scheme::Http);
+        assert_eq!(config.eth_server.network, "localhost");
+        assert_eq!(config.db.path, ".intmax/test_db");
+        assert_eq!(config.db.state_tree, StateTree::Verkle);
+        assert_eq!(config.db.state_cache_nodes, 4096);
+        assert_eq!(config.chain.chain_id, 20220);
+        assert_eq!(config.deposit.from_block, 0);
+        assert_eq!(config.deposit.confirmations, 0);
+        assert_eq!(config.deposit.poll_interval_secs, 1);
+        assert_eq!(config.deposit.tokens, vec!["MyCoin".to_string()]);
+        assert_eq!(config.block.interval_secs, 1);
+        assert_eq!(config.block.state_version_blocks, 16);
+        assert_eq!(config.block.prune_interval_secs, 1);
+        assert_eq!(config.block.max_log_blocks, 8);
+        assert_eq!(config.exit.finality_poll_interval_secs, 1);
+        assert_eq!(config.exit.withdrawal_batch_interval_secs, 1);
+        assert!(config.proof.dev_proof_system);
         assert_eq!(config.eth_server.node_url(), "http://127.0.0.1:8545");
         assert!(config.eth_server.is_http());
         assert!(!config.eth_server.is_ws());
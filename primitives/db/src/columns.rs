@@ -0,0 +1,24 @@
+//! Columns of the node database. Each component keeps its data in its own column,
+//! so that opening the database only needs `NUM_COLUMNS`.
+use crate::ColumnId;
+
+/// Account state (`intmax-verkle` storage): the tree nodes of every kept block, and the
+/// versions from which replaced nodes are stale.
+pub const STATE: ColumnId = 0;
+/// Executor bookkeeping, e.g. deposits that have already been applied, blocks.
+pub const EXECUTOR: ColumnId = 1;
+/// L1 deposit watcher cursor.
+pub const DEPOSIT_WATCHER: ColumnId = 2;
+/// Exit requests, withdrawal batches and the latest batch finalized on L1.
+pub const EXITOR: ColumnId = 3;
+/// Logs of the sealed blocks, by block number.
+pub const LOGS: ColumnId = 4;
+/// Hashes of the sealed transactions of every account.
+pub const ACCOUNT_TXS: ColumnId = 5;
+/// Proofs of the user state updates accepted by the su-receiver, and their aggregation.
+pub const SU_RECEIVER: ColumnId = 6;
+/// State diffs and witnesses of the sealed blocks, by state diff hash.
+pub const DA: ColumnId = 7;
+
+/// Number of columns the node database has to be opened with.
+pub const NUM_COLUMNS: u32 = 8;
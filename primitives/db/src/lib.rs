@@ -1,20 +1,40 @@
 use std::io;
+use std::sync::Arc;
 
 use codec::{Decode, Encode};
 pub use kvdb::DBKey;
 use kvdb::{DBTransaction, DBValue, KeyValueDB};
 
+pub mod columns;
+
 /// An identifier for a column.
 pub type ColumnId = u32;
 
 pub struct Db<DB: KeyValueDB> {
-    db: DB,
+    db: Arc<DB>,
     col: ColumnId,
 }
 
 impl<DB: KeyValueDB> Db<DB> {
     pub fn new(db: DB, col: ColumnId) -> Db<DB> {
-        Db { db, col }
+        Db {
+            db: Arc::new(db),
+            col,
+        }
+    }
+
+    /// Another column of the same underlying database.
+    pub fn with_column(&self, col: ColumnId) -> Db<DB> {
+        Db {
+            db: self.db.clone(),
+            col,
+        }
+    }
+}
+
+impl<DB: KeyValueDB> Clone for Db<DB> {
+    fn clone(&self) -> Self {
+        self.with_column(self.col)
     }
 }
 
@@ -49,6 +69,12 @@ pub trait Database: Send + Sync {
 
     fn get_raw(&self, key: &DBKey) -> Option<DBValue>;
 
+    /// Create a transaction writing to this database.
+    fn make_tx(&self) -> DBTx;
+
+    /// Create a transaction writing to this database with a preallocated capacity.
+    fn make_tx_with_capacity(&self, cap: usize) -> DBTx;
+
     /// Retrieve the value previously stored against `key` or `None` if
     /// `key` is not currently in the database.
     fn get<V: Decode>(&self, key: &DBKey) -> Option<V> {
@@ -83,17 +109,15 @@ impl<DB: KeyValueDB> Database for Db<DB> {
         }
         None
     }
-}
 
-impl<DB: KeyValueDB> Db<DB> {
-    pub fn make_tx(&self) -> DBTx {
+    fn make_tx(&self) -> DBTx {
         DBTx {
             tx: DBTransaction::new(),
             col: self.col,
         }
     }
 
-    pub fn make_tx_with_capacity(&self, cap: usize) -> DBTx {
+    fn make_tx_with_capacity(&self, cap: usize) -> DBTx {
         DBTx {
             tx: DBTransaction::with_capacity(cap),
             col: self.col,
@@ -168,6 +192,25 @@ mod tests {
         assert_eq!(none_value_b_2, None);
     }
 
+    #[test]
+    fn success_with_column() {
+        let db = Db::<kvdb_memorydb::InMemory>::new(kvdb_memorydb::create(2), 0);
+        let other = db.with_column(1);
+
+        let mut tx = db.make_tx();
+        tx.put(&vec![0, 1], &ValueA { a: 1, b: 2 });
+        assert!(db.commit(tx).is_ok());
+
+        let mut tx = other.make_tx();
+        tx.put(&vec![0, 1], &ValueA { a: 3, b: 4 });
+        assert!(other.commit(tx).is_ok());
+
+        let key = DBKey::from_vec(vec![0, 1]);
+        assert_eq!(db.get(&key), Some(ValueA { a: 1, b: 2 }));
+        assert_eq!(other.get(&key), Some(ValueA { a: 3, b: 4 }));
+        assert_eq!(db.clone().get(&key), Some(ValueA { a: 1, b: 2 }));
+    }
+
     #[test]
     fn it_works_onmemory() {
         let db = Db::<kvdb_memorydb::InMemory>::new(kvdb_memorydb::create(256), 128);
@@ -1,7 +1,15 @@
-use ethereum_types::H256;
+use ethereum_types::{H160, H256, U256, U64};
 use fc_rpc_core::types::TransactionRequest;
 use jsonrpc_core::{BoxFuture, Result};
 use jsonrpc_derive::rpc;
+use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
+
+pub mod types;
+
+use types::{
+    AccountProof, AccountTransactions, Block, BlockNumber, ClaimProof, ExitProof, ExitRequest,
+    ExitStatus, Filter, Log, NonMembershipProof, StateSync, StateSyncBlock, UserStateProof,
+};
 
 // grcov: ignore-start
 #[rpc(server)]
@@ -10,5 +18,138 @@ pub trait EthApi {
     /// transaction hash.
     #[rpc(name = "eth_sendTransaction")]
     fn send_transaction(&self, _: TransactionRequest) -> BoxFuture<Result<H256>>;
+
+    /// Returns the balance of the L1 native token of the account at the block, `latest` if
+    /// omitted.
+    #[rpc(name = "eth_getBalance")]
+    fn balance(&self, _: H160, _: Option<BlockNumber>) -> BoxFuture<Result<U256>>;
+
+    /// Returns the nonce of the account at the block, `latest` if omitted.
+    #[rpc(name = "eth_getTransactionCount")]
+    fn transaction_count(&self, _: H160, _: Option<BlockNumber>) -> BoxFuture<Result<U256>>;
+
+    /// Returns the latest sealed block number.
+    #[rpc(name = "eth_blockNumber")]
+    fn block_number(&self) -> BoxFuture<Result<U256>>;
+
+    /// Returns the block, with full transactions if the flag is set.
+    #[rpc(name = "eth_getBlockByNumber")]
+    fn block_by_number(&self, _: BlockNumber, _: bool) -> BoxFuture<Result<Option<Block>>>;
+
+    /// Returns the block, with full transactions if the flag is set.
+    #[rpc(name = "eth_getBlockByHash")]
+    fn block_by_hash(&self, _: H256, _: bool) -> BoxFuture<Result<Option<Block>>>;
+
+    /// Returns the chain id of the rollup.
+    #[rpc(name = "eth_chainId")]
+    fn chain_id(&self) -> BoxFuture<Result<U64>>;
+
+    /// Returns the proof of the balance of the token held by the account, with the state root
+    /// committed on L1. Blocks whose state tree is pruned cannot be proven.
+    #[rpc(name = "intmax_getProof")]
+    fn proof(&self, _: H160, _: H160, _: Option<BlockNumber>) -> BoxFuture<Result<AccountProof>>;
+
+    /// Returns the proof that nothing is stored under the state key, with the state root
+    /// committed on L1. Blocks whose state tree is pruned cannot be proven.
+    #[rpc(name = "intmax_getNonMembershipProof")]
+    fn non_membership_proof(
+        &self,
+        _: H256,
+        _: Option<BlockNumber>,
+    ) -> BoxFuture<Result<NonMembershipProof>>;
+
+    /// Returns the logs matching the filter.
+    #[rpc(name = "eth_getLogs")]
+    fn logs(&self, _: Filter) -> BoxFuture<Result<Vec<Log>>>;
+
+    /// Installs a log filter and returns its id.
+    #[rpc(name = "eth_newFilter")]
+    fn new_filter(&self, _: Filter) -> BoxFuture<Result<U256>>;
+
+    /// Returns the logs matching the filter since the previous poll.
+    #[rpc(name = "eth_getFilterChanges")]
+    fn filter_changes(&self, _: U256) -> BoxFuture<Result<Vec<Log>>>;
+
+    /// Uninstalls the filter. Returns whether it was installed.
+    #[rpc(name = "eth_uninstallFilter")]
+    fn uninstall_filter(&self, _: U256) -> BoxFuture<Result<bool>>;
+
+    /// Returns a page of the sealed transactions of the account, newest first. The cursor is
+    /// `nextCursor` of the previous page, the newest transactions if omitted.
+    #[rpc(name = "intmax_getAccountTransactions")]
+    fn account_transactions(
+        &self,
+        _: H160,
+        _: Option<U64>,
+        _: Option<U64>,
+    ) -> BoxFuture<Result<AccountTransactions>>;
+}
+
+#[rpc(server)]
+pub trait ExitApi {
+    /// Burns the requested L2 balance and returns the exit id. Like `eth_sendTransaction`,
+    /// the request is not authenticated yet.
+    #[rpc(name = "intmax_requestExit")]
+    fn request_exit(&self, _: ExitRequest) -> BoxFuture<Result<H256>>;
+
+    /// Returns the proof to withdraw the exit on L1, once its batch is finalized.
+    #[rpc(name = "intmax_getExitProof")]
+    fn exit_proof(&self, _: H256) -> BoxFuture<Result<ExitProof>>;
+
+    /// Returns the proof to claim the exit on L1, once its withdrawal batch is submitted.
+    #[rpc(name = "intmax_getClaimProof")]
+    fn claim_proof(&self, _: H256) -> BoxFuture<Result<ClaimProof>>;
+
+    /// Returns where the exit is on its way to L1.
+    #[rpc(name = "intmax_getExitStatus")]
+    fn exit_status(&self, _: H256) -> BoxFuture<Result<ExitStatus>>;
+
+    /// Returns the statuses of the exits requested by the account, oldest first.
+    #[rpc(name = "intmax_listExits")]
+    fn list_exits(&self, _: H160) -> BoxFuture<Result<Vec<ExitStatus>>>;
+}
+
+#[rpc(server)]
+pub trait SuApi {
+    /// Verifies the proof of a user state transition and includes it in the next block.
+    /// Returns the hash of the state diff.
+    #[rpc(name = "intmax_submitStateUpdate")]
+    fn submit_state_update(&self, _: UserStateProof) -> BoxFuture<Result<H256>>;
+}
+
+#[rpc(server)]
+pub trait StateSyncApi {
+    type Metadata;
+
+    /// Returns the blocks with state updates of the user among at most `limit` blocks, 100 by
+    /// default, from the block on. Pass `nextBlock` to resume.
+    #[rpc(name = "intmax_getStateSync")]
+    fn state_sync(&self, _: H160, _: U64, _: Option<U64>) -> BoxFuture<Result<StateSync>>;
+
+    /// Returns the sealed state diff with the proof submitted with it.
+    #[rpc(name = "intmax_getStateDiff")]
+    fn state_diff(&self, _: H256) -> BoxFuture<Result<Option<UserStateProof>>>;
+
+    /// Sends the blocks with state updates of the user from the block on, as they are sealed.
+    #[pubsub(
+        subscription = "intmax_stateSync",
+        subscribe,
+        name = "intmax_subscribeStateSync"
+    )]
+    fn subscribe_state_sync(
+        &self,
+        _: Self::Metadata,
+        _: Subscriber<StateSyncBlock>,
+        _: H160,
+        _: U64,
+    );
+
+    /// Stops the subscription.
+    #[pubsub(
+        subscription = "intmax_stateSync",
+        unsubscribe,
+        name = "intmax_unsubscribeStateSync"
+    )]
+    fn unsubscribe_state_sync(&self, _: Option<Self::Metadata>, _: SubscriptionId) -> Result<bool>;
 }
 // grcov: ignore-end
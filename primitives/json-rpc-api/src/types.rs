@@ -0,0 +1,404 @@
+//! Wire types of the intmax specific RPC methods.
+use ethereum_types::{H160, H256, U256, U64};
+use fc_rpc_core::types::Bytes;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// Params of `intmax_requestExit`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExitRequest {
+    /// Account whose L2 balance is withdrawn. It receives the funds on L1.
+    pub from: H160,
+    /// The zero address is the L1 native token.
+    pub token: H160,
+    pub amount: U256,
+    /// Current nonce of `from`.
+    pub nonce: U256,
+    /// EIP-712 signature by `from` of the withdrawal, see `exitor::signature`.
+    pub signature: Bytes,
+}
+
+/// Result of `intmax_getExitProof`: the arguments of `Rollup.withdraw`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExitProof {
+    pub id: H256,
+    pub batch_number: u64,
+    /// State root of the batch the proof is against, `Rollup.stateRoots(batchNumber)`.
+    pub state_root: H256,
+    pub recipient: H160,
+    pub token: H160,
+    pub amount: U256,
+    pub openings: Vec<Bytes>,
+    /// Encoded `intmax_verkle::Slot` of the stem of the key.
+    pub slot: Bytes,
+}
+
+/// Result of `intmax_getClaimProof`: the arguments of `Rollup.claim`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaimProof {
+    pub id: H256,
+    pub withdrawal_batch: u64,
+    pub root: H256,
+    pub recipient: H160,
+    pub token: H160,
+    pub amount: U256,
+    pub proof: Vec<H256>,
+    pub claimed: bool,
+}
+
+/// Claim status of an exit in a withdrawal batch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ClaimStatus {
+    Unclaimed,
+    Claimed,
+}
+
+/// Result of `intmax_getExitStatus`, and items of `intmax_listExits`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExitStatus {
+    pub id: H256,
+    pub recipient: H160,
+    pub token: H160,
+    pub amount: U256,
+    /// Unix time in seconds.
+    pub requested_at: u64,
+    /// Block, i.e. batch, including the exit.
+    pub block_number: Option<u64>,
+    /// Unix time in seconds at which the challenge window of the block ends at the earliest.
+    pub challenge_window_end: Option<u64>,
+    /// Whether `intmax_getExitProof` is available.
+    pub proof_available: bool,
+    pub withdrawal_batch: Option<u64>,
+    /// L1 transaction which submitted the root of the withdrawal batch. `intmax_getClaimProof`
+    /// is available once it is set.
+    pub finalization_tx: Option<H256>,
+    /// `null` until the exit is in a withdrawal batch.
+    pub claim_status: Option<ClaimStatus>,
+}
+
+/// Result of `eth_getBlockByNumber` and `eth_getBlockByHash`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Block {
+    pub number: U64,
+    pub hash: H256,
+    pub parent_hash: H256,
+    /// Unix time in seconds.
+    pub timestamp: U64,
+    pub transactions: BlockTransactions,
+}
+
+/// Transactions of a `Block`, in full only if requested.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BlockTransactions {
+    Hashes(Vec<H256>),
+    Full(Vec<Transaction>),
+}
+
+impl Default for BlockTransactions {
+    fn default() -> Self {
+        BlockTransactions::Hashes(vec![])
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TransactionKind {
+    Deposit,
+    Withdraw,
+    UpdateState,
+}
+
+/// A transaction of a `Block`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Transaction {
+    pub hash: H256,
+    #[serde(rename = "type")]
+    pub kind: TransactionKind,
+    /// Withdrawing account or updating user, `null` for deposits.
+    pub from: Option<H160>,
+    /// Deposit recipient, `null` for withdrawals.
+    pub to: Option<H160>,
+    /// The zero address is the L1 native token.
+    pub token: H160,
+    pub value: U256,
+    /// `null` for deposits.
+    pub nonce: Option<U256>,
+    /// New root of the user state of a state update, `null` otherwise.
+    pub state_root: Option<H256>,
+    pub block_number: U64,
+}
+
+/// Result of `intmax_getAccountTransactions`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountTransactions {
+    /// Newest first.
+    pub transactions: Vec<Transaction>,
+    /// Cursor of the next page, `null` if this is the last one.
+    pub next_cursor: Option<U64>,
+}
+
+/// Result of `intmax_getProof`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountProof {
+    pub address: H160,
+    pub token: H160,
+    pub balance: U256,
+    /// Block whose state is proven, `null` for the pending state.
+    pub block_number: Option<U64>,
+    /// State root of the block committed on L1, `null` until it is committed.
+    pub state_root: Option<H256>,
+    /// Key of the balance in the state.
+    pub key: H256,
+    /// Scheme of the state tree, `Verkle` or `SparseMerkle`, to pick the verifier in
+    /// `intmax_verkle::verifier`.
+    pub scheme: String,
+    /// Root of the state tree the proof is against.
+    pub root: H256,
+    pub openings: Vec<Bytes>,
+    /// Encoded `intmax_verkle::Slot` of the stem of the key.
+    pub slot: Bytes,
+}
+
+/// Result of `intmax_getNonMembershipProof`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NonMembershipProof {
+    pub key: H256,
+    /// Block whose state is proven, `null` for the pending state.
+    pub block_number: Option<U64>,
+    /// State root of the block committed on L1, `null` until it is committed.
+    pub state_root: Option<H256>,
+    /// Scheme of the state tree, as in `AccountProof`.
+    pub scheme: String,
+    /// Root of the state tree the proof is against.
+    pub root: H256,
+    pub openings: Vec<Bytes>,
+    /// Encoded `intmax_verkle::Slot` of the stem of the key.
+    pub slot: Bytes,
+}
+
+/// A single value or an array of values, as in the `address` and `topics` of a `Filter`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ValueOrArray<T> {
+    Value(T),
+    Array(Vec<T>),
+}
+
+impl<T> ValueOrArray<T> {
+    pub fn into_vec(self) -> Vec<T> {
+        match self {
+            ValueOrArray::Value(value) => vec![value],
+            ValueOrArray::Array(values) => values,
+        }
+    }
+}
+
+/// Params of `eth_getLogs` and `eth_newFilter`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Filter {
+    /// `latest` if omitted.
+    pub from_block: Option<BlockNumber>,
+    /// `latest` if omitted.
+    pub to_block: Option<BlockNumber>,
+    /// Only the logs of this block. Excludes `fromBlock` and `toBlock`.
+    pub block_hash: Option<H256>,
+    /// Any address if omitted.
+    pub address: Option<ValueOrArray<H160>>,
+    /// For every position, any of the topics, or any topic if `null`.
+    pub topics: Option<Vec<Option<ValueOrArray<H256>>>>,
+}
+
+/// Result items of `eth_getLogs` and `eth_getFilterChanges`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Log {
+    /// Token moved by the transaction. The zero address is the L1 native token.
+    pub address: H160,
+    pub topics: Vec<H256>,
+    pub data: Bytes,
+    pub block_number: U64,
+    pub block_hash: H256,
+    pub transaction_hash: H256,
+    pub transaction_index: U64,
+    pub log_index: U64,
+    /// Always `false`, blocks are never reorganized.
+    pub removed: bool,
+}
+
+/// Params of `intmax_submitStateUpdate`: a user state transition and its proof.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserStateProof {
+    pub user: H160,
+    /// Root of the user state before the transition, zero for a new user.
+    pub old_root: H256,
+    pub new_root: H256,
+    /// Number of the previous transitions of the user.
+    pub nonce: U64,
+    pub proof: Bytes,
+}
+
+/// A state update of the user in `StateSyncBlock`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateSyncUpdate {
+    /// Position of the state diff in the state diff tree of the block.
+    pub index: U64,
+    pub old_root: H256,
+    pub new_root: H256,
+    pub nonce: U64,
+    /// The proof submitted with the state diff.
+    pub proof: Bytes,
+    /// Siblings of the state diff up to `diffRoot`, `null` where a node has none.
+    pub diff_proof: Vec<Option<H256>>,
+}
+
+/// A block with state updates of the user, sent by the `intmax_stateSync` subscription.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateSyncBlock {
+    pub number: U64,
+    pub hash: H256,
+    pub parent_hash: H256,
+    pub timestamp: U64,
+    /// Root of the state diff tree of the block.
+    pub diff_root: H256,
+    /// Aggregated proof of the state diffs under `diffRoot`.
+    pub aggregated_proof: Bytes,
+    pub updates: Vec<StateSyncUpdate>,
+}
+
+/// Result of `intmax_getStateSync`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateSync {
+    pub blocks: Vec<StateSyncBlock>,
+    /// The first block not scanned yet, to resume from.
+    pub next_block: U64,
+}
+
+/// Block param of the state queries: `"latest"`, `"pending"`, `"finalized"`, `"earliest"`, a
+/// hex number, or `{"blockNumber": ..}` / `{"blockHash": ..}`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlockNumber {
+    #[default]
+    Latest,
+    Pending,
+    /// The latest block finalized on L1.
+    Finalized,
+    Earliest,
+    Num(u64),
+    Hash(H256),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum BlockNumberRepr {
+    Tag(String),
+    #[serde(rename_all = "camelCase")]
+    Number {
+        block_number: U64,
+    },
+    #[serde(rename_all = "camelCase")]
+    Hash {
+        block_hash: H256,
+    },
+}
+
+impl Serialize for BlockNumber {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let repr = match self {
+            BlockNumber::Latest => BlockNumberRepr::Tag("latest".into()),
+            BlockNumber::Pending => BlockNumberRepr::Tag("pending".into()),
+            BlockNumber::Finalized => BlockNumberRepr::Tag("finalized".into()),
+            BlockNumber::Earliest => BlockNumberRepr::Tag("earliest".into()),
+            BlockNumber::Num(number) => BlockNumberRepr::Tag(format!("{:#x}", number)),
+            BlockNumber::Hash(hash) => BlockNumberRepr::Hash { block_hash: *hash },
+        };
+        repr.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for BlockNumber {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match BlockNumberRepr::deserialize(deserializer)? {
+            BlockNumberRepr::Tag(tag) => match tag.as_str() {
+                "latest" => Ok(BlockNumber::Latest),
+                "pending" => Ok(BlockNumber::Pending),
+                "finalized" => Ok(BlockNumber::Finalized),
+                "earliest" => Ok(BlockNumber::Earliest),
+                number => number
+                    .strip_prefix("0x")
+                    .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+                    .map(BlockNumber::Num)
+                    .ok_or_else(|| de::Error::custom(format!("invalid block number: {}", number))),
+            },
+            BlockNumberRepr::Number { block_number } => Ok(BlockNumber::Num(block_number.as_u64())),
+            BlockNumberRepr::Hash { block_hash } => Ok(BlockNumber::Hash(block_hash)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn success_block_number_serde() {
+        let parse = |json: &str| serde_json::from_str::<BlockNumber>(json).unwrap();
+        assert_eq!(parse(r#""latest""#), BlockNumber::Latest);
+        assert_eq!(parse(r#""pending""#), BlockNumber::Pending);
+        assert_eq!(parse(r#""finalized""#), BlockNumber::Finalized);
+        assert_eq!(parse(r#""earliest""#), BlockNumber::Earliest);
+        assert_eq!(parse(r#""0x1a""#), BlockNumber::Num(26));
+        assert_eq!(parse(r#"{"blockNumber": "0x2"}"#), BlockNumber::Num(2));
+        let hash = H256::repeat_byte(1);
+        assert_eq!(
+            parse(&format!(r#"{{"blockHash": "{:?}"}}"#, hash)),
+            BlockNumber::Hash(hash)
+        );
+        assert!(serde_json::from_str::<BlockNumber>(r#""safe""#).is_err());
+        assert!(serde_json::from_str::<BlockNumber>(r#""26""#).is_err());
+
+        for block in [
+            BlockNumber::Finalized,
+            BlockNumber::Num(26),
+            BlockNumber::Hash(hash),
+        ] {
+            let json = serde_json::to_string(&block).unwrap();
+            assert_eq!(parse(&json), block);
+        }
+    }
+
+    #[test]
+    fn success_filter_serde() {
+        let filter: Filter = serde_json::from_str(
+            r#"{"fromBlock": "0x1", "address": "0x0000000000000000000000000000000000000001", "topics": [null, ["0x0000000000000000000000000000000000000000000000000000000000000002"]]}"#,
+        )
+        .unwrap();
+        assert_eq!(filter.from_block, Some(BlockNumber::Num(1)));
+        assert_eq!(filter.to_block, None);
+        assert_eq!(
+            filter.address.unwrap().into_vec(),
+            vec![H160::from_low_u64_be(1)]
+        );
+        assert_eq!(
+            filter.topics.unwrap(),
+            vec![
+                None,
+                Some(ValueOrArray::Array(vec![H256::from_low_u64_be(2)]))
+            ]
+        );
+    }
+}
@@ -1,5 +1,8 @@
+use std::sync::Arc;
+
 use jsonrpc_core::IoHandlerExtension;
 use jsonrpc_core::MetaIoHandler;
+use jsonrpc_pubsub::Session;
 
 use crate::middleware::{Meta, TracingMiddleware};
 
@@ -45,17 +48,20 @@ pub fn start_http_server(
 
 pub fn start_ws_server(addr: &std::net::SocketAddr, io: RpcHandler) -> std::io::Result<ws::Server> {
     println!("server address: {}", addr);
-    ws::ServerBuilder::new(io)
-        .start(addr)
-        .map_err(|err| match err {
-            ws::Error::Io(io) => io,
-            ws::Error::ConnectionClosed => std::io::ErrorKind::BrokenPipe.into(),
-            er => {
-                println!("error: {:?}", er);
-                // output error log.
-                std::io::ErrorKind::Other.into()
-            }
-        })
+    // every connection gets a pubsub session, so that subscriptions are served over ws.
+    ws::ServerBuilder::with_meta_extractor(io, |context: &ws::RequestContext| {
+        Meta(0, Some(Arc::new(Session::new(context.sender()))))
+    })
+    .start(addr)
+    .map_err(|err| match err {
+        ws::Error::Io(io) => io,
+        ws::Error::ConnectionClosed => std::io::ErrorKind::BrokenPipe.into(),
+        er => {
+            println!("error: {:?}", er);
+            // output error log.
+            std::io::ErrorKind::Other.into()
+        }
+    })
 }
 
 #[cfg(test)]
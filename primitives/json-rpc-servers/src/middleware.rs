@@ -1,17 +1,26 @@
 use jsonrpc_core::futures_util::{future::Either, FutureExt};
 use jsonrpc_core::*;
+use jsonrpc_pubsub::{PubSubMetadata, Session};
 use std::future::Future;
 use std::sync::atomic::AtomicUsize;
+use std::sync::Arc;
 use std::time::Instant;
 use tracing::{info, info_span};
 use tracing_futures::Instrument;
 use uuid::Uuid;
 
+/// Request metadata. Holds the pubsub session of ws connections.
 #[derive(Clone, Debug, Default)]
-pub struct Meta(usize);
+pub struct Meta(pub usize, pub Option<Arc<Session>>);
 
 impl Metadata for Meta {}
 
+impl PubSubMetadata for Meta {
+    fn session(&self) -> Option<Arc<Session>> {
+        self.1.clone()
+    }
+}
+
 #[derive(Default)]
 pub struct TracingMiddleware(AtomicUsize);
 
@@ -75,11 +84,11 @@ mod tests {
         let request = r#"{"jsonrpc": "2.0", "method": "say_hello", "params": [42, 23], "id": 1}"#;
         let response = r#"{"jsonrpc":"2.0","result":"Hello World: 5","id":1}"#;
         assert_eq!(
-            io.handle_request_sync(request, Meta(5)),
+            io.handle_request_sync(request, Meta(5, None)),
             Some(response.to_owned())
         );
 
-        let m = Meta(0);
+        let m = Meta(0, None);
         println!("{:?}", m);
     }
 
@@ -93,6 +102,6 @@ mod tests {
         });
 
         let request = r#"{"jsonrpc": "2.0", "method": "say_hello", "params": [42, 23], "id": 1}"#;
-        io.handle_request_sync(request, Meta(5));
+        io.handle_request_sync(request, Meta(5, None));
     }
 }
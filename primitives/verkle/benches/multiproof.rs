@@ -0,0 +1,79 @@
+//! Compares one multiproof for many keys with a proof per key, in encoded size and in
+//! verification time.
+
+use codec::Encode;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use db::Db;
+use intmax_verkle::{
+    verifier::{Scheme, SparseMerkle, Verkle},
+    Batch, Key, Proof, Tree, VerkleStorage, STEM_LEN,
+};
+
+const STORED: u32 = 10_000;
+const PROVEN: [usize; 3] = [10, 100, 1000];
+
+fn key(i: u32) -> Key {
+    let mut stem = [0; STEM_LEN];
+    stem[STEM_LEN - 4..].copy_from_slice(&i.to_be_bytes());
+    Key::new(stem, 0)
+}
+
+fn tree<S: Scheme>() -> Tree<Db<kvdb_memorydb::InMemory>, S> {
+    let tree = Tree::new(Db::new(kvdb_memorydb::create(1), 0));
+    let mut batch = Batch::default();
+    for i in 0..STORED {
+        batch.put(&key(i), &u64::from(i));
+    }
+    tree.apply(&batch).unwrap();
+    tree
+}
+
+fn bench_scheme<S: Scheme>(c: &mut Criterion, name: &str) {
+    let tree = tree::<S>();
+    let mut group = c.benchmark_group(name);
+    for proven in PROVEN {
+        let keys: Vec<_> = (0..proven as u32)
+            .map(|i| key(i * (STORED / proven as u32)))
+            .collect();
+        let proofs: Vec<Proof> = keys
+            .iter()
+            .map(|key| tree.inclusion_proof(key).unwrap())
+            .collect();
+        let multiproof = tree.inclusion_multiproof(&keys).unwrap();
+
+        let individual: usize = proofs.iter().map(|proof| proof.encode().len()).sum();
+        println!(
+            "{}/{} keys: {} bytes in individual proofs, {} bytes in a multiproof",
+            name,
+            proven,
+            individual,
+            multiproof.encode().len()
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("individual", proven),
+            &proofs,
+            |b, proofs| {
+                b.iter(|| {
+                    for proof in proofs {
+                        S::verify_proof(proof).unwrap();
+                    }
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("multiproof", proven),
+            &multiproof,
+            |b, multiproof| b.iter(|| S::verify_multiproof(multiproof).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+fn bench_multiproof(c: &mut Criterion) {
+    bench_scheme::<Verkle>(c, "verkle");
+    bench_scheme::<SparseMerkle>(c, "sparse_merkle");
+}
+
+criterion_group!(benches, bench_multiproof);
+criterion_main!(benches);
@@ -0,0 +1,267 @@
+//! Pedersen vector commitments over Bandersnatch, opened with an inner product argument.
+//!
+//! A node of a `verifier::Verkle` tree commits to the vector `a` of its `WIDTH` children as
+//! `C = Σ a_i G_i`, `a_i` being a scalar of the commitment of child `i`, zero if it is empty.
+//! The opening of child `i` proves `a_i` with the inner product argument of `a` and the unit
+//! vector of `i`, halving both each round: 2 points per round and a final scalar, 544 bytes
+//! whatever the node holds.
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
+use ark_ed_on_bls12_381_bandersnatch::{EdwardsAffine, EdwardsProjective, Fr};
+use ark_ff::{Field, One, PrimeField, Zero};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use once_cell::race::OnceBox;
+
+use crate::verifier::{keccak256, Commitment, EMPTY};
+use crate::{Result, StorageError};
+
+/// Length of the committed vectors, the number of children of a node.
+pub(crate) const WIDTH: usize = 256;
+/// Rounds halving the vectors down to a single scalar.
+const ROUNDS: usize = 8;
+/// Bytes of an encoded point or scalar.
+const ELEMENT_LEN: usize = 32;
+/// Bytes of the opening of one child.
+pub(crate) const OPENING_LEN: usize = ELEMENT_LEN * (2 * ROUNDS + 1);
+
+const GENERATOR_DOMAIN: &[u8] = b"intmax-verkle-generator";
+const SCALAR_DOMAIN: &[u8] = b"intmax-verkle-scalar";
+const TRANSCRIPT_DOMAIN: &[u8] = b"intmax-verkle-ipa";
+
+/// The bases `G_i` of the vectors and `Q` of their inner product. Each is hashed to the curve,
+/// so no discrete log relation between them is known.
+struct Generators {
+    g: Vec<EdwardsAffine>,
+    q: EdwardsAffine,
+}
+
+fn generators() -> &'static Generators {
+    static GENERATORS: OnceBox<Generators> = OnceBox::new();
+    GENERATORS.get_or_init(|| {
+        Box::new(Generators {
+            g: (0..WIDTH as u64).map(hash_to_curve).collect(),
+            q: hash_to_curve(WIDTH as u64),
+        })
+    })
+}
+
+/// A point of the prime order subgroup from the first hash of `index` and a counter which
+/// decodes as a curve point.
+fn hash_to_curve(index: u64) -> EdwardsAffine {
+    (0u64..)
+        .filter_map(|counter| {
+            let bytes = keccak256(&[
+                GENERATOR_DOMAIN,
+                &index.to_le_bytes(),
+                &counter.to_le_bytes(),
+            ]);
+            EdwardsAffine::deserialize_compressed_unchecked(&bytes[..]).ok()
+        })
+        .map(|point| point.clear_cofactor())
+        .find(|point| !point.is_zero())
+        .expect("a hash decodes as a point")
+}
+
+/// The entry of the vector for a child of commitment `commitment`, zero if it is empty.
+pub(crate) fn scalar(commitment: &Commitment) -> Fr {
+    if *commitment == EMPTY {
+        return Fr::zero();
+    }
+
+    Fr::from_le_bytes_mod_order(&keccak256(&[SCALAR_DOMAIN, commitment]))
+}
+
+/// Commitment to `vector`, `EMPTY` for the zero vector.
+pub(crate) fn commit(vector: &[Fr]) -> Commitment {
+    let point = EdwardsProjective::msm_unchecked(&generators().g, vector);
+    if point.is_zero() {
+        return EMPTY;
+    }
+
+    encode(&point)
+}
+
+/// Opening of the entry `index` of `vector`, whose commitment is `commitment`.
+pub(crate) fn open(vector: &[Fr], index: u8, commitment: &Commitment) -> Vec<u8> {
+    let generators = generators();
+    let mut transcript = Transcript::new(commitment, index, &vector[index as usize]);
+    let q = generators.q * transcript.challenge(&[]);
+
+    let mut a = vector.to_vec();
+    let mut b = vec![Fr::zero(); WIDTH];
+    b[index as usize] = Fr::one();
+    let mut g = generators.g.clone();
+    let mut opening = Vec::with_capacity(OPENING_LEN);
+    while a.len() > 1 {
+        let half = a.len() / 2;
+        let (a_l, a_r) = a.split_at(half);
+        let (b_l, b_r) = b.split_at(half);
+        let (g_l, g_r) = g.split_at(half);
+
+        let l = encode(&(EdwardsProjective::msm_unchecked(g_r, a_l) + q * inner(a_l, b_r)));
+        let r = encode(&(EdwardsProjective::msm_unchecked(g_l, a_r) + q * inner(a_r, b_l)));
+        let x = transcript.challenge(&[&l, &r]);
+        let x_inv = x.inverse().expect("challenges are not zero");
+        opening.extend_from_slice(&l);
+        opening.extend_from_slice(&r);
+
+        a = a_l.iter().zip(a_r).map(|(l, r)| *l + x * r).collect();
+        b = b_l.iter().zip(b_r).map(|(l, r)| *l + x_inv * r).collect();
+        let folded: Vec<_> = g_l.iter().zip(g_r).map(|(l, r)| *r * x_inv + l).collect();
+        g = EdwardsProjective::normalize_batch(&folded);
+    }
+
+    a[0].serialize_compressed(&mut opening)
+        .expect("scalars encode");
+    opening
+}
+
+/// Checks that `opening` proves `value` at `index` of the vector committed to by
+/// `commitment`.
+pub(crate) fn verify(commitment: &Commitment, index: u8, value: &Fr, opening: &[u8]) -> Result<()> {
+    if opening.len() != OPENING_LEN {
+        return Err(StorageError::InvalidProof);
+    }
+
+    let generators = generators();
+    let mut transcript = Transcript::new(commitment, index, value);
+    let q = generators.q * transcript.challenge(&[]);
+
+    // Each round adds `x⁻¹ L + x R` to the commitment to `a` and `<a, b>`, and multiplies the
+    // entries of the right half of the generators and of `b` by `x⁻¹`.
+    let (rounds, last) = opening.split_at(2 * ROUNDS * ELEMENT_LEN);
+    let mut points = Vec::with_capacity(2 * ROUNDS);
+    let mut factors = Vec::with_capacity(2 * ROUNDS);
+    let mut s = vec![Fr::one()];
+    for round in rounds.chunks(2 * ELEMENT_LEN) {
+        let (l, r) = round.split_at(ELEMENT_LEN);
+        let x = transcript.challenge(&[l, r]);
+        let x_inv = x.inverse().ok_or(StorageError::InvalidProof)?;
+        points.extend([decode(l)?, decode(r)?]);
+        factors.extend([x_inv, x]);
+        s = s
+            .iter()
+            .flat_map(|entry| [*entry, *entry * x_inv])
+            .collect();
+    }
+    let a = Fr::deserialize_compressed(last).map_err(|_| StorageError::InvalidProof)?;
+
+    let folded =
+        EdwardsProjective::msm_unchecked(&points, &factors) + q * value + point(commitment)?;
+    let g = EdwardsProjective::msm_unchecked(&generators.g, &s);
+    match g * a + q * (a * s[index as usize]) {
+        expected if expected == folded => Ok(()),
+        _ => Err(StorageError::InvalidProof),
+    }
+}
+
+fn inner(a: &[Fr], b: &[Fr]) -> Fr {
+    a.iter().zip(b).map(|(a, b)| *a * b).sum()
+}
+
+fn encode(point: &EdwardsProjective) -> [u8; ELEMENT_LEN] {
+    let mut bytes = [0; ELEMENT_LEN];
+    point
+        .into_affine()
+        .serialize_compressed(&mut bytes[..])
+        .expect("points encode in 32 bytes");
+    bytes
+}
+
+/// A point of the prime order subgroup, `InvalidProof` for any other bytes.
+fn decode(bytes: &[u8]) -> Result<EdwardsAffine> {
+    EdwardsAffine::deserialize_compressed(bytes).map_err(|_| StorageError::InvalidProof)
+}
+
+/// The point of a node commitment, the identity for `EMPTY`.
+fn point(commitment: &Commitment) -> Result<EdwardsAffine> {
+    match *commitment {
+        EMPTY => Ok(EdwardsAffine::zero()),
+        _ => decode(commitment),
+    }
+}
+
+/// Fiat-Shamir challenges, each a hash of the previous one and the points of its round.
+struct Transcript(Commitment);
+
+impl Transcript {
+    fn new(commitment: &Commitment, index: u8, value: &Fr) -> Self {
+        let mut value_bytes = [0; ELEMENT_LEN];
+        value
+            .serialize_compressed(&mut value_bytes[..])
+            .expect("scalars encode in 32 bytes");
+        Transcript(keccak256(&[
+            TRANSCRIPT_DOMAIN,
+            commitment,
+            &[index],
+            &value_bytes,
+        ]))
+    }
+
+    fn challenge(&mut self, points: &[&[u8]]) -> Fr {
+        let mut data = vec![&self.0[..]];
+        data.extend_from_slice(points);
+        self.0 = keccak256(&data);
+        Fr::from_le_bytes_mod_order(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vector() -> Vec<Fr> {
+        (0..WIDTH as u64)
+            .map(|i| match i % 3 {
+                0 => Fr::zero(),
+                _ => Fr::from(i * 7 + 1),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn success_open() {
+        let vector = vector();
+        let commitment = commit(&vector);
+        for index in [0u8, 1, 128, 255] {
+            let opening = open(&vector, index, &commitment);
+            assert_eq!(opening.len(), OPENING_LEN);
+            verify(&commitment, index, &vector[index as usize], &opening).expect("verify ok.");
+
+            let other = Fr::from(3u64) + vector[index as usize];
+            assert!(verify(&commitment, index, &other, &opening).is_err());
+            assert!(verify(&commitment, index ^ 1, &vector[index as usize], &opening).is_err());
+        }
+    }
+
+    #[test]
+    fn fail_forged_opening() {
+        let vector = vector();
+        let commitment = commit(&vector);
+        let opening = open(&vector, 4, &commitment);
+
+        let mut changed = vector.clone();
+        changed[4] += Fr::one();
+        let other = commit(&changed);
+        assert!(verify(&other, 4, &vector[4], &opening).is_err());
+        assert!(verify(&other, 4, &changed[4], &opening).is_err());
+        verify(&other, 4, &changed[4], &open(&changed, 4, &other)).expect("verify ok.");
+
+        let mut forged = opening.clone();
+        forged[OPENING_LEN - 1] ^= 1;
+        assert!(verify(&commitment, 4, &vector[4], &forged).is_err());
+        assert!(verify(&commitment, 4, &vector[4], &opening[1..]).is_err());
+        assert!(verify(&[1; 32], 4, &vector[4], &opening).is_err());
+    }
+
+    #[test]
+    fn success_empty_vector() {
+        let vector = vec![Fr::zero(); WIDTH];
+        assert_eq!(commit(&vector), EMPTY);
+        verify(&EMPTY, 7, &Fr::zero(), &open(&vector, 7, &EMPTY)).expect("verify ok.");
+        assert_eq!(scalar(&EMPTY), Fr::zero());
+        assert_ne!(scalar(&[1; 32]), Fr::zero());
+    }
+}
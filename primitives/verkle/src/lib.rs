@@ -1,76 +1,263 @@
+//! State trees and their proofs. Without the default `std` feature only `Proof` and the
+//! `verifier` are built, for light clients and circuits.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
 use codec::{Decode, Encode};
 
-#[derive(Debug)]
+mod ipa;
+#[cfg(feature = "std")]
+pub mod snapshot;
+#[cfg(feature = "std")]
+mod state_tree;
+#[cfg(feature = "std")]
+mod tree;
+pub mod verifier;
+
+#[cfg(feature = "std")]
+pub use state_tree::StateTree;
+#[cfg(feature = "std")]
+pub use tree::{SparseMerkleTree, Tree, VerkleTree};
+pub use verifier::{Commitment, SchemeKind};
+
+#[derive(Debug, PartialEq, Eq)]
 pub enum StorageError {
-    InvalidError,
+    /// A stored value does not decode as the requested type.
+    Decode,
+    /// The database failed to write, or holds a node which does not decode.
+    Database,
+    /// A value encoding to nothing, which stands for an absent key in proofs.
+    EmptyValue,
+    /// No value is stored under the key.
+    KeyNotFound,
+    /// A value is stored under the key.
+    KeyExists,
+    /// The version of block `number` is not kept.
+    UnknownVersion { number: u64 },
+    /// Block `number` is not above the latest kept version, block `latest`.
+    StaleVersion { number: u64, latest: u64 },
+    /// A proof does not prove its value, or its absence, under its root.
+    InvalidProof,
+    /// A snapshot cannot be read or written.
+    InvalidSnapshot { reason: String },
+    /// The state rebuilt from a snapshot is not the one of its header.
+    SnapshotRootMismatch {
+        expected: Commitment,
+        actual: Commitment,
+    },
+    /// The database holds a tree of another scheme.
+    SchemeMismatch {
+        stored: SchemeKind,
+        expected: SchemeKind,
+    },
 }
 
-pub struct Proof {
-    pub siblings: Vec<Vec<u8>>,
-    pub leaf: Vec<u8>,
+impl core::fmt::Display for StorageError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            StorageError::Decode => write!(f, "Stored value does not decode"),
+            StorageError::Database => write!(f, "Database failure"),
+            StorageError::EmptyValue => write!(f, "Value encodes to nothing"),
+            StorageError::KeyNotFound => write!(f, "No value is stored under the key"),
+            StorageError::KeyExists => write!(f, "A value is stored under the key"),
+            StorageError::UnknownVersion { number } => {
+                write!(f, "Version of block {} is not kept", number)
+            }
+            StorageError::StaleVersion { number, latest } => write!(
+                f,
+                "Block {} is not above the latest version, block {}",
+                number, latest
+            ),
+            StorageError::InvalidProof => write!(f, "Invalid proof"),
+            StorageError::InvalidSnapshot { reason } => write!(f, "Invalid snapshot: {}", reason),
+            StorageError::SnapshotRootMismatch { expected, actual } => write!(
+                f,
+                "Rebuilt root 0x{} is not the snapshot root 0x{}",
+                Hex(actual),
+                Hex(expected)
+            ),
+            StorageError::SchemeMismatch { stored, expected } => write!(
+                f,
+                "Database holds a {} tree, not a {} tree",
+                stored, expected
+            ),
+        }
+    }
 }
 
-pub type Result<T> = std::result::Result<T, StorageError>;
+#[cfg(feature = "std")]
+impl std::error::Error for StorageError {}
 
-pub trait VerkleStorage {
-    fn inclusion_proof<K: Clone + AsRef<[u8]>>(&self, key: &K) -> Result<Proof>;
-    fn verify_proof(&self, proof: &Proof) -> Result<()>;
-    fn put<K: Clone + AsRef<[u8]>, V: Encode>(&self, key: &K, data: &V) -> Result<()>;
-    fn remove<K: Clone + AsRef<[u8]>, V: Encode>(&self, key: &K, data: &V) -> Result<()>;
-    fn get<K: Clone + AsRef<[u8]>, V: Decode>(&self, key: &K) -> Option<V>;
-}
+struct Hex<'a>(&'a [u8]);
 
-pub trait VerkleSMTStorage: VerkleStorage {
-    fn inclusion_empty_proof<K: Clone + AsRef<[u8]>>(&self, key: &K) -> Result<Proof>;
+impl core::fmt::Display for Hex<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Number of bytes of a `Key`.
+pub const KEY_LEN: usize = 32;
+/// Number of bytes of the stem of a `Key`.
+pub const STEM_LEN: usize = 31;
 
-    struct MockVeckleStorage;
+/// Key of the state tree in the Verkle layout: a 31 byte stem and a 1 byte suffix. The values of
+/// the keys of a stem share a leaf, placed in the tree by a hash of the stem, see
+/// `verifier::Scheme::path`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Encode, Decode)]
+pub struct Key([u8; KEY_LEN]);
 
-    impl VerkleStorage for MockVeckleStorage {
-        fn inclusion_proof<K: Clone + AsRef<[u8]>>(&self, _key: &K) -> Result<Proof> {
-            Ok(Proof {
-                siblings: vec![],
-                leaf: vec![],
-            })
-        }
-        fn verify_proof(&self, _proof: &Proof) -> Result<()> {
-            Ok(())
-        }
-        fn put<K: Clone + AsRef<[u8]>, V: Encode>(&self, _key: &K, _data: &V) -> Result<()> {
-            Ok(())
-        }
-        fn remove<K: Clone + AsRef<[u8]>, V: Encode>(&self, _key: &K, _data: &V) -> Result<()> {
-            Ok(())
-        }
-        fn get<K: Clone + AsRef<[u8]>, V: Decode>(&self, _key: &K) -> Option<V> {
-            None
-        }
+impl Key {
+    pub fn new(stem: [u8; STEM_LEN], suffix: u8) -> Self {
+        let mut key = [0; KEY_LEN];
+        key[..STEM_LEN].copy_from_slice(&stem);
+        key[STEM_LEN] = suffix;
+        Key(key)
     }
 
-    impl VerkleSMTStorage for MockVeckleStorage {
-        fn inclusion_empty_proof<K: Clone + AsRef<[u8]>>(&self, _key: &K) -> Result<Proof> {
-            Ok(Proof {
-                siblings: vec![],
-                leaf: vec![],
-            })
-        }
+    pub fn stem(&self) -> &[u8] {
+        &self.0[..STEM_LEN]
+    }
+
+    pub fn suffix(&self) -> u8 {
+        self.0[STEM_LEN]
+    }
+
+    pub fn as_bytes(&self) -> &[u8; KEY_LEN] {
+        &self.0
+    }
+}
+
+impl From<[u8; KEY_LEN]> for Key {
+    fn from(key: [u8; KEY_LEN]) -> Self {
+        Key(key)
     }
+}
 
-    #[test]
-    fn wrong_test() {
-        let storage = MockVeckleStorage {};
+impl AsRef<[u8]> for Key {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
 
-        let k = vec![5, 10];
-        let v: i32 = 10;
-        storage.put(&k, &v).expect("put ok.");
+/// What the slot at the end of the path of the stem of a key holds.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub enum Slot {
+    /// Nothing: the key is absent.
+    Empty,
+    /// The leaf of the stem, with the opening of its values at the suffix of the key, see
+    /// `verifier::Scheme::open_values`.
+    Stem(Vec<u8>),
+    /// The leaf of another stem sharing the path down to the slot, with the commitment of its
+    /// values: the key is absent.
+    Other { stem: Vec<u8>, values: Commitment },
+}
+
+/// Proof that `key` holds `value` in the tree of `root`, or that it is absent. Checked with
+/// `verifier::Scheme::verify_proof`.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct Proof {
+    pub root: Commitment,
+    pub key: Vec<u8>,
+    /// Encoded value under `key`, `None` if the proof is of absence.
+    pub value: Option<Vec<u8>>,
+    /// For each node from the root down to the slot of the stem of `key`, its opening of the
+    /// child on the path, see `verifier::Scheme::open`.
+    pub openings: Vec<Vec<u8>>,
+    pub slot: Slot,
+}
+
+/// A key proven by a `MultiProof`.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct MultiProofEntry {
+    pub key: Vec<u8>,
+    /// Encoded value under `key`, `None` if the key is absent.
+    pub value: Option<Vec<u8>>,
+    /// Number of nodes from the root down to the slot of the stem of `key`.
+    pub depth: u32,
+    pub slot: Slot,
+}
+
+/// Proof of many keys against `root`. The nodes on the paths of several keys are listed once.
+/// Checked with `verifier::Scheme::verify_multiproof`.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct MultiProof {
+    pub root: Commitment,
+    pub entries: Vec<MultiProofEntry>,
+    /// For each node on the paths of the entries, ordered by path, its opening of its children
+    /// on these paths.
+    pub openings: Vec<Vec<u8>>,
+}
+
+/// Writes applied to a state tree at once, in order. A `None` value removes the key.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Encode, Decode)]
+pub struct Batch {
+    pub writes: Vec<(Key, Option<Vec<u8>>)>,
+}
 
-        let proof = storage
-            .inclusion_proof(&k)
-            .expect("failed inclusion proof.");
-        storage.verify_proof(&proof).expect("failed verify proof");
+impl Batch {
+    pub fn put<V: Encode>(&mut self, key: &Key, data: &V) {
+        self.writes.push((*key, Some(data.encode())));
     }
+
+    pub fn remove(&mut self, key: &Key) {
+        self.writes.push((*key, None));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.writes.is_empty()
+    }
+}
+
+pub type Result<T> = core::result::Result<T, StorageError>;
+
+pub trait VerkleStorage {
+    /// Scheme of the tree, the one to verify its proofs with.
+    fn scheme(&self) -> SchemeKind;
+    /// Commitment to the whole state.
+    fn root(&self) -> Result<Commitment>;
+    /// Root of the version of block `number`, `None` if it is not kept.
+    fn root_at(&self, number: u64) -> Result<Option<Commitment>>;
+    /// Proof of the value of `key`. `StorageError::KeyNotFound` if it is absent.
+    fn inclusion_proof(&self, key: &Key) -> Result<Proof>;
+    /// Proof of the value of `key` in the version of block `number`, or of its absence.
+    fn proof_at(&self, number: u64, key: &Key) -> Result<Proof>;
+    /// Proof of the values of `keys` at once. Absent keys are proven absent.
+    fn inclusion_multiproof(&self, keys: &[Key]) -> Result<MultiProof>;
+    /// Checks `proof` against the current root.
+    fn verify_proof(&self, proof: &Proof) -> Result<()>;
+    /// Applies the writes of `batch`. Commitments are computed once for all of them, and the
+    /// changed nodes are written to the database by `commit_version`.
+    fn apply(&self, batch: &Batch) -> Result<()>;
+    fn put<V: Encode>(&self, key: &Key, data: &V) -> Result<()>;
+    fn remove(&self, key: &Key) -> Result<()>;
+    /// The value of `key`, `None` if it is absent. `StorageError::Decode` if it is not a `V`.
+    fn get<V: Decode>(&self, key: &Key) -> Result<Option<V>>;
+    /// The value of `key` in the version of block `number`, as `get`.
+    /// `StorageError::UnknownVersion` if the version is not kept.
+    fn get_at<V: Decode>(&self, number: u64, key: &Key) -> Result<Option<V>>;
+    /// Keeps the current state as the version of block `number`, which must be above the
+    /// latest kept one. Committing the latest one again with the same state does nothing.
+    /// Later writes go to the next version.
+    fn commit_version(&self, number: u64) -> Result<()>;
+    /// Calls `visit` with every key and encoded value of the version of block `number`, in
+    /// the order of their paths.
+    fn leaves_at(
+        &self,
+        number: u64,
+        visit: &mut dyn FnMut(Key, Vec<u8>) -> Result<()>,
+    ) -> Result<()>;
+    /// Drops the versions of the blocks before `oldest` except `pinned`, and the nodes no
+    /// other kept version reaches. Returns the number of dropped nodes.
+    fn prune(&self, oldest: u64, pinned: Option<u64>) -> Result<usize>;
+    /// Drops every version and the pending writes, leaving an empty tree.
+    fn reset(&self) -> Result<()>;
+}
+
+pub trait VerkleSMTStorage: VerkleStorage {
+    /// Proof of the absence of `key`. `StorageError::KeyExists` if it holds a value.
+    fn inclusion_empty_proof(&self, key: &Key) -> Result<Proof>;
 }
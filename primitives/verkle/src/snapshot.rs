@@ -0,0 +1,262 @@
+//! Snapshots of the state tree at a block, to bootstrap a node without replaying the blocks
+//! before it.
+//!
+//! A snapshot starts with `SNAPSHOT_MAGIC`, then a frame of its `SnapshotHeader` and frames of
+//! `Chunk`s of leaves in path order, the last one empty. A frame is the little endian `u32`
+//! length of its data, the data, and the keccak hash of the data. It is written and read as a
+//! stream, a chunk at a time.
+use std::io::{Read, Write};
+
+use codec::{Decode, Encode};
+
+use crate::verifier::{keccak256, Commitment, EMPTY};
+use crate::{Batch, Key, Result, StorageError, VerkleStorage};
+
+pub const SNAPSHOT_MAGIC: &[u8] = b"intmax-state-snapshot/1";
+
+/// Largest frame read, well above a chunk of `export`.
+const MAX_FRAME_LEN: usize = 1 << 30;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct SnapshotHeader {
+    /// Block whose state tree the snapshot holds.
+    pub number: u64,
+    pub root: Commitment,
+}
+
+/// Leaves of a snapshot, numbered from 0.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+struct Chunk {
+    index: u64,
+    leaves: Vec<(Key, Vec<u8>)>,
+}
+
+/// Writes a snapshot of the state tree of block `number` to `out`, `chunk_leaves` leaves per
+/// chunk.
+pub fn export<T: VerkleStorage, W: Write>(
+    tree: &T,
+    number: u64,
+    chunk_leaves: usize,
+    mut out: W,
+) -> Result<SnapshotHeader> {
+    let root = tree
+        .root_at(number)?
+        .ok_or(StorageError::UnknownVersion { number })?;
+    let header = SnapshotHeader { number, root };
+    out.write_all(SNAPSHOT_MAGIC).map_err(invalid)?;
+    write_frame(&mut out, &header.encode())?;
+
+    let mut chunk = Chunk {
+        index: 0,
+        leaves: Vec::with_capacity(chunk_leaves),
+    };
+    tree.leaves_at(number, &mut |key, value| {
+        chunk.leaves.push((key, value));
+        if chunk.leaves.len() < chunk_leaves.max(1) {
+            return Ok(());
+        }
+
+        write_frame(&mut out, &chunk.encode())?;
+        chunk.index += 1;
+        chunk.leaves.clear();
+        Ok(())
+    })?;
+    if !chunk.leaves.is_empty() {
+        write_frame(&mut out, &chunk.encode())?;
+        chunk.index += 1;
+        chunk.leaves.clear();
+    }
+    write_frame(&mut out, &chunk.encode())?;
+    out.flush().map_err(invalid)?;
+
+    Ok(header)
+}
+
+/// Rebuilds the state tree of a snapshot read from `input` into `tree`, which must be empty,
+/// and keeps it as the version of the block of its header. Nothing is written to the database
+/// unless the rebuilt root is the one of the header.
+pub fn import<T: VerkleStorage, R: Read>(tree: &T, mut input: R) -> Result<SnapshotHeader> {
+    let mut magic = vec![0; SNAPSHOT_MAGIC.len()];
+    input.read_exact(&mut magic).map_err(invalid)?;
+    if magic != SNAPSHOT_MAGIC {
+        return Err(StorageError::InvalidSnapshot {
+            reason: "unknown format".to_string(),
+        });
+    }
+    if tree.root()? != EMPTY {
+        return Err(StorageError::InvalidSnapshot {
+            reason: "the state tree is not empty".to_string(),
+        });
+    }
+
+    let header: SnapshotHeader = decode(&read_frame(&mut input)?)?;
+    for index in 0.. {
+        let chunk: Chunk = decode(&read_frame(&mut input)?)?;
+        if chunk.index != index {
+            return Err(StorageError::InvalidSnapshot {
+                reason: format!("chunk {} instead of chunk {}", chunk.index, index),
+            });
+        }
+        if chunk.leaves.is_empty() {
+            break;
+        }
+
+        tree.apply(&Batch {
+            writes: chunk
+                .leaves
+                .into_iter()
+                .map(|(key, value)| (key, Some(value)))
+                .collect(),
+        })?;
+    }
+
+    let root = tree.root()?;
+    if root != header.root {
+        return Err(StorageError::SnapshotRootMismatch {
+            expected: header.root,
+            actual: root,
+        });
+    }
+    tree.commit_version(header.number)?;
+
+    Ok(header)
+}
+
+/// Writes `data` in a frame, see the module documentation.
+pub fn write_frame<W: Write>(out: &mut W, data: &[u8]) -> Result<()> {
+    let len = u32::try_from(data.len()).map_err(|_| StorageError::InvalidSnapshot {
+        reason: format!("frame of {} bytes", data.len()),
+    })?;
+    out.write_all(&len.to_le_bytes()).map_err(invalid)?;
+    out.write_all(data).map_err(invalid)?;
+    out.write_all(&keccak256(&[data])).map_err(invalid)
+}
+
+/// Reads the data of a frame written by `write_frame`, checking its hash.
+pub fn read_frame<R: Read>(input: &mut R) -> Result<Vec<u8>> {
+    let mut len = [0; 4];
+    input.read_exact(&mut len).map_err(invalid)?;
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(StorageError::InvalidSnapshot {
+            reason: format!("frame of {} bytes", len),
+        });
+    }
+
+    let mut data = vec![0; len];
+    input.read_exact(&mut data).map_err(invalid)?;
+    let mut checksum = [0; 32];
+    input.read_exact(&mut checksum).map_err(invalid)?;
+    if checksum != keccak256(&[&data]) {
+        return Err(StorageError::InvalidSnapshot {
+            reason: "checksum mismatch".to_string(),
+        });
+    }
+
+    Ok(data)
+}
+
+fn decode<V: Decode>(data: &[u8]) -> Result<V> {
+    V::decode(&mut &data[..]).map_err(|e| StorageError::InvalidSnapshot {
+        reason: e.to_string(),
+    })
+}
+
+fn invalid(e: std::io::Error) -> StorageError {
+    StorageError::InvalidSnapshot {
+        reason: e.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verifier::{Scheme, SparseMerkle, Verkle};
+    use crate::Tree;
+    use db::Db;
+
+    fn tree<S: Scheme>() -> Tree<Db<kvdb_memorydb::InMemory>, S> {
+        Tree::new(Db::new(kvdb_memorydb::create(1), 0))
+    }
+
+    fn key(i: u32) -> Key {
+        let mut key = [0; 32];
+        key[28..].copy_from_slice(&i.to_be_bytes());
+        Key::from(key)
+    }
+
+    /// A tree with a version at block 1 and another one at block 2.
+    fn exported<S: Scheme>() -> Tree<Db<kvdb_memorydb::InMemory>, S> {
+        let tree = tree::<S>();
+        for i in 0..100u32 {
+            tree.put(&key(i), &i).unwrap();
+        }
+        tree.commit_version(1).unwrap();
+        tree.remove(&key(0)).unwrap();
+        tree.put(&key(1), &1000u32).unwrap();
+        tree.commit_version(2).unwrap();
+        tree
+    }
+
+    fn round_trip<S: Scheme>() {
+        let tree = exported::<S>();
+        let mut out = vec![];
+        let header = export(&tree, 1, 16, &mut out).unwrap();
+        assert_eq!(header.root, tree.root_at(1).unwrap().unwrap());
+
+        let imported = self::tree::<S>();
+        assert_eq!(import(&imported, &out[..]).unwrap(), header);
+        assert_eq!(imported.root_at(1).unwrap(), Some(header.root));
+        assert_eq!(imported.get::<u32>(&key(0)).unwrap(), Some(0));
+        assert_eq!(imported.get::<u32>(&key(1)).unwrap(), Some(1));
+        assert!(import(&imported, &out[..]).is_err());
+
+        // An empty chunk ends the snapshot, even with no leaves at all.
+        let mut out = vec![];
+        let empty = self::tree::<S>();
+        empty.commit_version(1).unwrap();
+        assert_eq!(export(&empty, 1, 16, &mut out).unwrap().root, EMPTY);
+        import(&self::tree::<S>(), &out[..]).unwrap();
+        assert!(export(&empty, 2, 16, &mut out).is_err());
+    }
+
+    #[test]
+    fn success_round_trip() {
+        round_trip::<Verkle>();
+        round_trip::<SparseMerkle>();
+    }
+
+    #[test]
+    fn fail_import_corrupted() {
+        let mut out = vec![];
+        export(&exported::<Verkle>(), 2, 16, &mut out).unwrap();
+
+        let mut corrupted = out.clone();
+        let last = corrupted.len() - 40;
+        corrupted[last] ^= 1;
+        assert!(matches!(
+            import(&tree::<Verkle>(), &corrupted[..]),
+            Err(StorageError::InvalidSnapshot { .. })
+        ));
+        assert!(matches!(
+            import(&tree::<Verkle>(), &out[..out.len() - 1]),
+            Err(StorageError::InvalidSnapshot { .. })
+        ));
+
+        // A header with another root, its checksum fixed.
+        let mut forged = SNAPSHOT_MAGIC.to_vec();
+        let header = SnapshotHeader {
+            number: 2,
+            root: [1; 32],
+        };
+        write_frame(&mut forged, &header.encode()).unwrap();
+        let header_len = SNAPSHOT_MAGIC.len() + 4 + header.encode().len() + 32;
+        forged.extend_from_slice(&out[header_len..]);
+        let imported = tree::<Verkle>();
+        assert!(matches!(
+            import(&imported, &forged[..]),
+            Err(StorageError::SnapshotRootMismatch { expected, .. }) if expected == [1; 32]
+        ));
+        assert_eq!(imported.root_at(2).unwrap(), None);
+    }
+}
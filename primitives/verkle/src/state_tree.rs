@@ -0,0 +1,151 @@
+use codec::{Decode, Encode};
+use db::Database;
+
+use crate::{
+    Batch, Commitment, Key, MultiProof, Proof, Result, SchemeKind, SparseMerkleTree,
+    VerkleSMTStorage, VerkleStorage, VerkleTree,
+};
+
+/// The state tree of the scheme chosen at node start. A database keeps the scheme it was
+/// created with.
+pub enum StateTree<D: Database> {
+    Verkle(VerkleTree<D>),
+    SparseMerkle(SparseMerkleTree<D>),
+}
+
+impl<D: Database> StateTree<D> {
+    /// See `Tree::check_scheme`.
+    pub fn check_scheme(&self) -> Result<()> {
+        match self {
+            StateTree::Verkle(tree) => tree.check_scheme(),
+            StateTree::SparseMerkle(tree) => tree.check_scheme(),
+        }
+    }
+}
+
+impl<D: Database> VerkleStorage for StateTree<D> {
+    fn scheme(&self) -> SchemeKind {
+        match self {
+            StateTree::Verkle(tree) => tree.scheme(),
+            StateTree::SparseMerkle(tree) => tree.scheme(),
+        }
+    }
+
+    fn root(&self) -> Result<Commitment> {
+        match self {
+            StateTree::Verkle(tree) => tree.root(),
+            StateTree::SparseMerkle(tree) => tree.root(),
+        }
+    }
+
+    fn root_at(&self, number: u64) -> Result<Option<Commitment>> {
+        match self {
+            StateTree::Verkle(tree) => tree.root_at(number),
+            StateTree::SparseMerkle(tree) => tree.root_at(number),
+        }
+    }
+
+    fn inclusion_proof(&self, key: &Key) -> Result<Proof> {
+        match self {
+            StateTree::Verkle(tree) => tree.inclusion_proof(key),
+            StateTree::SparseMerkle(tree) => tree.inclusion_proof(key),
+        }
+    }
+
+    fn proof_at(&self, number: u64, key: &Key) -> Result<Proof> {
+        match self {
+            StateTree::Verkle(tree) => tree.proof_at(number, key),
+            StateTree::SparseMerkle(tree) => tree.proof_at(number, key),
+        }
+    }
+
+    fn inclusion_multiproof(&self, keys: &[Key]) -> Result<MultiProof> {
+        match self {
+            StateTree::Verkle(tree) => tree.inclusion_multiproof(keys),
+            StateTree::SparseMerkle(tree) => tree.inclusion_multiproof(keys),
+        }
+    }
+
+    fn verify_proof(&self, proof: &Proof) -> Result<()> {
+        match self {
+            StateTree::Verkle(tree) => tree.verify_proof(proof),
+            StateTree::SparseMerkle(tree) => tree.verify_proof(proof),
+        }
+    }
+
+    fn apply(&self, batch: &Batch) -> Result<()> {
+        match self {
+            StateTree::Verkle(tree) => tree.apply(batch),
+            StateTree::SparseMerkle(tree) => tree.apply(batch),
+        }
+    }
+
+    fn put<V: Encode>(&self, key: &Key, data: &V) -> Result<()> {
+        match self {
+            StateTree::Verkle(tree) => tree.put(key, data),
+            StateTree::SparseMerkle(tree) => tree.put(key, data),
+        }
+    }
+
+    fn remove(&self, key: &Key) -> Result<()> {
+        match self {
+            StateTree::Verkle(tree) => tree.remove(key),
+            StateTree::SparseMerkle(tree) => tree.remove(key),
+        }
+    }
+
+    fn get<V: Decode>(&self, key: &Key) -> Result<Option<V>> {
+        match self {
+            StateTree::Verkle(tree) => tree.get(key),
+            StateTree::SparseMerkle(tree) => tree.get(key),
+        }
+    }
+
+    fn get_at<V: Decode>(&self, number: u64, key: &Key) -> Result<Option<V>> {
+        match self {
+            StateTree::Verkle(tree) => tree.get_at(number, key),
+            StateTree::SparseMerkle(tree) => tree.get_at(number, key),
+        }
+    }
+
+    fn commit_version(&self, number: u64) -> Result<()> {
+        match self {
+            StateTree::Verkle(tree) => tree.commit_version(number),
+            StateTree::SparseMerkle(tree) => tree.commit_version(number),
+        }
+    }
+
+    fn leaves_at(
+        &self,
+        number: u64,
+        visit: &mut dyn FnMut(Key, Vec<u8>) -> Result<()>,
+    ) -> Result<()> {
+        match self {
+            StateTree::Verkle(tree) => tree.leaves_at(number, visit),
+            StateTree::SparseMerkle(tree) => tree.leaves_at(number, visit),
+        }
+    }
+
+    fn prune(&self, oldest: u64, pinned: Option<u64>) -> Result<usize> {
+        match self {
+            StateTree::Verkle(tree) => tree.prune(oldest, pinned),
+            StateTree::SparseMerkle(tree) => tree.prune(oldest, pinned),
+        }
+    }
+
+    fn reset(&self) -> Result<()> {
+        match self {
+            StateTree::Verkle(tree) => tree.reset(),
+            StateTree::SparseMerkle(tree) => tree.reset(),
+        }
+    }
+}
+
+impl<D: Database> VerkleSMTStorage for StateTree<D> {
+    fn inclusion_empty_proof(&self, key: &Key) -> Result<Proof> {
+        match self {
+            StateTree::Verkle(tree) => tree.inclusion_empty_proof(key),
+            StateTree::SparseMerkle(tree) => tree.inclusion_empty_proof(key),
+        }
+    }
+}
@@ -0,0 +1,1342 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::marker::PhantomData;
+use std::sync::{Mutex, MutexGuard};
+
+use codec::{Decode, Encode};
+use db::{DBKey, Database};
+use lru::LruCache;
+
+use crate::verifier::{self, Commitment, Scheme, SchemeKind, SparseMerkle, Verkle, EMPTY};
+use crate::{
+    Batch, Key, MultiProof, MultiProofEntry, Proof, Result, Slot, StorageError, VerkleSMTStorage,
+    VerkleStorage, STEM_LEN,
+};
+
+/// Nodes by the version they are written at and their path.
+const NODE_PREFIX: &[u8] = b"node";
+/// Version of the root node of each kept block.
+const VERSION_PREFIX: &[u8] = b"version";
+/// Nodes replaced while writing a version, with the version they were written at.
+const STALE_PREFIX: &[u8] = b"stale";
+/// Version of the current root node, 0 while the tree is empty.
+const HEAD_KEY: &[u8] = b"head";
+/// Latest block whose version is kept.
+const LATEST_VERSION_KEY: &[u8] = b"latest_version";
+/// Versions of the blocks below are pruned, except the pinned one.
+const PRUNED_KEY: &[u8] = b"pruned";
+const PINNED_KEY: &[u8] = b"pinned";
+/// Stale nodes which the pinned version still reaches.
+const PINNED_STALE_KEY: &[u8] = b"pinned_stale";
+/// `SchemeKind` of the tree, recorded by `check_scheme`.
+const SCHEME_KEY: &[u8] = b"scheme";
+
+/// Number of nodes read from the database kept in memory by default.
+const DEFAULT_CACHE_NODES: usize = 65536;
+
+/// A child of an internal node, written at `version`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Encode, Decode)]
+struct Child {
+    index: u8,
+    commitment: Commitment,
+    version: u64,
+}
+
+/// A node of the tree, stored under its version and the path leading to it.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+enum Node {
+    /// The non-empty children, sorted by index.
+    Internal(Vec<Child>),
+    /// The encoded values of the keys of `stem`, sorted by suffix. At least one.
+    Leaf {
+        stem: Vec<u8>,
+        values: Vec<(u8, Vec<u8>)>,
+    },
+}
+
+/// A subtree after a removal.
+enum Removed {
+    Empty,
+    /// A single leaf is left. It moves up to the slot of its parent.
+    Leaf(Node),
+    /// The node is left, changed.
+    Changed,
+}
+
+/// The nodes of the next version, kept in memory until its block is sealed.
+#[derive(Default)]
+struct Pending {
+    version: u64,
+    /// Changed nodes by path, `None` if removed.
+    nodes: HashMap<Vec<u8>, Option<Node>>,
+    /// Paths changed since the commitments were last computed.
+    unhashed: HashSet<Vec<u8>>,
+}
+
+/// State tree on a database column, laid out and committed to by `S`.
+///
+/// Writes go to the version of the next block, held in memory and written back at once by
+/// `VerkleStorage::commit_version`. A node replaced by a later version is stale from then on,
+/// and is deleted by `prune` once no kept version reaches it.
+pub struct Tree<D: Database, S: Scheme> {
+    db: D,
+    pending: Mutex<Pending>,
+    /// Nodes recently read from `db`, by version and path.
+    cache: Mutex<LruCache<(u64, Vec<u8>), Node>>,
+    _scheme: PhantomData<fn() -> S>,
+}
+
+/// See `verifier::Verkle`.
+pub type VerkleTree<D> = Tree<D, Verkle>;
+
+/// See `verifier::SparseMerkle`.
+pub type SparseMerkleTree<D> = Tree<D, SparseMerkle>;
+
+impl<D: Database, S: Scheme> Tree<D, S> {
+    pub fn new(db: D) -> Self {
+        Tree {
+            db,
+            pending: Mutex::default(),
+            cache: Mutex::new(LruCache::new(DEFAULT_CACHE_NODES)),
+            _scheme: PhantomData,
+        }
+    }
+
+    /// Keeps at most `nodes` nodes read from the database in memory, at least one.
+    pub fn with_cache_size(mut self, nodes: usize) -> Self {
+        self.cache = Mutex::new(LruCache::new(nodes.max(1)));
+        self
+    }
+
+    /// Records the scheme of the tree in the database on first start.
+    /// `StorageError::SchemeMismatch` if the database holds a tree of another scheme.
+    pub fn check_scheme(&self) -> Result<()> {
+        match self.load::<SchemeKind>(&DBKey::from_slice(SCHEME_KEY))? {
+            Some(stored) if stored == S::KIND => Ok(()),
+            Some(stored) => Err(StorageError::SchemeMismatch {
+                stored,
+                expected: S::KIND,
+            }),
+            None => {
+                let mut tx = self.db.make_tx();
+                tx.put(&SCHEME_KEY, &S::KIND);
+                self.db.commit(tx).map_err(|_| StorageError::Database)
+            }
+        }
+    }
+
+    fn pending(&self) -> MutexGuard<'_, Pending> {
+        self.pending.lock().expect("pending nodes lock poisoned")
+    }
+
+    /// The record under `key`, `StorageError::Database` if it does not decode.
+    fn load<V: Decode>(&self, key: &DBKey) -> Result<Option<V>> {
+        match self.db.get_raw(key) {
+            Some(value) => V::decode(&mut &value[..])
+                .map(Some)
+                .map_err(|_| StorageError::Database),
+            None => Ok(None),
+        }
+    }
+
+    fn node(&self, version: u64, path: &[u8]) -> Result<Option<Node>> {
+        let key = (version, path.to_vec());
+        let mut cache = self.cache.lock().expect("node cache lock poisoned");
+        if let Some(node) = cache.get(&key) {
+            return Ok(Some(node.clone()));
+        }
+
+        let node: Option<Node> = self.load(&node_key(version, path))?;
+        if let Some(node) = &node {
+            cache.put(key, node.clone());
+        }
+        Ok(node)
+    }
+
+    /// The node at `path` written at `version`, which may be the pending one.
+    fn read(&self, pending: Option<&Pending>, version: u64, path: &[u8]) -> Result<Option<Node>> {
+        match pending {
+            Some(pending) if !pending.nodes.is_empty() && version == pending.version => {
+                Ok(pending.nodes.get(path).cloned().flatten())
+            }
+            _ => self.node(version, path),
+        }
+    }
+
+    fn children(&self, pending: Option<&Pending>, version: u64, path: &[u8]) -> Result<Vec<Child>> {
+        match self.read(pending, version, path)? {
+            Some(Node::Internal(children)) => Ok(children),
+            _ => Ok(vec![]),
+        }
+    }
+
+    fn head(&self) -> Result<u64> {
+        Ok(self.load(&DBKey::from_slice(HEAD_KEY))?.unwrap_or_default())
+    }
+
+    /// Version of the current root node.
+    fn current(&self, pending: &Pending) -> Result<u64> {
+        match pending.nodes.contains_key(&[][..]) {
+            true => Ok(pending.version),
+            false => self.head(),
+        }
+    }
+
+    fn latest_version(&self) -> Result<u64> {
+        Ok(self
+            .load(&DBKey::from_slice(LATEST_VERSION_KEY))?
+            .unwrap_or_default())
+    }
+
+    /// Version of the root node of block `number`, `None` if it is not kept.
+    fn version_root(&self, number: u64) -> Result<Option<u64>> {
+        self.load(&prefixed_key(VERSION_PREFIX, number))
+    }
+
+    /// The value of `key` under the node `root`.
+    fn value<V: Decode>(
+        &self,
+        pending: Option<&Pending>,
+        root: u64,
+        key: &Key,
+    ) -> Result<Option<V>> {
+        let value = match self.walk(pending, root, key.stem())? {
+            (_, Some(Node::Leaf { stem, values })) if stem == key.stem() => values
+                .into_iter()
+                .find(|(suffix, _)| *suffix == key.suffix())
+                .map(|(_, value)| value),
+            _ => None,
+        };
+        match value {
+            Some(value) => V::decode(&mut &value[..])
+                .map(Some)
+                .map_err(|_| StorageError::Decode),
+            None => Ok(None),
+        }
+    }
+
+    fn stale(&self, version: u64) -> Result<Vec<(u64, Vec<u8>)>> {
+        Ok(self
+            .load(&prefixed_key(STALE_PREFIX, version))?
+            .unwrap_or_default())
+    }
+
+    /// Commitment of the tree whose root node is written at `root`.
+    fn commitment(&self, pending: Option<&Pending>, root: u64) -> Result<Commitment> {
+        Ok(S::internal_commitment(&commitments(&self.children(
+            pending,
+            root,
+            &[],
+        )?)))
+    }
+
+    /// The children of each node from the root node written at `root` down to the slot of
+    /// `stem`, and the node in that slot.
+    fn walk(
+        &self,
+        pending: Option<&Pending>,
+        root: u64,
+        stem: &[u8],
+    ) -> Result<(Vec<Vec<Child>>, Option<Node>)> {
+        let path = S::path(stem);
+        let mut levels = vec![self.children(pending, root, &[])?];
+        for depth in 0..path.len() {
+            let child = levels[depth]
+                .iter()
+                .find(|child| child.index == path[depth])
+                .map(|child| child.version);
+            let slot = match child {
+                Some(version) => self.read(pending, version, &path[..=depth])?,
+                None => None,
+            };
+            match slot {
+                Some(Node::Internal(next)) => levels.push(next),
+                slot => return Ok((levels, slot)),
+            }
+        }
+
+        Ok((levels, None))
+    }
+
+    /// Proof of the value of `key`, or of its absence, in the tree whose root node is written
+    /// at `root`.
+    fn prove(&self, pending: Option<&Pending>, root: u64, key: &Key) -> Result<Proof> {
+        let (levels, slot) = self.walk(pending, root, key.stem())?;
+        let (value, slot) = slot_of::<S>(key, slot);
+
+        Ok(Proof {
+            root: self.commitment(pending, root)?,
+            key: key.as_ref().to_vec(),
+            value,
+            openings: openings::<S>(&S::path(key.stem()), &levels),
+            slot,
+        })
+    }
+
+    /// Proof of `key` in the current state, with the pending commitments computed.
+    fn prove_current(&self, key: &Key) -> Result<Proof> {
+        let mut pending = self.pending();
+        hash::<S>(&mut pending);
+        let root = self.current(&pending)?;
+        self.prove(Some(&pending), root, key)
+    }
+}
+
+impl<D: Database, S: Scheme> VerkleStorage for Tree<D, S> {
+    fn scheme(&self) -> SchemeKind {
+        S::KIND
+    }
+
+    fn root(&self) -> Result<Commitment> {
+        let mut pending = self.pending();
+        hash::<S>(&mut pending);
+        let root = self.current(&pending)?;
+        self.commitment(Some(&pending), root)
+    }
+
+    fn root_at(&self, number: u64) -> Result<Option<Commitment>> {
+        match self.version_root(number)? {
+            Some(root) => Ok(Some(self.commitment(None, root)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn inclusion_proof(&self, key: &Key) -> Result<Proof> {
+        let proof = self.prove_current(key)?;
+        if proof.value.is_none() {
+            return Err(StorageError::KeyNotFound);
+        }
+
+        Ok(proof)
+    }
+
+    fn proof_at(&self, number: u64, key: &Key) -> Result<Proof> {
+        let root = self
+            .version_root(number)?
+            .ok_or(StorageError::UnknownVersion { number })?;
+
+        self.prove(None, root, key)
+    }
+
+    fn inclusion_multiproof(&self, keys: &[Key]) -> Result<MultiProof> {
+        let mut pending = self.pending();
+        hash::<S>(&mut pending);
+        let root = self.current(&pending)?;
+        let pending = Some(&*pending);
+
+        let mut nodes = BTreeMap::new();
+        let mut slots = BTreeSet::new();
+        let mut entries = Vec::with_capacity(keys.len());
+        for key in keys {
+            let path = S::path(key.stem());
+            let (levels, slot) = self.walk(pending, root, key.stem())?;
+            let (value, slot) = slot_of::<S>(key, slot);
+
+            let depth = levels.len();
+            slots.insert(path[..depth].to_vec());
+            for (depth, children) in levels.into_iter().enumerate() {
+                nodes.insert(path[..depth].to_vec(), commitments(&children));
+            }
+            entries.push(MultiProofEntry {
+                key: key.as_ref().to_vec(),
+                value,
+                depth: depth as u32,
+                slot,
+            });
+        }
+
+        // Each node opens its children on the paths, which the verifier recomputes.
+        let openings = nodes
+            .iter()
+            .map(|(node, children)| {
+                let indices: Vec<_> = (0..=u8::MAX)
+                    .filter(|index| {
+                        let mut child = node.clone();
+                        child.push(*index);
+                        nodes.contains_key(&child) || slots.contains(&child)
+                    })
+                    .collect();
+                S::open(children, &indices)
+            })
+            .collect();
+
+        Ok(MultiProof {
+            root: self.commitment(pending, root)?,
+            entries,
+            openings,
+        })
+    }
+
+    fn verify_proof(&self, proof: &Proof) -> Result<()> {
+        if proof.root != self.root()? {
+            return Err(StorageError::InvalidProof);
+        }
+
+        S::verify_proof(proof)
+    }
+
+    fn apply(&self, batch: &Batch) -> Result<()> {
+        // An empty leaf value stands for an absent key in proofs.
+        if batch
+            .writes
+            .iter()
+            .any(|(_, value)| matches!(value, Some(value) if value.is_empty()))
+        {
+            return Err(StorageError::EmptyValue);
+        }
+
+        let mut pending = self.pending();
+        if pending.nodes.is_empty() {
+            pending.version = self.latest_version()? + 1;
+        }
+        let mut update = Update::new(self, &mut pending);
+        for (key, value) in batch.writes.iter() {
+            let path = S::path(key.stem());
+            match value {
+                Some(value) => update.insert(&path, 0, key, value.clone())?,
+                None => {
+                    update.remove(&path, 0, key)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn put<V: Encode>(&self, key: &Key, data: &V) -> Result<()> {
+        let mut batch = Batch::default();
+        batch.put(key, data);
+        self.apply(&batch)
+    }
+
+    fn remove(&self, key: &Key) -> Result<()> {
+        let mut batch = Batch::default();
+        batch.remove(key);
+        self.apply(&batch)
+    }
+
+    fn get<V: Decode>(&self, key: &Key) -> Result<Option<V>> {
+        let pending = self.pending();
+        let root = self.current(&pending)?;
+        self.value(Some(&pending), root, key)
+    }
+
+    fn get_at<V: Decode>(&self, number: u64, key: &Key) -> Result<Option<V>> {
+        let root = self
+            .version_root(number)?
+            .ok_or(StorageError::UnknownVersion { number })?;
+
+        self.value(None, root, key)
+    }
+
+    fn commit_version(&self, number: u64) -> Result<()> {
+        let latest = self.latest_version()?;
+        let mut pending = self.pending();
+        hash::<S>(&mut pending);
+        if number == latest && latest != 0 {
+            // Committed already, e.g. before a crash lost the caller's own record of it.
+            let root = self.commitment(Some(&pending), self.current(&pending)?)?;
+            if self.root_at(number)? == Some(root) {
+                *pending = Pending::default();
+                return Ok(());
+            }
+        }
+        if number <= latest {
+            return Err(StorageError::StaleVersion { number, latest });
+        }
+
+        let mut tx = self.db.make_tx();
+        let head = match pending.nodes.is_empty() {
+            true => self.head()?,
+            false => {
+                // The nodes the pending ones replace are stale from the pending version.
+                let mut stored = Stored::new(self);
+                let mut stale = vec![];
+                for (path, node) in pending.nodes.iter() {
+                    if let Some((written, _)) = stored.get(path)? {
+                        stale.push((written, path.clone()));
+                    }
+                    if let Some(node) = node {
+                        tx.put(&node_key(pending.version, path), node);
+                    }
+                }
+                tx.put(&prefixed_key(STALE_PREFIX, pending.version), &stale);
+                tx.put(&HEAD_KEY, &pending.version);
+                pending.version
+            }
+        };
+        tx.put(&prefixed_key(VERSION_PREFIX, number), &head);
+        tx.put(&LATEST_VERSION_KEY, &number);
+        self.db.commit(tx).map_err(|_| StorageError::Database)?;
+
+        *pending = Pending::default();
+        Ok(())
+    }
+
+    fn leaves_at(
+        &self,
+        number: u64,
+        visit: &mut dyn FnMut(Key, Vec<u8>) -> Result<()>,
+    ) -> Result<()> {
+        let root = self
+            .version_root(number)?
+            .ok_or(StorageError::UnknownVersion { number })?;
+
+        // Depth first, the children of a node pushed in reverse so that the lowest index is
+        // visited first.
+        let mut stack = vec![(root, vec![])];
+        while let Some((version, path)) = stack.pop() {
+            match self.node(version, &path)? {
+                Some(Node::Internal(children)) => {
+                    for child in children.iter().rev() {
+                        let mut child_path = path.clone();
+                        child_path.push(child.index);
+                        stack.push((child.version, child_path));
+                    }
+                }
+                Some(Node::Leaf { stem, values }) => {
+                    let stem =
+                        <[u8; STEM_LEN]>::try_from(stem).map_err(|_| StorageError::Database)?;
+                    for (suffix, value) in values {
+                        visit(Key::new(stem, suffix), value)?;
+                    }
+                }
+                None => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn prune(&self, oldest: u64, pinned: Option<u64>) -> Result<usize> {
+        let pruned: u64 = self
+            .load(&DBKey::from_slice(PRUNED_KEY))?
+            .unwrap_or_default();
+        let previous_pin: Option<u64> = self.load(&DBKey::from_slice(PINNED_KEY))?;
+        let oldest = oldest.max(pruned);
+
+        let mut tx = self.db.make_tx();
+        // A node stale from version `stale` is reached by the versions from the one it was
+        // written at up to `stale`, all dropped once `stale <= oldest` but the pinned one.
+        let mut stale: Vec<(u64, u64, Vec<u8>)> = self
+            .load(&DBKey::from_slice(PINNED_STALE_KEY))?
+            .unwrap_or_default();
+        for version in pruned + 1..=oldest {
+            stale.extend(
+                self.stale(version)?
+                    .into_iter()
+                    .map(|(written, path)| (version, written, path)),
+            );
+            tx.delete(&prefixed_key(STALE_PREFIX, version));
+        }
+        let mut dropped = vec![];
+        stale.retain(|(version, written, path)| {
+            let reached = matches!(pinned, Some(pin) if *written <= pin && pin < *version);
+            if !reached {
+                tx.delete(&node_key(*written, path));
+                dropped.push((*written, path.clone()));
+            }
+            reached
+        });
+        tx.put(&PINNED_STALE_KEY, &stale);
+
+        for number in pruned..oldest {
+            if Some(number) != pinned {
+                tx.delete(&prefixed_key(VERSION_PREFIX, number));
+            }
+        }
+        match previous_pin {
+            Some(previous) if previous < pruned && Some(previous) != pinned => {
+                tx.delete(&prefixed_key(VERSION_PREFIX, previous))
+            }
+            _ => (),
+        }
+        tx.put(&PRUNED_KEY, &oldest);
+        match pinned {
+            Some(pinned) => tx.put(&PINNED_KEY, &pinned),
+            None => tx.delete(&PINNED_KEY),
+        }
+        self.db.commit(tx).map_err(|_| StorageError::Database)?;
+
+        let mut cache = self.cache.lock().expect("node cache lock poisoned");
+        for node in dropped.iter() {
+            cache.pop(node);
+        }
+        Ok(dropped.len())
+    }
+
+    fn reset(&self) -> Result<()> {
+        let mut pending = self.pending();
+        let mut tx = self.db.make_tx();
+        for prefix in [NODE_PREFIX, VERSION_PREFIX, STALE_PREFIX] {
+            tx.delete_prefix(&prefix);
+        }
+        for key in [
+            HEAD_KEY,
+            LATEST_VERSION_KEY,
+            PRUNED_KEY,
+            PINNED_KEY,
+            PINNED_STALE_KEY,
+        ] {
+            tx.delete(&key);
+        }
+        self.db.commit(tx).map_err(|_| StorageError::Database)?;
+
+        *pending = Pending::default();
+        self.cache.lock().expect("node cache lock poisoned").clear();
+        Ok(())
+    }
+}
+
+impl<D: Database, S: Scheme> VerkleSMTStorage for Tree<D, S> {
+    fn inclusion_empty_proof(&self, key: &Key) -> Result<Proof> {
+        let proof = self.prove_current(key)?;
+        if proof.value.is_some() {
+            return Err(StorageError::KeyExists);
+        }
+
+        Ok(proof)
+    }
+}
+
+/// The nodes of the latest written back version, by path, with the version they are written
+/// at.
+struct Stored<'a, D: Database, S: Scheme> {
+    tree: &'a Tree<D, S>,
+    nodes: HashMap<Vec<u8>, Option<(u64, Node)>>,
+}
+
+impl<'a, D: Database, S: Scheme> Stored<'a, D, S> {
+    fn new(tree: &'a Tree<D, S>) -> Self {
+        Stored {
+            tree,
+            nodes: HashMap::new(),
+        }
+    }
+
+    fn get(&mut self, path: &[u8]) -> Result<Option<(u64, Node)>> {
+        if let Some(stored) = self.nodes.get(path) {
+            return Ok(stored.clone());
+        }
+
+        let version = match path.split_last() {
+            None => Some(self.tree.head()?),
+            Some((index, parent)) => match self.get(parent)? {
+                Some((_, Node::Internal(children))) => children
+                    .iter()
+                    .find(|child| child.index == *index)
+                    .map(|child| child.version),
+                _ => None,
+            },
+        };
+        let stored = match version {
+            Some(version) => self.tree.node(version, path)?.map(|node| (version, node)),
+            None => None,
+        };
+        self.nodes.insert(path.to_vec(), stored.clone());
+        Ok(stored)
+    }
+}
+
+/// Changes the structure of the pending nodes. Their commitments are computed by `hash`.
+struct Update<'a, D: Database, S: Scheme> {
+    pending: &'a mut Pending,
+    stored: Stored<'a, D, S>,
+}
+
+impl<'a, D: Database, S: Scheme> Update<'a, D, S> {
+    fn new(tree: &'a Tree<D, S>, pending: &'a mut Pending) -> Self {
+        Update {
+            pending,
+            stored: Stored::new(tree),
+        }
+    }
+
+    fn node(&mut self, path: &[u8]) -> Result<Option<Node>> {
+        match self.pending.nodes.get(path) {
+            Some(node) => Ok(node.clone()),
+            None => Ok(self.stored.get(path)?.map(|(_, node)| node)),
+        }
+    }
+
+    fn set(&mut self, path: &[u8], node: Option<Node>) {
+        self.pending.nodes.insert(path.to_vec(), node);
+        self.pending.unhashed.insert(path.to_vec());
+    }
+
+    fn children(&mut self, path: &[u8]) -> Result<Vec<Child>> {
+        match self.node(path)? {
+            Some(Node::Internal(children)) => Ok(children),
+            _ => Ok(vec![]),
+        }
+    }
+
+    /// Sets the child `index` of the internal node at `parent` to the pending node under it.
+    fn link(&mut self, parent: &[u8], index: u8) -> Result<()> {
+        let mut children = self.children(parent)?;
+        set_child(&mut children, index, Some(EMPTY), self.pending.version);
+        self.set(parent, Some(Node::Internal(children)));
+        Ok(())
+    }
+
+    /// Inserts the value of `key` in the leaf of its stem under the internal node at
+    /// `path[..depth]`, creating them if needed.
+    fn insert(&mut self, path: &[u8], depth: usize, key: &Key, value: Vec<u8>) -> Result<()> {
+        let slot = &path[..=depth];
+        match self.node(slot)? {
+            Some(Node::Internal(_)) => self.insert(path, depth + 1, key, value)?,
+            Some(Node::Leaf { stem, values }) if stem != key.stem() => {
+                // Both stems share the slot: the other leaf moves one level down.
+                let other_path = S::path(&stem);
+                self.set(&other_path[..=depth + 1], Some(Node::Leaf { stem, values }));
+                self.set(slot, Some(Node::Internal(vec![])));
+                self.link(slot, other_path[depth + 1])?;
+                self.insert(path, depth + 1, key, value)?
+            }
+            Some(Node::Leaf { stem, mut values }) => {
+                let position = values.partition_point(|(suffix, _)| *suffix < key.suffix());
+                match values.get_mut(position) {
+                    Some((suffix, old)) if *suffix == key.suffix() => *old = value,
+                    _ => values.insert(position, (key.suffix(), value)),
+                }
+                self.set(slot, Some(Node::Leaf { stem, values }))
+            }
+            None => self.set(
+                slot,
+                Some(Node::Leaf {
+                    stem: key.stem().to_vec(),
+                    values: vec![(key.suffix(), value)],
+                }),
+            ),
+        }
+
+        self.link(&path[..depth], path[depth])
+    }
+
+    /// Removes the value of `key` under the internal node at `path[..depth]`, and the leaf of
+    /// its stem if no other value is left. Returns what is left of that node, `None` if `key`
+    /// is not stored.
+    fn remove(&mut self, path: &[u8], depth: usize, key: &Key) -> Result<Option<Removed>> {
+        let slot = &path[..=depth];
+        let removed = match self.node(slot)? {
+            Some(Node::Internal(_)) => match self.remove(path, depth + 1, key)? {
+                Some(removed) => removed,
+                None => return Ok(None),
+            },
+            Some(Node::Leaf { stem, mut values }) if stem == key.stem() => {
+                match values
+                    .iter()
+                    .position(|(suffix, _)| *suffix == key.suffix())
+                {
+                    Some(position) => values.remove(position),
+                    None => return Ok(None),
+                };
+                if values.is_empty() {
+                    self.set(slot, None);
+                    Removed::Empty
+                } else {
+                    self.set(slot, Some(Node::Leaf { stem, values }));
+                    Removed::Changed
+                }
+            }
+            _ => return Ok(None),
+        };
+
+        let (mut children, version) = (self.children(&path[..depth])?, self.pending.version);
+        match removed {
+            Removed::Empty => set_child(&mut children, path[depth], None, version),
+            Removed::Leaf(leaf) => {
+                self.set(slot, Some(leaf));
+                set_child(&mut children, path[depth], Some(EMPTY), version);
+            }
+            Removed::Changed => set_child(&mut children, path[depth], Some(EMPTY), version),
+        }
+
+        // Below the root, a node left with a single leaf is replaced by the leaf.
+        if depth > 0 {
+            match children.as_slice() {
+                [] => {
+                    self.set(&path[..depth], None);
+                    return Ok(Some(Removed::Empty));
+                }
+                [only] => {
+                    let mut child = path[..depth].to_vec();
+                    child.push(only.index);
+                    if let Some(leaf @ Node::Leaf { .. }) = self.node(&child)? {
+                        self.set(&child, None);
+                        self.set(&path[..depth], None);
+                        return Ok(Some(Removed::Leaf(leaf)));
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        self.set(&path[..depth], Some(Node::Internal(children)));
+        Ok(Some(Removed::Changed))
+    }
+}
+
+/// Computes the commitments of the nodes changed since the last call, each once, from the
+/// deepest up.
+fn hash<S: Scheme>(pending: &mut Pending) {
+    let mut paths: Vec<_> = pending.unhashed.drain().collect();
+    paths.sort_by_key(|path| Reverse(path.len()));
+    let changed: HashSet<_> = paths.iter().cloned().collect();
+    for path in paths {
+        let mut children = match pending.nodes.get(&path) {
+            Some(Some(Node::Internal(children))) => children.clone(),
+            _ => continue,
+        };
+        for child in children.iter_mut() {
+            let mut child_path = path.clone();
+            child_path.push(child.index);
+            if !changed.contains(&child_path) {
+                continue;
+            }
+            match pending.nodes.get(&child_path) {
+                Some(Some(Node::Leaf { stem, values })) => {
+                    let values = S::values_commitment(&value_commitments(values));
+                    child.commitment = S::leaf_commitment(stem, &values)
+                }
+                Some(Some(Node::Internal(grandchildren))) => {
+                    child.commitment = S::internal_commitment(&commitments(grandchildren))
+                }
+                _ => (),
+            }
+        }
+        pending.nodes.insert(path, Some(Node::Internal(children)));
+    }
+}
+
+/// The value of `key` and what proves it in the slot of its stem, holding `node`.
+fn slot_of<S: Scheme>(key: &Key, node: Option<Node>) -> (Option<Vec<u8>>, Slot) {
+    match node {
+        Some(Node::Leaf { stem, values }) if stem == key.stem() => {
+            let opening = S::open_values(&value_commitments(&values), &[key.suffix()]);
+            let value = values
+                .into_iter()
+                .find(|(suffix, _)| *suffix == key.suffix())
+                .map(|(_, value)| value);
+            (value, Slot::Stem(opening))
+        }
+        Some(Node::Leaf { stem, values }) => (
+            None,
+            Slot::Other {
+                stem,
+                values: S::values_commitment(&value_commitments(&values)),
+            },
+        ),
+        _ => (None, Slot::Empty),
+    }
+}
+
+fn value_commitments(values: &[(u8, Vec<u8>)]) -> Vec<(u8, Commitment)> {
+    values
+        .iter()
+        .map(|(suffix, value)| (*suffix, verifier::value_commitment(value)))
+        .collect()
+}
+
+fn commitments(children: &[Child]) -> Vec<(u8, Commitment)> {
+    children
+        .iter()
+        .map(|child| (child.index, child.commitment))
+        .collect()
+}
+
+/// The opening of each level of the child on `path`.
+fn openings<S: Scheme>(path: &[u8], levels: &[Vec<Child>]) -> Vec<Vec<u8>> {
+    levels
+        .iter()
+        .zip(path)
+        .map(|(children, index)| S::open(&commitments(children), &[*index]))
+        .collect()
+}
+
+/// Sets the child `index` of `children` to `commitment` written at `version`, removing it on
+/// `None`.
+fn set_child(children: &mut Vec<Child>, index: u8, commitment: Option<Commitment>, version: u64) {
+    let position = children.partition_point(|child| child.index < index);
+    let present = children.get(position).map(|child| child.index) == Some(index);
+    match (commitment, present) {
+        (Some(commitment), true) => {
+            children[position].commitment = commitment;
+            children[position].version = version;
+        }
+        (Some(commitment), false) => children.insert(
+            position,
+            Child {
+                index,
+                commitment,
+                version,
+            },
+        ),
+        (None, true) => {
+            children.remove(position);
+        }
+        (None, false) => (),
+    }
+}
+
+fn node_key(version: u64, path: &[u8]) -> DBKey {
+    let mut key = prefixed_key(NODE_PREFIX, version);
+    key.extend_from_slice(path);
+    key
+}
+
+fn prefixed_key(prefix: &[u8], version: u64) -> DBKey {
+    let mut key = DBKey::from_slice(prefix);
+    key.extend_from_slice(&version.to_be_bytes());
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use db::Db;
+
+    /// The key of suffix 0 of the stem `i`.
+    fn key(i: u32) -> Key {
+        let mut stem = [0; STEM_LEN];
+        stem[STEM_LEN - 4..].copy_from_slice(&i.to_be_bytes());
+        Key::new(stem, 0)
+    }
+
+    fn tree<S: Scheme>() -> Tree<Db<kvdb_memorydb::InMemory>, S> {
+        Tree::new(Db::new(kvdb_memorydb::create(1), 0))
+    }
+
+    /// Two keys whose paths share exactly `depth` indices, and a key on another first index.
+    fn colliding_keys<S: Scheme>(depth: usize) -> (Key, Key, Key) {
+        let keys = (0u32..).map(key);
+        let mut seen: HashMap<Vec<u8>, (Key, u8)> = HashMap::new();
+        for key in keys.clone() {
+            let path = S::path(key.stem());
+            match seen.get(&path[..depth]) {
+                Some((other, index)) if *index != path[depth] => {
+                    let third = keys
+                        .clone()
+                        .find(|third| S::path(third.stem())[0] != path[0])
+                        .unwrap();
+                    return (*other, key, third);
+                }
+                Some(_) => (),
+                None => {
+                    seen.insert(path[..depth].to_vec(), (key, path[depth]));
+                }
+            }
+        }
+        unreachable!()
+    }
+
+    fn put_get_remove<S: Scheme>() {
+        let tree = tree::<S>();
+        let k = key(5);
+        assert_eq!(tree.root().unwrap(), EMPTY);
+        tree.put(&k, &10u32).expect("put ok.");
+        assert_eq!(tree.get::<u32>(&k).unwrap(), Some(10));
+
+        tree.put(&k, &20u32).expect("put ok.");
+        assert_eq!(tree.get::<u32>(&k).unwrap(), Some(20));
+
+        tree.remove(&k).expect("remove ok.");
+        assert_eq!(tree.get::<u32>(&k).unwrap(), None);
+        assert_eq!(tree.root().unwrap(), EMPTY);
+        assert_eq!(tree.put(&k, &()), Err(StorageError::EmptyValue));
+
+        tree.put(&k, &1u8).unwrap();
+        assert_eq!(tree.get::<u32>(&k), Err(StorageError::Decode));
+    }
+
+    #[test]
+    fn success_put_get_remove() {
+        put_get_remove::<Verkle>();
+        put_get_remove::<SparseMerkle>();
+    }
+
+    fn root_is_independent_of_history<S: Scheme>() {
+        let (a, b, c) = colliding_keys::<S>(2);
+
+        let tree = tree::<S>();
+        tree.put(&a, &1u32).unwrap();
+        let only_a = tree.root().unwrap();
+        tree.put(&b, &2u32).unwrap();
+        tree.put(&c, &3u32).unwrap();
+        assert_eq!(tree.get::<u32>(&a).unwrap(), Some(1));
+        assert_eq!(tree.get::<u32>(&b).unwrap(), Some(2));
+
+        let other = self::tree::<S>();
+        other.put(&c, &3u32).unwrap();
+        other.put(&b, &2u32).unwrap();
+        other.put(&a, &1u32).unwrap();
+        assert_eq!(tree.root().unwrap(), other.root().unwrap());
+
+        // Removing the keys moves `a` back up to the root.
+        tree.remove(&b).unwrap();
+        tree.remove(&c).unwrap();
+        assert_eq!(tree.root().unwrap(), only_a);
+        assert_eq!(tree.inclusion_proof(&a).unwrap().openings.len(), 1);
+    }
+
+    #[test]
+    fn success_root_is_independent_of_history() {
+        root_is_independent_of_history::<Verkle>();
+        root_is_independent_of_history::<SparseMerkle>();
+    }
+
+    fn inclusion_proof<S: Scheme>() {
+        let tree = tree::<S>();
+        let (a, b, c) = colliding_keys::<S>(2);
+        tree.put(&a, &10u32).expect("put ok.");
+        tree.put(&b, &11u32).expect("put ok.");
+        tree.put(&c, &12u32).expect("put ok.");
+
+        let proof = tree.inclusion_proof(&a).expect("inclusion proof.");
+        assert_eq!(proof.openings.len(), 3);
+        assert_eq!(proof.root, tree.root().unwrap());
+        tree.verify_proof(&proof).expect("verify proof.");
+        S::verify_proof(&proof).expect("verify ok.");
+        S::verify(&tree.root().unwrap(), &proof, &a, &10u32).expect("verify ok.");
+
+        tree.put(&b, &12u32).expect("put ok.");
+        assert!(tree.verify_proof(&proof).is_err());
+        tree.verify_proof(&tree.inclusion_proof(&a).unwrap())
+            .expect("verify proof.");
+        assert!(tree.inclusion_proof(&key(u32::MAX)).is_err());
+    }
+
+    #[test]
+    fn success_inclusion_proof() {
+        inclusion_proof::<Verkle>();
+        inclusion_proof::<SparseMerkle>();
+    }
+
+    fn inclusion_empty_proof<S: Scheme>() {
+        let tree = tree::<S>();
+        let (a, b, _) = colliding_keys::<S>(1);
+        let proof = tree.inclusion_empty_proof(&a).expect("empty proof.");
+        S::verify_absence(&EMPTY, &proof, &a).expect("verify ok.");
+
+        // The slot of `a` holds `b`.
+        tree.put(&b, &10u32).expect("put ok.");
+        let proof = tree.inclusion_empty_proof(&a).expect("empty proof.");
+        tree.verify_proof(&proof).expect("verify proof.");
+        S::verify_absence(&tree.root().unwrap(), &proof, &a).expect("verify ok.");
+        assert!(S::verify_absence(&tree.root().unwrap(), &proof, &b).is_err());
+
+        tree.put(&a, &10u32).expect("put ok.");
+        assert!(tree.inclusion_empty_proof(&a).is_err());
+        assert!(S::verify_absence(&tree.root().unwrap(), &proof, &a).is_err());
+    }
+
+    fn shared_stem<S: Scheme>() {
+        let tree = tree::<S>();
+        let (a, b, _) = colliding_keys::<S>(1);
+        let stem: [u8; STEM_LEN] = a.stem().try_into().unwrap();
+        let (nonce, balance, absent) = (a, Key::new(stem, 1), Key::new(stem, 2));
+        tree.put(&nonce, &1u32).unwrap();
+        tree.put(&balance, &2u32).unwrap();
+        tree.put(&b, &3u32).unwrap();
+        assert_eq!(tree.get::<u32>(&balance).unwrap(), Some(2));
+
+        // The keys of a stem share its leaf, so their proofs share its path.
+        let root = tree.root().unwrap();
+        let (first, second) = (
+            tree.inclusion_proof(&nonce).unwrap(),
+            tree.inclusion_proof(&balance).unwrap(),
+        );
+        assert_eq!(first.openings, second.openings);
+        S::verify(&root, &first, &nonce, &1u32).expect("verify ok.");
+        S::verify(&root, &second, &balance, &2u32).expect("verify ok.");
+        assert!(S::verify(&root, &first, &balance, &1u32).is_err());
+        let proof = tree.inclusion_empty_proof(&absent).unwrap();
+        assert!(matches!(proof.slot, Slot::Stem(_)));
+        S::verify_absence(&root, &proof, &absent).expect("verify ok.");
+
+        let mut leaves = vec![];
+        tree.commit_version(1).unwrap();
+        tree.leaves_at(1, &mut |key, _| {
+            leaves.push(key);
+            Ok(())
+        })
+        .unwrap();
+        assert!(leaves.windows(2).any(|pair| pair == [nonce, balance]));
+
+        // Removing one value keeps the leaf of the others.
+        tree.remove(&nonce).unwrap();
+        assert_eq!(tree.get::<u32>(&nonce).unwrap(), None);
+        assert_eq!(tree.get::<u32>(&balance).unwrap(), Some(2));
+        tree.remove(&absent).unwrap();
+        let only = self::tree::<S>();
+        only.put(&b, &3u32).unwrap();
+        only.put(&balance, &2u32).unwrap();
+        assert_eq!(tree.root().unwrap(), only.root().unwrap());
+        tree.remove(&balance).unwrap();
+        only.remove(&balance).unwrap();
+        assert_eq!(tree.root().unwrap(), only.root().unwrap());
+    }
+
+    #[test]
+    fn success_shared_stem() {
+        shared_stem::<Verkle>();
+        shared_stem::<SparseMerkle>();
+    }
+
+    #[test]
+    fn success_inclusion_empty_proof() {
+        inclusion_empty_proof::<Verkle>();
+        inclusion_empty_proof::<SparseMerkle>();
+    }
+
+    fn multiproof<S: Scheme>() {
+        let tree = tree::<S>();
+        let keys: Vec<_> = (0u32..64).map(key).collect();
+        for (i, key) in keys.iter().enumerate() {
+            tree.put(key, &(i as u32)).expect("put ok.");
+        }
+        let mut proven: Vec<_> = keys.iter().step_by(2).cloned().collect();
+        proven.extend((1000u32..1008).map(key));
+        proven.push(keys[0]);
+
+        let proof = tree.inclusion_multiproof(&proven).expect("multiproof.");
+        assert_eq!(proof.root, tree.root().unwrap());
+        S::verify_multiproof(&proof).expect("verify ok.");
+        for (entry, key) in proof.entries.iter().zip(&proven) {
+            assert_eq!(entry.key, key.as_ref());
+            assert_eq!(
+                entry.value,
+                tree.get::<u32>(key).unwrap().map(|value| value.encode())
+            );
+        }
+
+        let individual: usize = proven
+            .iter()
+            .map(|key| {
+                tree.inclusion_proof(key)
+                    .or_else(|_| tree.inclusion_empty_proof(key))
+                    .unwrap()
+                    .encode()
+                    .len()
+            })
+            .sum();
+        assert!(proof.encode().len() < individual);
+
+        let mut forged = proof.clone();
+        forged.entries[0].value = Some(1000u32.encode());
+        assert!(S::verify_multiproof(&forged).is_err());
+        let mut forged = proof.clone();
+        forged.entries[0].depth += 1;
+        assert!(S::verify_multiproof(&forged).is_err());
+        // A depth past the end of the path is rejected, not sliced.
+        for depth in [S::path(proven[0].stem()).len() as u32 + 1, u32::MAX] {
+            let mut forged = proof.clone();
+            forged.entries[0].depth = depth;
+            assert_eq!(
+                S::verify_multiproof(&forged),
+                Err(StorageError::InvalidProof)
+            );
+        }
+        let mut forged = proof.clone();
+        forged.openings.pop();
+        assert!(S::verify_multiproof(&forged).is_err());
+        assert!(S::verify_multiproof(&MultiProof {
+            root: EMPTY,
+            entries: vec![],
+            openings: vec![],
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn success_multiproof() {
+        multiproof::<Verkle>();
+        multiproof::<SparseMerkle>();
+    }
+
+    fn versions<S: Scheme>() {
+        let tree = tree::<S>();
+        let (a, b, c) = colliding_keys::<S>(2);
+        tree.put(&a, &1u32).unwrap();
+        tree.put(&b, &2u32).unwrap();
+        tree.commit_version(1).unwrap();
+        let first = tree.root().unwrap();
+
+        tree.put(&a, &3u32).unwrap();
+        tree.put(&c, &4u32).unwrap();
+        tree.remove(&b).unwrap();
+        tree.commit_version(2).unwrap();
+        let second = tree.root().unwrap();
+        tree.commit_version(2).expect("same state again ok.");
+        tree.put(&a, &5u32).unwrap();
+        assert!(tree.commit_version(2).is_err());
+
+        assert_eq!(tree.root_at(1).unwrap(), Some(first));
+        let proof = tree.proof_at(1, &a).expect("proof at 1.");
+        assert_eq!(proof.root, first);
+        S::verify(&first, &proof, &a, &1u32).expect("verify ok.");
+        S::verify_absence(&first, &tree.proof_at(1, &c).unwrap(), &c).expect("verify ok.");
+
+        assert_eq!(tree.root_at(2).unwrap(), Some(second));
+        S::verify(&second, &tree.proof_at(2, &a).unwrap(), &a, &3u32).expect("verify ok.");
+        S::verify_absence(&second, &tree.proof_at(2, &b).unwrap(), &b).expect("verify ok.");
+        assert_eq!(tree.get::<u32>(&a).unwrap(), Some(5));
+        assert_eq!(tree.get_at::<u32>(1, &a).unwrap(), Some(1));
+        assert_eq!(tree.get_at::<u32>(1, &b).unwrap(), Some(2));
+        assert_eq!(tree.get_at::<u32>(2, &a).unwrap(), Some(3));
+        assert_eq!(tree.get_at::<u32>(2, &b).unwrap(), None);
+
+        assert_eq!(tree.root_at(3).unwrap(), None);
+        assert!(tree.proof_at(3, &a).is_err());
+        assert_eq!(
+            tree.get_at::<u32>(3, &a),
+            Err(StorageError::UnknownVersion { number: 3 })
+        );
+
+        tree.reset().unwrap();
+        assert_eq!(tree.root().unwrap(), EMPTY);
+        assert_eq!(tree.root_at(1).unwrap(), None);
+        tree.put(&a, &1u32).unwrap();
+        tree.put(&b, &2u32).unwrap();
+        tree.commit_version(1).expect("version 1 again.");
+        assert_eq!(tree.root_at(1).unwrap(), Some(first));
+    }
+
+    #[test]
+    fn success_versions() {
+        versions::<Verkle>();
+        versions::<SparseMerkle>();
+    }
+
+    /// Every kept version proves the values of all `keys` it holds.
+    fn assert_version<S: Scheme>(
+        tree: &Tree<Db<kvdb_memorydb::InMemory>, S>,
+        number: u64,
+        keys: &[Key],
+    ) {
+        let root = tree.root_at(number).unwrap().expect("kept version.");
+        for (i, key) in keys.iter().enumerate() {
+            let proof = tree.proof_at(number, key).unwrap();
+            assert_eq!(proof.root, root);
+            S::verify_proof(&proof).expect("verify ok.");
+            let expected = (i as u64 != number).then(|| (number as u32).encode());
+            assert_eq!(proof.value, expected);
+        }
+    }
+
+    fn prune<S: Scheme>() {
+        let tree = tree::<S>();
+        let keys: Vec<_> = (0u32..16).map(key).collect();
+        // Block `n` sets every key to `n` but the key `n`, which it removes.
+        for number in 1..=6 {
+            for key in keys.iter() {
+                tree.put(key, &(number as u32)).unwrap();
+            }
+            tree.remove(&keys[number as usize]).unwrap();
+            tree.commit_version(number).unwrap();
+        }
+
+        assert!(tree.prune(4, Some(2)).unwrap() > 0);
+        assert_eq!(tree.root_at(1).unwrap(), None);
+        assert_eq!(tree.root_at(3).unwrap(), None);
+        for number in [2, 4, 5, 6] {
+            assert_version(&tree, number, &keys);
+        }
+
+        // Moving the pin drops the version it kept.
+        assert!(tree.prune(4, None).unwrap() > 0);
+        assert_eq!(tree.root_at(2).unwrap(), None);
+        assert!(tree.prune(6, None).unwrap() > 0);
+        assert_eq!(tree.prune(6, None).unwrap(), 0);
+        assert_eq!(tree.root_at(5).unwrap(), None);
+        assert_version(&tree, 6, &keys);
+
+        tree.put(&keys[6], &7u32).unwrap();
+        tree.commit_version(7).unwrap();
+        assert_eq!(tree.get::<u32>(&keys[6]).unwrap(), Some(7));
+        assert_version(&tree, 6, &keys);
+    }
+
+    #[test]
+    fn success_prune() {
+        prune::<Verkle>();
+        prune::<SparseMerkle>();
+    }
+
+    fn batch<S: Scheme>() {
+        let db = Db::new(kvdb_memorydb::create(1), 0);
+        let tree = Tree::<_, S>::new(db.clone()).with_cache_size(2);
+        let keys: Vec<_> = (0u32..64).map(key).collect();
+        for key in keys.iter() {
+            tree.put(key, &1u32).unwrap();
+        }
+        tree.commit_version(1).unwrap();
+
+        let mut batch = Batch::default();
+        for (i, key) in keys.iter().enumerate() {
+            match i % 3 {
+                0 => batch.remove(key),
+                _ => batch.put(key, &(i as u32)),
+            }
+        }
+        batch.put(&key(1000), &2u32);
+        tree.apply(&batch).unwrap();
+
+        let sequential = self::tree::<S>();
+        for (key, value) in batch.writes.iter() {
+            if let Some(value) = value {
+                sequential
+                    .put(key, &u32::decode(&mut &value[..]).unwrap())
+                    .unwrap();
+            }
+        }
+        assert_eq!(tree.root().unwrap(), sequential.root().unwrap());
+        assert_eq!(tree.get::<u32>(&keys[0]).unwrap(), None);
+        assert_eq!(tree.get::<u32>(&keys[1]).unwrap(), Some(1));
+
+        // The writes stay in memory until the block is sealed.
+        let reopened = Tree::<_, S>::new(db.clone());
+        assert_eq!(reopened.root().unwrap(), tree.root_at(1).unwrap().unwrap());
+        tree.commit_version(2).unwrap();
+        let reopened = Tree::<_, S>::new(db);
+        assert_eq!(reopened.root().unwrap(), sequential.root().unwrap());
+        assert_eq!(reopened.get::<u32>(&key(1000)).unwrap(), Some(2));
+
+        let mut invalid = Batch::default();
+        invalid.put(&keys[0], &());
+        assert!(tree.apply(&invalid).is_err());
+    }
+
+    #[test]
+    fn success_batch() {
+        batch::<Verkle>();
+        batch::<SparseMerkle>();
+    }
+
+    #[test]
+    fn fail_check_scheme() {
+        let db = Db::new(kvdb_memorydb::create(1), 0);
+        let verkle = Tree::<_, Verkle>::new(db.with_column(0));
+        verkle.check_scheme().expect("first start ok.");
+        verkle.check_scheme().expect("same scheme ok.");
+
+        let smt = Tree::<_, SparseMerkle>::new(db.with_column(0));
+        assert_eq!(
+            smt.check_scheme(),
+            Err(StorageError::SchemeMismatch {
+                stored: SchemeKind::Verkle,
+                expected: SchemeKind::SparseMerkle,
+            })
+        );
+    }
+
+    #[test]
+    fn success_schemes_commit_differently() {
+        let (verkle, smt) = (tree::<Verkle>(), tree::<SparseMerkle>());
+        verkle.put(&key(1), &1u32).unwrap();
+        smt.put(&key(1), &1u32).unwrap();
+
+        assert_ne!(verkle.root().unwrap(), EMPTY);
+        assert_ne!(verkle.root().unwrap(), smt.root().unwrap());
+    }
+}
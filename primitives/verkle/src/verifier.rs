@@ -0,0 +1,644 @@
+//! Proof verification without a storage, for clients which do not run a node.
+//!
+//! A state tree follows the hash of the stems of the keys, one child index per level, and the
+//! leaf of a stem sits at the shallowest slot no other stem shares. The leaf commits to the stem
+//! and to the values of its keys by their suffix. A proof carries, for each node from the root
+//! down to the slot of the stem, an opening of the node proving the commitment of its child on
+//! the path, then the opening of the values of the leaf at the suffix. `Verkle` opens a node
+//! with a vector commitment proof of constant size, `SparseMerkle` with the commitment of the
+//! other child.
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec;
+use alloc::vec::Vec;
+use ark_ff::Zero;
+use codec::{Decode, Encode};
+use tiny_keccak::{Hasher, Keccak};
+
+use crate::ipa::{self, OPENING_LEN, WIDTH};
+use crate::{MultiProof, Proof, Result, Slot, StorageError, KEY_LEN, STEM_LEN};
+
+/// Commitment to a node of the tree. The commitment of an empty subtree is zero.
+pub type Commitment = [u8; 32];
+
+/// Commitment of an empty subtree, and the root of an empty tree.
+pub const EMPTY: Commitment = [0; 32];
+
+const LEAF_DOMAIN: &[u8] = b"intmax-verkle-leaf";
+const VALUE_DOMAIN: &[u8] = b"intmax-verkle-value";
+const SMT_LEAF_DOMAIN: &[u8] = b"intmax-smt-leaf";
+const SMT_VALUES_DOMAIN: &[u8] = b"intmax-smt-values";
+const SMT_INTERNAL_DOMAIN: &[u8] = b"intmax-smt-internal";
+
+pub(crate) fn keccak256(data: &[&[u8]]) -> Commitment {
+    let mut hasher = Keccak::v256();
+    for bytes in data {
+        hasher.update(bytes);
+    }
+    let mut out = [0; 32];
+    hasher.finalize(&mut out);
+    out
+}
+
+/// Commitment of an encoded value in the values of a leaf.
+pub fn value_commitment(value: &[u8]) -> Commitment {
+    keccak256(&[VALUE_DOMAIN, value])
+}
+
+/// A `Scheme`, to pick the verifier of a proof at run time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Encode, Decode)]
+pub enum SchemeKind {
+    #[codec(index = 2)]
+    Verkle,
+    #[codec(index = 1)]
+    SparseMerkle,
+}
+
+impl core::fmt::Display for SchemeKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            SchemeKind::Verkle => write!(f, "Verkle"),
+            SchemeKind::SparseMerkle => write!(f, "SparseMerkle"),
+        }
+    }
+}
+
+/// The layout of a state tree and the commitments of its nodes.
+pub trait Scheme {
+    const KIND: SchemeKind;
+
+    /// Child index of each level along the path of `stem`.
+    fn path(stem: &[u8]) -> Vec<u8>;
+
+    /// Commitment of the leaf of `stem` whose values commit to `values`.
+    fn leaf_commitment(stem: &[u8], values: &Commitment) -> Commitment;
+
+    /// Commitment to the `value_commitment`s of the values of a leaf, sorted by suffix.
+    fn values_commitment(values: &[(u8, Commitment)]) -> Commitment;
+
+    /// Opening of the values of a leaf proving the commitments of the values at `suffixes`,
+    /// sorted.
+    fn open_values(values: &[(u8, Commitment)], suffixes: &[u8]) -> Vec<u8>;
+
+    /// The commitment of the values `opening` opens, if it proves the commitments of the
+    /// `opened` values, sorted by suffix, `EMPTY` for an absent one.
+    fn opened_values(opening: &[u8], opened: &[(u8, Commitment)]) -> Result<Commitment>;
+
+    /// Commitment of a node from its non-empty `children`, sorted by index. `EMPTY` if there
+    /// are none.
+    fn internal_commitment(children: &[(u8, Commitment)]) -> Commitment;
+
+    /// Opening of the node with the non-empty `children`, sorted by index, proving the
+    /// commitments of its children at `indices`, sorted too.
+    fn open(children: &[(u8, Commitment)], indices: &[u8]) -> Vec<u8>;
+
+    /// The commitment of the node `opening` opens, if it proves the commitments of the
+    /// `opened` children, sorted by index, `EMPTY` for an empty one.
+    fn opened(opening: &[u8], opened: &[(u8, Commitment)]) -> Result<Commitment>;
+
+    /// Checks that `proof` proves its value, or the absence of its key, under its root.
+    ///
+    /// The slot of an absent key is empty, holds the leaf of its stem without a value at its
+    /// suffix, or the leaf of another stem sharing the path of the stem down to the slot.
+    fn verify_proof(proof: &Proof) -> Result<()> {
+        let path = Self::path(stem(&proof.key)?);
+        let depth = proof.openings.len();
+        let slot = slot_commitment::<Self>(&proof.key, &proof.value, &proof.slot, &path, depth)?;
+        if depth == 0 {
+            return Err(StorageError::InvalidProof);
+        }
+
+        let mut node = slot;
+        for (depth, opening) in proof.openings.iter().enumerate().rev() {
+            node = Self::opened(opening, &[(path[depth], node)])?;
+        }
+        match node {
+            root if root == proof.root => Ok(()),
+            _ => Err(StorageError::InvalidProof),
+        }
+    }
+
+    /// Checks that `proof` proves the values of its entries, or their absence, under its
+    /// root, in one pass over the nodes.
+    fn verify_multiproof(proof: &MultiProof) -> Result<()> {
+        // The commitment of every slot and node on the paths, by path.
+        let mut commitments = BTreeMap::new();
+        let mut nodes = BTreeSet::new();
+        for entry in &proof.entries {
+            let path = Self::path(stem(&entry.key)?);
+            let depth = entry.depth as usize;
+            if depth > path.len() {
+                return Err(StorageError::InvalidProof);
+            }
+            let slot =
+                slot_commitment::<Self>(&entry.key, &entry.value, &entry.slot, &path, depth)?;
+            // Keys sharing a slot must agree on it.
+            if *commitments.entry(path[..depth].to_vec()).or_insert(slot) != slot {
+                return Err(StorageError::InvalidProof);
+            }
+            nodes.extend((0..depth).map(|depth| path[..depth].to_vec()));
+        }
+        if nodes.len() != proof.openings.len()
+            || nodes.iter().any(|node| commitments.contains_key(node))
+        {
+            return Err(StorageError::InvalidProof);
+        }
+
+        // A path sorts before its extensions, so children are committed before their parent.
+        for (node, opening) in nodes.iter().zip(&proof.openings).rev() {
+            let opened: Vec<_> = commitments
+                .range::<Vec<u8>, _>(node..)
+                .take_while(|(child, _)| child.starts_with(node))
+                .filter(|(child, _)| child.len() == node.len() + 1)
+                .map(|(child, commitment)| (child[node.len()], *commitment))
+                .collect();
+            commitments.insert(node.clone(), Self::opened(opening, &opened)?);
+        }
+
+        match commitments.get(&Vec::new()) {
+            Some(root) if *root == proof.root => Ok(()),
+            _ => Err(StorageError::InvalidProof),
+        }
+    }
+
+    /// Checks that `proof` proves `value` under `key` in the tree of `root`.
+    fn verify<K: AsRef<[u8]>, V: Encode>(
+        root: &Commitment,
+        proof: &Proof,
+        key: &K,
+        value: &V,
+    ) -> Result<()> {
+        if proof.root != *root
+            || proof.key != key.as_ref()
+            || proof.value.as_ref() != Some(&value.encode())
+        {
+            return Err(StorageError::InvalidProof);
+        }
+
+        Self::verify_proof(proof)
+    }
+
+    /// Checks that `proof` proves that nothing is stored under `key` in the tree of `root`.
+    fn verify_absence<K: AsRef<[u8]>>(root: &Commitment, proof: &Proof, key: &K) -> Result<()> {
+        if proof.root != *root || proof.key != key.as_ref() || proof.value.is_some() {
+            return Err(StorageError::InvalidProof);
+        }
+
+        Self::verify_proof(proof)
+    }
+}
+
+/// Tree of width 256 along the keccak hash of the keys, one byte per level, whose nodes are
+/// Pedersen vector commitments to their children over Bandersnatch. A node opens each child with
+/// an inner product argument of 544 bytes, whatever its other children: a proof takes about
+/// 576 bytes per level, and a state of `n` stems about `log256(n)` levels. The values of a leaf
+/// are committed to and opened as the children of a node.
+pub struct Verkle;
+
+impl Scheme for Verkle {
+    const KIND: SchemeKind = SchemeKind::Verkle;
+
+    fn path(stem: &[u8]) -> Vec<u8> {
+        keccak256(&[stem]).to_vec()
+    }
+
+    fn leaf_commitment(stem: &[u8], values: &Commitment) -> Commitment {
+        keccak256(&[LEAF_DOMAIN, stem, values])
+    }
+
+    fn values_commitment(values: &[(u8, Commitment)]) -> Commitment {
+        Self::internal_commitment(values)
+    }
+
+    fn open_values(values: &[(u8, Commitment)], suffixes: &[u8]) -> Vec<u8> {
+        Self::open(values, suffixes)
+    }
+
+    fn opened_values(opening: &[u8], opened: &[(u8, Commitment)]) -> Result<Commitment> {
+        Self::opened(opening, opened)
+    }
+
+    fn internal_commitment(children: &[(u8, Commitment)]) -> Commitment {
+        ipa::commit(&vector(children))
+    }
+
+    /// The commitment of the node, then the opening of each child of `indices`.
+    fn open(children: &[(u8, Commitment)], indices: &[u8]) -> Vec<u8> {
+        let vector = vector(children);
+        let commitment = ipa::commit(&vector);
+        let mut opening = commitment.to_vec();
+        for index in indices {
+            opening.extend(ipa::open(&vector, *index, &commitment));
+        }
+        opening
+    }
+
+    fn opened(opening: &[u8], opened: &[(u8, Commitment)]) -> Result<Commitment> {
+        let sorted = opened.windows(2).all(|pair| pair[0].0 < pair[1].0);
+        if !sorted || opening.len() != EMPTY.len() + opened.len() * OPENING_LEN {
+            return Err(StorageError::InvalidProof);
+        }
+
+        let (commitment, openings) = opening.split_at(EMPTY.len());
+        let commitment: Commitment = commitment.try_into().expect("split at its length");
+        for ((index, child), opening) in opened.iter().zip(openings.chunks(OPENING_LEN)) {
+            ipa::verify(&commitment, *index, &ipa::scalar(child), opening)?;
+        }
+        Ok(commitment)
+    }
+}
+
+/// The vector committed to by a node with `children`.
+fn vector(children: &[(u8, Commitment)]) -> Vec<ark_ed_on_bls12_381_bandersnatch::Fr> {
+    let mut vector = vec![Zero::zero(); WIDTH];
+    for (index, commitment) in children {
+        vector[*index as usize] = ipa::scalar(commitment);
+    }
+    vector
+}
+
+/// Binary keccak tree along the bits of the keccak hash of the stems, most significant first. A
+/// node is the keccak hash of its left and right children. The values of a leaf are hashed
+/// together, and opened with the commitments of the other values, a few for the stem of an
+/// account.
+pub struct SparseMerkle;
+
+impl Scheme for SparseMerkle {
+    const KIND: SchemeKind = SchemeKind::SparseMerkle;
+
+    fn path(stem: &[u8]) -> Vec<u8> {
+        keccak256(&[stem])
+            .iter()
+            .flat_map(|byte| (0..8).rev().map(move |bit| (byte >> bit) & 1))
+            .collect()
+    }
+
+    fn leaf_commitment(stem: &[u8], values: &Commitment) -> Commitment {
+        keccak256(&[SMT_LEAF_DOMAIN, stem, values])
+    }
+
+    fn values_commitment(values: &[(u8, Commitment)]) -> Commitment {
+        if values.is_empty() {
+            return EMPTY;
+        }
+
+        keccak256(&[SMT_VALUES_DOMAIN, &values.encode()])
+    }
+
+    fn open_values(values: &[(u8, Commitment)], suffixes: &[u8]) -> Vec<u8> {
+        others(values, suffixes)
+    }
+
+    fn opened_values(opening: &[u8], opened: &[(u8, Commitment)]) -> Result<Commitment> {
+        Ok(Self::values_commitment(&merged(opening, opened)?))
+    }
+
+    fn internal_commitment(children: &[(u8, Commitment)]) -> Commitment {
+        if children.is_empty() {
+            return EMPTY;
+        }
+
+        let child = |index| {
+            children
+                .iter()
+                .find(|(child, _)| *child == index)
+                .map_or(EMPTY, |(_, commitment)| *commitment)
+        };
+        keccak256(&[SMT_INTERNAL_DOMAIN, &child(0), &child(1)])
+    }
+
+    fn open(children: &[(u8, Commitment)], indices: &[u8]) -> Vec<u8> {
+        others(children, indices)
+    }
+
+    fn opened(opening: &[u8], opened: &[(u8, Commitment)]) -> Result<Commitment> {
+        Ok(Self::internal_commitment(&merged(opening, opened)?))
+    }
+}
+
+/// The encoded `entries` off `indices`, opening the others by listing them.
+fn others(entries: &[(u8, Commitment)], indices: &[u8]) -> Vec<u8> {
+    entries
+        .iter()
+        .filter(|(index, _)| !indices.contains(index))
+        .copied()
+        .collect::<Vec<_>>()
+        .encode()
+}
+
+/// The non-empty entries of `opened` and of the encoded others of `opening`, sorted by index.
+fn merged(opening: &[u8], opened: &[(u8, Commitment)]) -> Result<Vec<(u8, Commitment)>> {
+    let mut entries = <Vec<(u8, Commitment)>>::decode(&mut &opening[..])
+        .map_err(|_| StorageError::InvalidProof)?;
+    let sorted =
+        |entries: &[(u8, Commitment)]| entries.windows(2).all(|pair| pair[0].0 < pair[1].0);
+    if !sorted(&entries) || !sorted(opened) {
+        return Err(StorageError::InvalidProof);
+    }
+
+    for (index, commitment) in opened {
+        let position = entries.partition_point(|(entry, _)| entry < index);
+        if entries.get(position).map(|(entry, _)| entry) == Some(index) {
+            return Err(StorageError::InvalidProof);
+        }
+        if *commitment != EMPTY {
+            entries.insert(position, (*index, *commitment));
+        }
+    }
+    Ok(entries)
+}
+
+/// The stem of `key`, `InvalidProof` if it is not a `KEY_LEN` byte key.
+fn stem(key: &[u8]) -> Result<&[u8]> {
+    match key.len() {
+        KEY_LEN => Ok(&key[..STEM_LEN]),
+        _ => Err(StorageError::InvalidProof),
+    }
+}
+
+/// The commitment of `slot`, `depth` nodes down the `path` of the stem of `key`: the leaf of
+/// the stem, opened at the suffix of `key` with `value` or absent, nothing, or the leaf of
+/// another stem sharing the path.
+fn slot_commitment<S: Scheme + ?Sized>(
+    key: &[u8],
+    value: &Option<Vec<u8>>,
+    slot: &Slot,
+    path: &[u8],
+    depth: usize,
+) -> Result<Commitment> {
+    if depth > path.len() || matches!(value, Some(value) if value.is_empty()) {
+        return Err(StorageError::InvalidProof);
+    }
+
+    let (stem, suffix) = (stem(key)?, key[STEM_LEN]);
+    match (value, slot) {
+        (value, Slot::Stem(opening)) => {
+            let opened = value.as_deref().map_or(EMPTY, value_commitment);
+            let values = S::opened_values(opening, &[(suffix, opened)])?;
+            Ok(S::leaf_commitment(stem, &values))
+        }
+        (None, Slot::Empty) => Ok(EMPTY),
+        (
+            None,
+            Slot::Other {
+                stem: other,
+                values,
+            },
+        ) if other.len() == STEM_LEN
+            && other != stem
+            && S::path(other)[..depth] == path[..depth] =>
+        {
+            Ok(S::leaf_commitment(other, values))
+        }
+        _ => Err(StorageError::InvalidProof),
+    }
+}
+
+/// Checks a proof of a tree of `scheme`, see `Scheme::verify_proof`.
+pub fn verify_proof(scheme: SchemeKind, proof: &Proof) -> Result<()> {
+    match scheme {
+        SchemeKind::Verkle => Verkle::verify_proof(proof),
+        SchemeKind::SparseMerkle => SparseMerkle::verify_proof(proof),
+    }
+}
+
+/// Checks a multiproof of a tree of `scheme`, see `Scheme::verify_multiproof`.
+pub fn verify_multiproof(scheme: SchemeKind, proof: &MultiProof) -> Result<()> {
+    match scheme {
+        SchemeKind::Verkle => Verkle::verify_multiproof(proof),
+        SchemeKind::SparseMerkle => SparseMerkle::verify_multiproof(proof),
+    }
+}
+
+/// Checks that `proof` proves `value` under `key` in the tree of `scheme` and `root`.
+pub fn verify<K: AsRef<[u8]>, V: Encode>(
+    scheme: SchemeKind,
+    root: &Commitment,
+    proof: &Proof,
+    key: &K,
+    value: &V,
+) -> Result<()> {
+    match scheme {
+        SchemeKind::Verkle => Verkle::verify(root, proof, key, value),
+        SchemeKind::SparseMerkle => SparseMerkle::verify(root, proof, key, value),
+    }
+}
+
+/// Checks that `proof` proves that nothing is stored under `key` in the tree of `scheme` and
+/// `root`.
+pub fn verify_absence<K: AsRef<[u8]>>(
+    scheme: SchemeKind,
+    root: &Commitment,
+    proof: &Proof,
+    key: &K,
+) -> Result<()> {
+    match scheme {
+        SchemeKind::Verkle => Verkle::verify_absence(root, proof, key),
+        SchemeKind::SparseMerkle => SparseMerkle::verify_absence(root, proof, key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(stem: u8, suffix: u8) -> Vec<u8> {
+        let mut key = vec![stem; KEY_LEN];
+        key[STEM_LEN] = suffix;
+        key
+    }
+
+    /// A tree of `S` holding only `key`, at depth 1.
+    fn single_leaf<S: Scheme>(key: &[u8], value: u32) -> Proof {
+        let (stem, suffix) = (&key[..STEM_LEN], key[STEM_LEN]);
+        let values = [(suffix, value_commitment(&value.encode()))];
+        let children = [(
+            S::path(stem)[0],
+            S::leaf_commitment(stem, &S::values_commitment(&values)),
+        )];
+        Proof {
+            root: S::internal_commitment(&children),
+            key: key.to_vec(),
+            value: Some(value.encode()),
+            openings: vec![S::open(&children, &[children[0].0])],
+            slot: Slot::Stem(S::open_values(&values, &[suffix])),
+        }
+    }
+
+    #[test]
+    fn success_verify() {
+        let k = key(5, 10);
+        let proof = single_leaf::<Verkle>(&k, 10);
+        assert_eq!(proof.openings[0].len(), 32 + OPENING_LEN);
+        verify_proof(SchemeKind::Verkle, &proof).expect("verify ok.");
+        verify(SchemeKind::Verkle, &proof.root, &proof, &k, &10u32).expect("verify ok.");
+
+        assert!(verify(SchemeKind::Verkle, &proof.root, &proof, &k, &11u32).is_err());
+        assert!(verify(SchemeKind::Verkle, &proof.root, &proof, &key(5, 11), &10u32).is_err());
+        assert!(verify(SchemeKind::Verkle, &EMPTY, &proof, &k, &10u32).is_err());
+        let index = Verkle::path(&k[..STEM_LEN])[0];
+        for forged in [
+            Proof {
+                value: Some(11u32.encode()),
+                ..proof.clone()
+            },
+            Proof {
+                root: EMPTY,
+                ..proof.clone()
+            },
+            Proof {
+                value: Some(vec![]),
+                ..proof.clone()
+            },
+            // The value at another suffix of the stem.
+            Proof {
+                key: key(5, 11),
+                ..proof.clone()
+            },
+            Proof {
+                key: k[..STEM_LEN].to_vec(),
+                ..proof.clone()
+            },
+            // An opening of another child does not prove the slot of the stem.
+            Proof {
+                openings: vec![Verkle::open(&[(index, [1; 32])], &[index ^ 1])],
+                ..proof.clone()
+            },
+            Proof {
+                openings: vec![proof.openings[0][..32].to_vec()],
+                ..proof.clone()
+            },
+        ] {
+            assert!(verify_proof(SchemeKind::Verkle, &forged).is_err());
+        }
+
+        // The slot of the stem cannot be listed among the other children.
+        let smt = single_leaf::<SparseMerkle>(&k, 10);
+        let forged = Proof {
+            openings: vec![vec![(SparseMerkle::path(&k[..STEM_LEN])[0], [1; 32])].encode()],
+            ..smt
+        };
+        assert!(verify_proof(SchemeKind::SparseMerkle, &forged).is_err());
+    }
+
+    #[test]
+    fn success_verify_by_scheme() {
+        let k = key(5, 10);
+        let smt = single_leaf::<SparseMerkle>(&k, 10);
+        verify(SchemeKind::SparseMerkle, &smt.root, &smt, &k, &10u32).expect("verify ok.");
+        assert!(verify(SchemeKind::Verkle, &smt.root, &smt, &k, &10u32).is_err());
+
+        let verkle = single_leaf::<Verkle>(&k, 10);
+        verify_proof(SchemeKind::Verkle, &verkle).expect("verify ok.");
+        assert!(verify_proof(SchemeKind::SparseMerkle, &verkle).is_err());
+    }
+
+    #[test]
+    fn success_verify_absence() {
+        let k = key(5, 10);
+        let empty = Proof {
+            root: EMPTY,
+            key: k.clone(),
+            value: None,
+            openings: vec![Verkle::open(&[], &[Verkle::path(&k[..STEM_LEN])[0]])],
+            slot: Slot::Empty,
+        };
+        verify_absence(SchemeKind::Verkle, &EMPTY, &empty, &k).expect("verify ok.");
+
+        let proof = single_leaf::<Verkle>(&k, 10);
+        assert!(verify_absence(SchemeKind::Verkle, &proof.root, &empty, &k).is_err());
+        assert!(verify_absence(SchemeKind::Verkle, &proof.root, &proof, &k).is_err());
+        // Another suffix of the stem is absent from its leaf.
+        let values = [(10, value_commitment(&10u32.encode()))];
+        let absent = Proof {
+            key: key(5, 11),
+            value: None,
+            slot: Slot::Stem(Verkle::open_values(&values, &[11])),
+            ..proof.clone()
+        };
+        verify_absence(SchemeKind::Verkle, &proof.root, &absent, &key(5, 11)).expect("verify ok.");
+        let forged = Proof {
+            key: k.clone(),
+            slot: Slot::Stem(Verkle::open_values(&values, &[10])),
+            ..absent.clone()
+        };
+        assert!(verify_absence(SchemeKind::Verkle, &proof.root, &forged, &k).is_err());
+
+        // The leaf of a stem on another path proves nothing about `other`.
+        let other = (0..=u8::MAX)
+            .map(|b| key(b, 0))
+            .find(|other| Verkle::path(&other[..STEM_LEN])[0] != index_of(&k))
+            .unwrap();
+        let forged = Proof {
+            key: other.clone(),
+            value: None,
+            slot: Slot::Other {
+                stem: k[..STEM_LEN].to_vec(),
+                values: Verkle::values_commitment(&values),
+            },
+            ..proof.clone()
+        };
+        assert!(verify_absence(SchemeKind::Verkle, &proof.root, &forged, &other).is_err());
+    }
+
+    fn index_of(key: &[u8]) -> u8 {
+        Verkle::path(&key[..STEM_LEN])[0]
+    }
+
+    #[test]
+    fn success_verkle_openings() {
+        let children: Vec<_> = (0..=u8::MAX)
+            .step_by(3)
+            .map(|index| (index, keccak256(&[&[index]])))
+            .collect();
+        let root = Verkle::internal_commitment(&children);
+        assert_eq!(Verkle::internal_commitment(&[]), EMPTY);
+
+        // One opening proves several children, empty ones too.
+        let opened = [children[0], (1, EMPTY), children[5]];
+        let indices: Vec<_> = opened.iter().map(|(index, _)| *index).collect();
+        let opening = Verkle::open(&children, &indices);
+        assert_eq!(opening.len(), 32 + 3 * OPENING_LEN);
+        assert_eq!(Verkle::opened(&opening, &opened), Ok(root));
+
+        let mut forged = opened;
+        forged[1].1 = [1; 32];
+        assert!(Verkle::opened(&opening, &forged).is_err());
+        forged = opened;
+        forged.swap(0, 2);
+        assert!(Verkle::opened(&opening, &forged).is_err());
+        assert!(Verkle::opened(&opening, &opened[..2]).is_err());
+        // Another commitment in front of the openings does not verify.
+        let mut forged = opening.clone();
+        forged[..32].copy_from_slice(&Verkle::internal_commitment(&children[1..]));
+        assert!(Verkle::opened(&forged, &opened).is_err());
+    }
+
+    #[test]
+    fn success_sparse_merkle_commitments() {
+        let path = SparseMerkle::path(&[5, 10]);
+        assert_eq!(path.len(), 256);
+        assert!(path.iter().all(|bit| *bit <= 1));
+        let first = Verkle::path(&[5, 10])[0];
+        assert_eq!((path[0], path[7]), (first >> 7, first & 1));
+
+        // A missing child commits as empty.
+        let leaf = SparseMerkle::leaf_commitment(&[1], &[2; 32]);
+        assert_eq!(
+            SparseMerkle::internal_commitment(&[(1, leaf)]),
+            SparseMerkle::internal_commitment(&[(0, EMPTY), (1, leaf)])
+        );
+        assert_ne!(
+            SparseMerkle::internal_commitment(&[(0, leaf)]),
+            SparseMerkle::internal_commitment(&[(1, leaf)])
+        );
+        assert_eq!(SparseMerkle::internal_commitment(&[]), EMPTY);
+
+        let values = [(0, [1; 32]), (1, [2; 32])];
+        let opening = SparseMerkle::open_values(&values, &[1]);
+        assert_eq!(
+            SparseMerkle::opened_values(&opening, &[(1, [2; 32])]),
+            Ok(SparseMerkle::values_commitment(&values))
+        );
+        assert!(SparseMerkle::opened_values(&opening, &[(0, [2; 32])]).is_err());
+    }
+}
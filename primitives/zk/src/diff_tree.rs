@@ -0,0 +1,94 @@
+//! Keccak Merkle tree of the state diffs of a block. The leaves are the state diff hashes in
+//! the order of the block, nodes hash their children in order, and the last node of an odd
+//! level is carried up as is.
+use ethereum_types::H256;
+
+use crate::keccak256;
+
+fn next_level(level: &[H256]) -> Vec<H256> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [a, b] => keccak256(&[a.as_bytes(), b.as_bytes()]),
+            [a] => *a,
+            _ => unreachable!("chunks(2) yields one or two nodes"),
+        })
+        .collect()
+}
+
+/// Root of `leaves`. The root of no leaves is zero.
+pub fn root(leaves: &[H256]) -> H256 {
+    if leaves.is_empty() {
+        return H256::zero();
+    }
+
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+
+    level[0]
+}
+
+/// Siblings from the leaf at `index` up to the root. A carried up node has no sibling.
+pub fn proof(leaves: &[H256], index: usize) -> Vec<Option<H256>> {
+    let mut siblings = Vec::new();
+    let mut level = leaves.to_vec();
+    let mut index = index;
+    while level.len() > 1 {
+        siblings.push(level.get(index ^ 1).copied());
+        level = next_level(&level);
+        index /= 2;
+    }
+
+    siblings
+}
+
+/// Whether `leaf` is at `index` in the tree of `root`.
+pub fn verify(root: &H256, leaf: &H256, index: usize, proof: &[Option<H256>]) -> bool {
+    let mut index = index;
+    let mut node = *leaf;
+    for sibling in proof {
+        node = match sibling {
+            Some(sibling) if index & 1 == 0 => keccak256(&[node.as_bytes(), sibling.as_bytes()]),
+            Some(sibling) => keccak256(&[sibling.as_bytes(), node.as_bytes()]),
+            None if index & 1 == 0 => node,
+            None => return false,
+        };
+        index /= 2;
+    }
+
+    index == 0 && node == *root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(n: u64) -> Vec<H256> {
+        (0..n).map(H256::from_low_u64_be).collect()
+    }
+
+    #[test]
+    fn success_proof_of_every_leaf() {
+        for n in 1..=9 {
+            let leaves = leaves(n);
+            let root = root(&leaves);
+            for (i, leaf) in leaves.iter().enumerate() {
+                assert!(verify(&root, leaf, i, &proof(&leaves, i)), "{} of {}", i, n);
+            }
+        }
+    }
+
+    #[test]
+    fn fail_verify_other_position() {
+        let leaves = leaves(5);
+        let root = root(&leaves);
+
+        assert!(!verify(&root, &leaves[2], 3, &proof(&leaves, 2)));
+        assert!(!verify(&root, &leaves[4], 5, &proof(&leaves, 4)));
+        assert!(!verify(&root, &H256::random(), 2, &proof(&leaves, 2)));
+        assert_eq!(super::root(&[]), H256::zero());
+        assert_eq!(super::root(&leaves[..1]), leaves[0]);
+    }
+}
@@ -0,0 +1,22 @@
+use ethereum_types::H256;
+
+/// Proof system Result type.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Proof system errors.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// If the proof does not prove its public inputs.
+    #[error("Proof of StateDiff({}) is invalid", .state_diff)]
+    InvalidProof {
+        /// Hash of the proven state diff
+        state_diff: H256,
+    },
+
+    /// If the aggregated proof does not prove its state diff root.
+    #[error("Aggregated proof of diff root {} is invalid", .diff_root)]
+    InvalidAggregatedProof {
+        /// Root of the proven state diffs
+        diff_root: H256,
+    },
+}
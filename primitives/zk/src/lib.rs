@@ -1,8 +1,193 @@
+//! Proofs of the state transitions of the users, who hold their own state.
+//!
+//! A user proves that a `StateDiff`, the move of its state root from `old_root` to `new_root`,
+//! is a valid transition of its private state. The node only checks the proof through a
+//! `ProofSystem` and keeps the roots.
+//!
+//! The state diffs of a block are then aggregated into one proof of the root of their
+//! `diff_tree`, so that L1 verifies a single proof per block.
+use codec::{Decode, Encode};
+use ethereum_types::{H160, H256};
+use tiny_keccak::{Hasher, Keccak};
+
+pub use error::{Error, Result};
+
+pub mod diff_tree;
+mod error;
+
+/// Public inputs of a user state transition.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct StateDiff {
+    pub user: H160,
+    /// Root of the user state before the transition, zero for a new user.
+    pub old_root: H256,
+    pub new_root: H256,
+    /// Number of the previous transitions of the user.
+    pub nonce: u64,
+}
+
+impl StateDiff {
+    pub fn hash(&self) -> H256 {
+        keccak256(&[&self.encode()])
+    }
+}
+
+/// A `StateDiff` and the proof of its validity.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct UserStateProof {
+    pub diff: StateDiff,
+    pub proof: Vec<u8>,
+}
+
+/// Proof that every state diff under `diff_root`, the root of the `diff_tree` of a block, is
+/// proven.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct AggregatedProof {
+    pub diff_root: H256,
+    pub proof: Vec<u8>,
+}
+
+/// Backend checking the proofs.
+pub trait ProofSystem {
+    fn verify_user_state(&self, proof: &UserStateProof) -> Result<()>;
+
+    /// Verifies `proofs`, the proofs of the state diffs of a block in order, and proves them
+    /// all at once.
+    fn aggregate(&self, proofs: &[UserStateProof]) -> Result<AggregatedProof>;
+
+    fn verify_aggregated(&self, proof: &AggregatedProof) -> Result<()>;
+}
+
+/// Proof system for development and tests: the proof of a `StateDiff` is a hash of it.
+///
+/// Anyone can build such a proof, so it only checks that the proof was made for the diff and
+/// must not be used on a network holding value.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DevProofSystem;
+
+impl DevProofSystem {
+    const DOMAIN: &'static [u8] = b"intmax-dev-user-state";
+    const AGGREGATED_DOMAIN: &'static [u8] = b"intmax-dev-aggregated";
+
+    pub fn prove(&self, diff: &StateDiff) -> UserStateProof {
+        UserStateProof {
+            diff: diff.clone(),
+            proof: keccak256(&[Self::DOMAIN, &diff.encode()])
+                .as_bytes()
+                .to_vec(),
+        }
+    }
+
+    fn prove_aggregated(&self, diff_root: H256) -> AggregatedProof {
+        AggregatedProof {
+            diff_root,
+            proof: keccak256(&[Self::AGGREGATED_DOMAIN, diff_root.as_bytes()])
+                .as_bytes()
+                .to_vec(),
+        }
+    }
+}
+
+impl ProofSystem for DevProofSystem {
+    fn verify_user_state(&self, proof: &UserStateProof) -> Result<()> {
+        if self.prove(&proof.diff).proof != proof.proof {
+            return Err(Error::InvalidProof {
+                state_diff: proof.diff.hash(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn aggregate(&self, proofs: &[UserStateProof]) -> Result<AggregatedProof> {
+        for proof in proofs {
+            self.verify_user_state(proof)?;
+        }
+        let hashes: Vec<H256> = proofs.iter().map(|proof| proof.diff.hash()).collect();
+
+        Ok(self.prove_aggregated(diff_tree::root(&hashes)))
+    }
+
+    fn verify_aggregated(&self, proof: &AggregatedProof) -> Result<()> {
+        if self.prove_aggregated(proof.diff_root).proof != proof.proof {
+            return Err(Error::InvalidAggregatedProof {
+                diff_root: proof.diff_root,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn keccak256(data: &[&[u8]]) -> H256 {
+    let mut hasher = Keccak::v256();
+    for d in data {
+        hasher.update(d);
+    }
+    let mut out = [0u8; 32];
+    hasher.finalize(&mut out);
+
+    H256(out)
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    #[test]
+    fn success_verify_user_state() {
+        let diff = StateDiff {
+            user: H160::random(),
+            old_root: H256::zero(),
+            new_root: H256::random(),
+            nonce: 0,
+        };
+        let mut proof = DevProofSystem.prove(&diff);
+        DevProofSystem
+            .verify_user_state(&proof)
+            .expect("verify ok.");
+
+        proof.diff.nonce = 1;
+        assert!(matches!(
+            DevProofSystem.verify_user_state(&proof),
+            Err(Error::InvalidProof { .. })
+        ));
+    }
+
     #[test]
-    fn it_works() {
-        let result = 2 + 2;
-        assert_eq!(result, 4);
+    fn success_aggregate() {
+        let user = H160::random();
+        let diffs: Vec<StateDiff> = (0..3)
+            .map(|nonce| StateDiff {
+                user,
+                old_root: H256::from_low_u64_be(nonce),
+                new_root: H256::from_low_u64_be(nonce + 1),
+                nonce,
+            })
+            .collect();
+        let mut proofs: Vec<UserStateProof> = diffs
+            .iter()
+            .map(|diff| DevProofSystem.prove(diff))
+            .collect();
+
+        let aggregated = DevProofSystem.aggregate(&proofs).expect("aggregate ok.");
+        let hashes: Vec<H256> = diffs.iter().map(StateDiff::hash).collect();
+        assert_eq!(aggregated.diff_root, diff_tree::root(&hashes));
+        DevProofSystem
+            .verify_aggregated(&aggregated)
+            .expect("verify ok.");
+        assert!(matches!(
+            DevProofSystem.verify_aggregated(&AggregatedProof {
+                diff_root: H256::random(),
+                ..aggregated
+            }),
+            Err(Error::InvalidAggregatedProof { .. })
+        ));
+
+        proofs[1].proof[0] ^= 1;
+        assert!(matches!(
+            DevProofSystem.aggregate(&proofs),
+            Err(Error::InvalidProof { .. })
+        ));
     }
 }
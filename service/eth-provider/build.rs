@@ -0,0 +1,35 @@
+// grcov: ignore-start
+#[path = "src/codegen.rs"]
+mod codegen;
+
+use std::env;
+use std::path::Path;
+
+fn main() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR is set by cargo");
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR is set by cargo");
+
+    let artifacts_dir = Path::new(&manifest_dir).join(codegen::ARTIFACTS_DIR);
+    println!("cargo:rerun-if-changed={}", artifacts_dir.display());
+    println!("cargo:rerun-if-changed=src/codegen.rs");
+
+    // Every directory under `src/artifacts` is a network and must hold a `.chainId` file with
+    // its chain id. The networks are loaded first so that a missing one is reported by name.
+    let networks = codegen::load_networks(&artifacts_dir)
+        .unwrap_or_else(|e| panic!("failed to load networks: {}", e));
+
+    let bindings_dir = Path::new(&out_dir).join("contracts");
+    let mut modules = codegen::generate_bindings(&artifacts_dir, &bindings_dir)
+        .unwrap_or_else(|e| panic!("failed to generate contract bindings: {}", e));
+    std::fs::write(
+        bindings_dir.join(format!("{}.rs", codegen::ADDRESSES_MODULE)),
+        codegen::render_addresses(&networks),
+    )
+    .expect("failed to write contract addresses");
+    modules.push(codegen::ADDRESSES_MODULE.to_string());
+
+    let registry = codegen::render_registry(&modules, &bindings_dir);
+    std::fs::write(Path::new(&out_dir).join("contracts.rs"), registry)
+        .expect("failed to write contracts.rs");
+}
+// grcov: ignore-end
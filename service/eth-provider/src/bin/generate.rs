@@ -1,56 +1,43 @@
 // grcov: ignore-start
-use convert_case::{Case, Casing};
+#[path = "../codegen.rs"]
+#[allow(dead_code)]
+mod codegen;
 
-use ethcontract_generate::loaders::HardHatLoader;
-use ethcontract_generate::ContractBuilder;
-use std::env;
 use std::error::Error;
 use std::path::Path;
 
-const ARTIFACTS_PATH: &str = "service/eth-provider/src/artifacts";
+const CRATE_PATH: &str = "service/eth-provider";
 const CONTRACTS_DEST_PATH: &str = "service/eth-provider/src/contracts";
 
+/// Writes the same bindings and address constants `build.rs` generates for every network into
+/// `src/contracts/`, so they can be read and diffed. The crate always uses the build-time ones.
 fn main() -> Result<(), Box<dyn Error>> {
-    let args: Vec<String> = env::args().collect();
-    let network = &args[1];
+    let root = project_root::get_project_root()?;
 
-    let root = project_root::get_project_root().expect("failed to get project");
-
-    let artifacts_dir = Path::new(root.as_path()).join(ARTIFACTS_PATH);
+    let artifacts_dir = Path::new(root.as_path())
+        .join(CRATE_PATH)
+        .join(codegen::ARTIFACTS_DIR);
     let contracts_dir = Path::new(root.as_path()).join(CONTRACTS_DEST_PATH);
 
-    if !contracts_dir.is_dir() {
-        std::fs::create_dir(&contracts_dir)
-            .unwrap_or_else(|_| panic!("failed to create_dir {:?}", &contracts_dir));
-    }
-
-    println!("loading artifacts ...");
-
-    let artifacts = HardHatLoader::new()
-        .allow_network_by_name(network)
-        .load_from_directory(&artifacts_dir)
-        .unwrap_or_else(|_| panic!("failed to load {:?}", &artifacts_dir));
-
-    if artifacts.is_empty() {
-        panic!(
-            "{} has no artifacts. Please check '{:?}' directory and eth-provider/README.md",
-            network, &artifacts_dir
+    let networks = codegen::load_networks(&artifacts_dir)?;
+    for network in networks.iter() {
+        println!(
+            "network {} (chain id {}): {} contracts",
+            network.name,
+            network.chain_id,
+            network.contracts.len()
         );
     }
 
-    for contract in artifacts.iter() {
-        let file_name = contract.name.to_case(Case::Snake);
-        println!("generate {}.rs ...", file_name);
-
-        let dest = contracts_dir.join(format!("{}.rs", file_name));
-
-        let builder = ContractBuilder::new();
-        builder
-            .generate(contract)
-            .expect("failed to generate")
-            .write_to_file(dest)
-            .expect("failed to write rust file");
+    println!("loading artifacts ...");
+    for module in codegen::generate_bindings(&artifacts_dir, &contracts_dir)? {
+        println!("generate {}.rs ...", module);
     }
+    println!("generate {}.rs ...", codegen::ADDRESSES_MODULE);
+    std::fs::write(
+        contracts_dir.join(format!("{}.rs", codegen::ADDRESSES_MODULE)),
+        codegen::render_addresses(&networks),
+    )?;
 
     Ok(())
 }
@@ -0,0 +1,227 @@
+// grcov: ignore-start
+// Shared by `build.rs` and `bin/generate.rs` through `#[path]`, so this module must only
+// depend on build-dependencies.
+use convert_case::{Case, Casing};
+use ethcontract_generate::loaders::HardHatLoader;
+use ethcontract_generate::ContractBuilder;
+use std::error::Error;
+use std::fmt::Write;
+use std::path::Path;
+
+/// HardHat deployments, relative to the eth-provider crate root.
+/// Every sub directory is a network and holds a `.chainId` file next to its artifacts.
+pub const ARTIFACTS_DIR: &str = "src/artifacts";
+
+/// Module name of the generated per-network address constants.
+pub const ADDRESSES_MODULE: &str = "addresses";
+
+/// The deployments of a single network directory.
+#[derive(Debug)]
+pub struct NetworkArtifacts {
+    pub name: String,
+    pub chain_id: u64,
+    /// `(contract name, address)` sorted by contract name.
+    pub contracts: Vec<(String, [u8; 20])>,
+}
+
+/// Generates typed bindings for every contract of every network under `artifacts_dir`
+/// into `dest_dir`, and returns the module names of the generated files.
+///
+/// A contract deployed on several networks gets a single binding which knows all of its
+/// deployed addresses, so `Contract::deployed` picks the right one from the chain id.
+pub fn generate_bindings(
+    artifacts_dir: &Path,
+    dest_dir: &Path,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    if !dest_dir.is_dir() {
+        std::fs::create_dir_all(dest_dir)
+            .map_err(|e| format!("failed to create_dir {:?}: {}", dest_dir, e))?;
+    }
+
+    let artifacts = HardHatLoader::new()
+        .load_from_directory(artifacts_dir)
+        .map_err(|e| format!("failed to load {:?}: {}", artifacts_dir, e))?;
+
+    if artifacts.is_empty() {
+        return Err(format!(
+            "{:?} has no artifacts. Please check eth-provider/README.md",
+            artifacts_dir
+        )
+        .into());
+    }
+
+    let mut modules = Vec::new();
+    for contract in artifacts.iter() {
+        let module = contract.name.to_case(Case::Snake);
+        let dest = dest_dir.join(format!("{}.rs", module));
+
+        ContractBuilder::new()
+            .generate(contract)
+            .map_err(|e| format!("failed to generate {}: {}", contract.name, e))?
+            .write_to_file(&dest)
+            .map_err(|e| format!("failed to write {:?}: {}", dest, e))?;
+
+        modules.push(module);
+    }
+    modules.sort();
+
+    Ok(modules)
+}
+
+/// Reads the `.chainId` and the deployed address of every artifact of each network directory.
+/// Fails naming the directory if it has no `.chainId`.
+pub fn load_networks(artifacts_dir: &Path) -> Result<Vec<NetworkArtifacts>, Box<dyn Error>> {
+    let mut networks = Vec::new();
+    for entry in std::fs::read_dir(artifacts_dir)
+        .map_err(|e| format!("failed to read {:?}: {}", artifacts_dir, e))?
+    {
+        let network_dir = entry?.path();
+        if !network_dir.is_dir() {
+            continue;
+        }
+        let name = network_dir
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| format!("invalid network directory {:?}", network_dir))?
+            .to_string();
+
+        let chain_id_file = network_dir.join(".chainId");
+        if !chain_id_file.is_file() {
+            return Err(format!(
+                "network directory {:?} has no .chainId file. Every directory under {} is a \
+                 network and holds its chain id in .chainId, please check eth-provider/README.md",
+                network_dir, ARTIFACTS_DIR
+            )
+            .into());
+        }
+        let chain_id = std::fs::read_to_string(&chain_id_file)
+            .map_err(|e| format!("failed to read {:?}: {}", chain_id_file, e))?
+            .trim()
+            .parse::<u64>()
+            .map_err(|e| format!("invalid chain id in {:?}: {}", chain_id_file, e))?;
+
+        let mut contracts = Vec::new();
+        for entry in std::fs::read_dir(&network_dir)? {
+            let artifact = entry?.path();
+            if artifact.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let contract = artifact
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .ok_or_else(|| format!("invalid artifact name {:?}", artifact))?
+                .to_string();
+
+            let json: serde_json::Value =
+                serde_json::from_str(&std::fs::read_to_string(&artifact)?)
+                    .map_err(|e| format!("failed to parse {:?}: {}", artifact, e))?;
+            let address = json["address"]
+                .as_str()
+                .ok_or_else(|| format!("{:?} has no address", artifact))?;
+            contracts.push((
+                contract,
+                parse_address(address).map_err(|e| format!("{:?}: {}", artifact, e))?,
+            ));
+        }
+        contracts.sort();
+
+        networks.push(NetworkArtifacts {
+            name,
+            chain_id,
+            contracts,
+        });
+    }
+    networks.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut chain_ids = std::collections::HashMap::new();
+    for network in networks.iter() {
+        if let Some(other) = chain_ids.insert(network.chain_id, &network.name) {
+            return Err(format!(
+                "{} and {} have the same chain id {}",
+                other, network.name, network.chain_id
+            )
+            .into());
+        }
+    }
+
+    Ok(networks)
+}
+
+fn parse_address(address: &str) -> Result<[u8; 20], String> {
+    let hex = address.trim_start_matches("0x");
+    if hex.len() != 40 {
+        return Err(format!("invalid address {}", address));
+    }
+
+    let mut bytes = [0u8; 20];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .map_err(|_| format!("invalid address {}", address))?;
+    }
+
+    Ok(bytes)
+}
+
+/// Renders the address constants of every network, plus the `NETWORKS` table
+/// used to look a network up by name at runtime.
+pub fn render_addresses(networks: &[NetworkArtifacts]) -> String {
+    let mut out = String::from("// @generated by eth-provider/build.rs. Do not edit.\n");
+    out.push_str("use super::Network;\n");
+
+    for network in networks {
+        let module = network.name.to_case(Case::Snake);
+        let _ = write!(
+            out,
+            "\n/// Contract addresses on `{}` (chain id {}).\npub mod {} {{\n    use ethcontract::{{Address, H160}};\n\n    pub const CHAIN_ID: u64 = {};\n",
+            network.name, network.chain_id, module, network.chain_id
+        );
+        for (contract, address) in &network.contracts {
+            let _ = writeln!(
+                out,
+                "    pub const {}: Address = H160({:?});",
+                contract.to_case(Case::UpperSnake),
+                address
+            );
+        }
+        out.push_str("}\n");
+    }
+
+    out.push_str("\npub const NETWORKS: &[Network] = &[\n");
+    for network in networks {
+        let module = network.name.to_case(Case::Snake);
+        let _ = writeln!(
+            out,
+            "    Network {{\n        name: {:?},\n        chain_id: {}::CHAIN_ID,\n        contracts: &[",
+            network.name, module
+        );
+        for (contract, _) in &network.contracts {
+            let _ = writeln!(
+                out,
+                "            ({:?}, {}::{}),",
+                contract,
+                module,
+                contract.to_case(Case::UpperSnake)
+            );
+        }
+        out.push_str("        ],\n    },\n");
+    }
+    out.push_str("];\n");
+
+    out
+}
+
+/// Renders the module registry included by `contracts.rs`: one `pub mod` per generated binding.
+pub fn render_registry(modules: &[String], bindings_dir: &Path) -> String {
+    let mut registry = String::from("// @generated by eth-provider/build.rs. Do not edit.\n");
+    for module in modules {
+        let path = bindings_dir.join(format!("{}.rs", module));
+        registry.push_str(&format!(
+            "\n#[path = {:?}]\npub mod {};\n",
+            path.to_string_lossy(),
+            module
+        ));
+    }
+
+    registry
+}
+// grcov: ignore-end
@@ -1 +1,30 @@
-// please add `pub mod xxxxx;`
+use ethcontract::Address;
+
+// Bindings for every artifact under `src/artifacts` and the per-network `addresses` module are
+// generated and registered by `build.rs`.
+include!(concat!(env!("OUT_DIR"), "/contracts.rs"));
+
+/// A network under `src/artifacts` and the addresses of the contracts deployed on it.
+#[derive(Debug, PartialEq)]
+pub struct Network {
+    pub name: &'static str,
+    pub chain_id: u64,
+    pub contracts: &'static [(&'static str, Address)],
+}
+
+impl Network {
+    /// Looks a network up by its directory name, e.g. `localhost`.
+    pub fn find(name: &str) -> Option<&'static Network> {
+        addresses::NETWORKS
+            .iter()
+            .find(|network| network.name == name)
+    }
+
+    /// Address of `contract` (the artifact name, e.g. `Rollup`) on this network.
+    pub fn address(&self, contract: &str) -> Option<Address> {
+        self.contracts
+            .iter()
+            .find(|(name, _)| *name == contract)
+            .map(|(_, address)| *address)
+    }
+}
@@ -0,0 +1,585 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use db::{DBKey, Database};
+use ethcontract::futures::StreamExt;
+use ethcontract::web3::signing::keccak256;
+use ethcontract::web3::transports::WebSocket;
+use ethcontract::web3::types::{BlockNumber, FilterBuilder, Log, H160, H256, U256};
+use ethcontract::web3::{DuplexTransport, Transport, Web3};
+use ethcontract::Http;
+use executor::{Deposit, Error as ExecutorError, ExecutorTrait, Transaction};
+use intmax_config::{DepositConfig, EthConfig};
+use tracing::{debug, error, info, warn};
+
+use crate::contracts::Network;
+use crate::error::{Error, Result};
+
+const CURSOR_KEY: &[u8] = b"cursor";
+/// Deposits the executor rejected, by id.
+const REJECTED_PREFIX: &[u8] = b"rejected";
+/// Largest block range fetched by a single `eth_getLogs`.
+pub(crate) const MAX_BLOCK_RANGE: u64 = 1_000;
+
+const DEPOSITED_EVENT: &str = "Deposited(address,address,address,uint256,uint256)";
+const TRANSFER_EVENT: &str = "Transfer(address,address,uint256)";
+
+/// Turns confirmed L1 deposits into `Transaction::Deposit`s for the executor.
+///
+/// Deposits are `Deposited` events of the rollup contract and ERC-20 `Transfer`s of the
+/// configured tokens straight to the rollup contract. The last L1 block whose deposits have
+/// been executed is saved in `db` (the `db::columns::DEPOSIT_WATCHER` column) so that a restart
+/// resumes from there. A crash between executing and saving replays a few deposits, which the
+/// executor rejects as `DuplicateDeposit`. A deposit the executor rejects for any other reason
+/// than a storage failure, e.g. a balance overflow, is recorded as rejected and skipped, so that
+/// it does not hold back the deposits after it.
+pub struct DepositWatcher<T: Transport, D: Database> {
+    web3: Web3<T>,
+    rollup: H160,
+    tokens: Vec<H160>,
+    from_block: u64,
+    confirmations: u64,
+    db: D,
+    executor: Arc<dyn ExecutorTrait + Send + Sync>,
+}
+
+impl<T: Transport, D: Database> DepositWatcher<T, D> {
+    pub fn new(
+        transport: T,
+        network: &Network,
+        config: &DepositConfig,
+        db: D,
+        executor: Arc<dyn ExecutorTrait + Send + Sync>,
+    ) -> Result<Self> {
+        let address = |contract: &str| {
+            network
+                .address(contract)
+                .ok_or_else(|| Error::UnknownContract {
+                    network: network.name.to_string(),
+                    contract: contract.to_string(),
+                })
+        };
+
+        Ok(DepositWatcher {
+            web3: Web3::new(transport),
+            rollup: address("Rollup")?,
+            tokens: config
+                .tokens
+                .iter()
+                .map(|token| address(token))
+                .collect::<Result<_>>()?,
+            from_block: config.from_block,
+            confirmations: config.confirmations,
+            db,
+            executor,
+        })
+    }
+
+    /// The last L1 block whose deposits have been executed.
+    pub fn cursor(&self) -> Option<u64> {
+        self.db.get(&DBKey::from_slice(CURSOR_KEY))
+    }
+
+    /// The deposit `id` if the executor rejected it.
+    pub fn rejected_deposit(&self, id: &H256) -> Option<Deposit> {
+        self.db.get(&rejected_key(id))
+    }
+
+    fn reject(&self, deposit: &Deposit) -> Result<()> {
+        let mut tx = self.db.make_tx();
+        tx.put(&rejected_key(&deposit.id), deposit);
+        self.db.commit(tx)?;
+
+        Ok(())
+    }
+
+    fn save_cursor(&self, block: u64) -> Result<()> {
+        let mut tx = self.db.make_tx();
+        tx.put(&CURSOR_KEY, &block);
+        self.db.commit(tx)?;
+
+        Ok(())
+    }
+
+    /// Executes the deposits of every newly confirmed L1 block and returns how many were applied.
+    pub async fn poll_once(&self) -> Result<usize> {
+        let head = self.web3.eth().block_number().await?.as_u64();
+        let confirmed = match head.checked_sub(self.confirmations) {
+            Some(confirmed) => confirmed,
+            None => return Ok(0),
+        };
+
+        let mut applied = 0;
+        let mut from = self.cursor().map_or(self.from_block, |cursor| cursor + 1);
+        while from <= confirmed {
+            let to = confirmed.min(from + MAX_BLOCK_RANGE - 1);
+            for deposit in self.fetch_deposits(from, to).await? {
+                match self
+                    .executor
+                    .execute(&Transaction::Deposit(deposit.clone()))
+                {
+                    Ok(()) => applied += 1,
+                    Err(ExecutorError::DuplicateDeposit { id }) => {
+                        debug!("skip deposit {:?}: already applied", id)
+                    }
+                    // Retried on the next poll.
+                    Err(e @ (ExecutorError::Storage(_) | ExecutorError::Db(_))) => {
+                        return Err(e.into())
+                    }
+                    Err(e) => {
+                        error!("reject deposit {:?}: {}", deposit.id, e);
+                        self.reject(&deposit)?;
+                    }
+                }
+            }
+            self.save_cursor(to)?;
+            from = to + 1;
+        }
+
+        Ok(applied)
+    }
+
+    async fn fetch_deposits(&self, from: u64, to: u64) -> Result<Vec<Deposit>> {
+        let range = FilterBuilder::default()
+            .from_block(BlockNumber::Number(from.into()))
+            .to_block(BlockNumber::Number(to.into()));
+
+        let deposited = self
+            .web3
+            .eth()
+            .logs(
+                range
+                    .clone()
+                    .address(vec![self.rollup])
+                    .topics(Some(vec![event_topic(DEPOSITED_EVENT)]), None, None, None)
+                    .build(),
+            )
+            .await?;
+
+        let transfers = if self.tokens.is_empty() {
+            vec![]
+        } else {
+            self.web3
+                .eth()
+                .logs(
+                    range
+                        .address(self.tokens.clone())
+                        .topics(
+                            Some(vec![event_topic(TRANSFER_EVENT)]),
+                            None,
+                            Some(vec![H256::from(self.rollup)]),
+                            None,
+                        )
+                        .build(),
+                )
+                .await?
+        };
+
+        Ok(parse_deposits(&deposited, &transfers))
+    }
+
+    async fn poll_and_log(&self) {
+        match self.poll_once().await {
+            Ok(0) => (),
+            Ok(applied) => info!("applied {} deposits", applied),
+            Err(e) => warn!("failed to poll deposits: {}", e),
+        }
+    }
+
+    /// Polls the L1 node every `interval`. Used with http(s) endpoints.
+    pub async fn run_polling(self, interval: Duration) {
+        loop {
+            self.poll_and_log().await;
+            tokio::time::sleep(interval).await;
+        }
+    }
+}
+
+impl<T: DuplexTransport, D: Database> DepositWatcher<T, D> {
+    /// Polls the L1 node on every new head. Used with ws(s) endpoints.
+    pub async fn run_subscription(self) -> Result<()> {
+        self.poll_and_log().await;
+
+        let mut heads = self.web3.eth_subscribe().subscribe_new_heads().await?;
+        while let Some(head) = heads.next().await {
+            head?;
+            self.poll_and_log().await;
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs the deposit watcher against the L1 node of `eth_config`: subscribes to new heads over
+/// ws(s) and polls over http(s).
+pub async fn watch_deposits<D: Database>(
+    eth_config: &EthConfig,
+    config: &DepositConfig,
+    db: D,
+    executor: Arc<dyn ExecutorTrait + Send + Sync>,
+) -> Result<()> {
+    let network = Network::find(&eth_config.network).ok_or_else(|| Error::UnknownNetwork {
+        network: eth_config.network.clone(),
+    })?;
+
+    if eth_config.is_ws() {
+        let transport = WebSocket::new(&eth_config.node_url()).await?;
+        DepositWatcher::new(transport, network, config, db, executor)?
+            .run_subscription()
+            .await
+    } else {
+        let transport = Http::new(&eth_config.node_url())?;
+        DepositWatcher::new(transport, network, config, db, executor)?
+            .run_polling(Duration::from_secs(config.poll_interval_secs))
+            .await;
+        Ok(())
+    }
+}
+
+pub(crate) fn event_topic(signature: &str) -> H256 {
+    H256(keccak256(signature.as_bytes()))
+}
+
+/// Id of the deposit made by the log at `log_index` of `tx_hash`.
+fn deposit_id(tx_hash: &H256, log_index: &U256) -> H256 {
+    let mut data = [0u8; 64];
+    data[..32].copy_from_slice(tx_hash.as_bytes());
+    log_index.to_big_endian(&mut data[32..]);
+
+    H256(keccak256(&data))
+}
+
+fn rejected_key(id: &H256) -> DBKey {
+    let mut key = DBKey::from_slice(REJECTED_PREFIX);
+    key.extend_from_slice(id.as_bytes());
+    key
+}
+
+/// Position of a log in the chain, to execute deposits in L1 order.
+fn log_position(log: &Log) -> Option<(u64, U256)> {
+    Some((log.block_number?.as_u64(), log.log_index?))
+}
+
+fn deposit_from_log(log: &Log, recipient: H160, token: H160) -> Option<Deposit> {
+    let (l1_block, log_index) = log_position(log)?;
+
+    Some(Deposit {
+        id: deposit_id(&log.transaction_hash?, &log_index),
+        l1_block,
+        recipient,
+        token,
+        amount: U256::from_big_endian(log.data.0.get(..32)?),
+    })
+}
+
+/// `Deposited(address indexed sender, address indexed recipient, address indexed token,
+/// uint256 amount, uint256 depositId)`
+fn parse_deposited(log: &Log) -> Option<Deposit> {
+    if log.topics.len() != 4 || log.data.0.len() != 64 {
+        return None;
+    }
+
+    deposit_from_log(log, H160::from(log.topics[2]), H160::from(log.topics[3]))
+}
+
+/// `Transfer(address indexed from, address indexed to, uint256 value)` to the rollup: the sender
+/// is credited on L2.
+fn parse_transfer(log: &Log) -> Option<Deposit> {
+    if log.topics.len() != 3 || log.data.0.len() != 32 {
+        return None;
+    }
+
+    deposit_from_log(log, H160::from(log.topics[1]), log.address)
+}
+
+/// Deposits of `deposited` and `transfers` logs in L1 order.
+///
+/// The `Transfer` emitted when `Rollup.deposit` pulls an ERC-20 is already covered by the
+/// `Deposited` event of the same transaction, so it is skipped.
+fn parse_deposits(deposited: &[Log], transfers: &[Log]) -> Vec<Deposit> {
+    let deposit_txs: HashSet<H256> = deposited
+        .iter()
+        .filter_map(|log| log.transaction_hash)
+        .collect();
+
+    let deposited = deposited.iter().map(|log| (log, parse_deposited(log)));
+    let transfers = transfers
+        .iter()
+        .filter(|log| {
+            log.transaction_hash
+                .map_or(true, |hash| !deposit_txs.contains(&hash))
+        })
+        .map(|log| (log, parse_transfer(log)));
+
+    let mut deposits = Vec::new();
+    for (log, deposit) in deposited.chain(transfers) {
+        if log.removed == Some(true) {
+            continue;
+        }
+        match (log_position(log), deposit) {
+            (Some(position), Some(deposit)) => deposits.push((position, deposit)),
+            _ => warn!("skip malformed deposit log: {:?}", log),
+        }
+    }
+    deposits.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    deposits.into_iter().map(|(_, deposit)| deposit).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use db::Db;
+    use ethcontract::futures;
+    use ethcontract::web3::transports::ws::compat;
+    use ethcontract::web3::transports::WebSocket;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::test_utils::{json_rpc_server, ws_config};
+
+    const TOKEN: &str = "0xe7f1725e7734ce288f8367e1bb143e90bb3f0512";
+    const ROLLUP: &str = "0x9fe46736679d2d9a65f0992f2272de9f3c7fa6e0";
+    const ALICE: &str = "0x000000000000000000000000000000000000000a";
+    const BOB: &str = "0x000000000000000000000000000000000000000b";
+
+    #[derive(Default)]
+    struct MockExecutor {
+        deposits: Mutex<Vec<Deposit>>,
+        /// Deposits of this amount overflow the balance of their recipient.
+        overflowing_amount: Option<U256>,
+    }
+
+    impl ExecutorTrait for MockExecutor {
+        fn execute(&self, tx: &Transaction) -> executor::Result<()> {
+            let deposit = match tx {
+                Transaction::Deposit(deposit) => deposit,
+                tx => panic!("unexpected transaction {:?}", tx),
+            };
+            let mut deposits = self.deposits.lock().unwrap();
+            if deposits.iter().any(|d| d.id == deposit.id) {
+                return Err(ExecutorError::DuplicateDeposit { id: deposit.id });
+            }
+            if self.overflowing_amount == Some(deposit.amount) {
+                return Err(ExecutorError::BalanceOverflow {
+                    account: deposit.recipient,
+                    token: deposit.token,
+                });
+            }
+            deposits.push(deposit.clone());
+
+            Ok(())
+        }
+    }
+
+    fn topic(address: &str) -> String {
+        format!("0x{:0>64}", address.trim_start_matches("0x"))
+    }
+
+    fn amount(value: u64) -> String {
+        format!("{:064x}", value)
+    }
+
+    fn log(
+        address: &str,
+        topics: Vec<String>,
+        data: String,
+        block: u64,
+        tx: u64,
+        index: u64,
+    ) -> Value {
+        json!({
+            "address": address,
+            "topics": topics,
+            "data": format!("0x{}", data),
+            "blockHash": format!("{:#066x}", block),
+            "blockNumber": format!("{:#x}", block),
+            "transactionHash": format!("{:#066x}", tx),
+            "transactionIndex": "0x0",
+            "logIndex": format!("{:#x}", index),
+            "transactionLogIndex": "0x0",
+            "removed": false,
+        })
+    }
+
+    fn deposited_log(block: u64, tx: u64, index: u64) -> Value {
+        log(
+            ROLLUP,
+            vec![
+                format!("{:?}", event_topic(DEPOSITED_EVENT)),
+                topic(ALICE),
+                topic(BOB),
+                topic(TOKEN),
+            ],
+            format!("{}{}", amount(100), amount(1)),
+            block,
+            tx,
+            index,
+        )
+    }
+
+    fn transfer_log(block: u64, tx: u64, index: u64) -> Value {
+        log(
+            TOKEN,
+            vec![
+                format!("{:?}", event_topic(TRANSFER_EVENT)),
+                topic(ALICE),
+                topic(ROLLUP),
+            ],
+            amount(30),
+            block,
+            tx,
+            index,
+        )
+    }
+
+    fn logs(values: Vec<Value>) -> Vec<Log> {
+        serde_json::from_value(Value::Array(values)).unwrap()
+    }
+
+    #[test]
+    fn success_parse_deposits() {
+        let deposited = logs(vec![deposited_log(5, 1, 1)]);
+        // tx 1 is the `transferFrom` of `Rollup.deposit`, tx 2 a direct transfer.
+        let transfers = logs(vec![transfer_log(5, 1, 0), transfer_log(3, 2, 0)]);
+
+        let deposits = parse_deposits(&deposited, &transfers);
+        assert_eq!(deposits.len(), 2);
+
+        assert_eq!(deposits[0].l1_block, 3);
+        assert_eq!(deposits[0].recipient, ALICE.parse().unwrap());
+        assert_eq!(deposits[0].token, TOKEN.parse().unwrap());
+        assert_eq!(deposits[0].amount, U256::from(30));
+
+        assert_eq!(deposits[1].l1_block, 5);
+        assert_eq!(deposits[1].recipient, BOB.parse().unwrap());
+        assert_eq!(deposits[1].token, TOKEN.parse().unwrap());
+        assert_eq!(deposits[1].amount, U256::from(100));
+        assert_eq!(
+            deposits[1].id,
+            deposit_id(&H256::from_low_u64_be(1), &U256::from(1))
+        );
+        assert_ne!(deposits[0].id, deposits[1].id);
+    }
+
+    #[test]
+    fn success_parse_deposits_skips_removed_and_malformed() {
+        let mut removed = deposited_log(5, 1, 1);
+        removed["removed"] = json!(true);
+        let mut malformed = transfer_log(5, 2, 0);
+        malformed["data"] = json!("0x");
+
+        assert!(parse_deposits(&logs(vec![removed]), &logs(vec![malformed])).is_empty());
+    }
+
+    /// A watcher of an L1 node at `port` whose head is block 10, with a deposit and two
+    /// transfers.
+    async fn watcher(
+        port: u16,
+        executor: Arc<MockExecutor>,
+    ) -> DepositWatcher<WebSocket, Db<kvdb_memorydb::InMemory>> {
+        let config = ws_config(port, "localhost");
+        let listener =
+            futures::executor::block_on(compat::TcpListener::bind(format!("127.0.0.1:{}", port)))
+                .expect("Failed to bind");
+        tokio::spawn(json_rpc_server(listener, |req| {
+            match req["method"].as_str() {
+                Some("eth_blockNumber") => json!("0xa"),
+                Some("eth_getLogs") => {
+                    let filter = &req["params"][0];
+                    assert_eq!(filter["fromBlock"], "0x0");
+                    assert_eq!(filter["toBlock"], "0x8");
+                    let topics = filter["topics"].to_string();
+                    if topics.contains(&format!("{:?}", event_topic(DEPOSITED_EVENT))) {
+                        Value::Array(vec![deposited_log(5, 1, 1)])
+                    } else {
+                        Value::Array(vec![transfer_log(5, 1, 0), transfer_log(3, 2, 0)])
+                    }
+                }
+                method => panic!("unexpected method {:?}", method),
+            }
+        }));
+
+        let deposit_config = DepositConfig {
+            from_block: 0,
+            confirmations: 2,
+            poll_interval_secs: 1,
+            tokens: vec!["MyCoin".to_string()],
+        };
+        let transport = WebSocket::new(&config.node_url()).await.unwrap();
+        DepositWatcher::new(
+            transport,
+            Network::find("localhost").unwrap(),
+            &deposit_config,
+            Db::new(kvdb_memorydb::create(1), 0),
+            executor,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn success_poll_once() {
+        let executor = Arc::new(MockExecutor::default());
+        let watcher = watcher(8540, executor.clone()).await;
+
+        assert_eq!(watcher.cursor(), None);
+        assert_eq!(watcher.poll_once().await.unwrap(), 2);
+        assert_eq!(watcher.cursor(), Some(8));
+        assert_eq!(executor.deposits.lock().unwrap().len(), 2);
+
+        // nothing new is confirmed.
+        assert_eq!(watcher.poll_once().await.unwrap(), 0);
+        assert_eq!(watcher.cursor(), Some(8));
+
+        // losing the cursor, e.g. a crash before it is saved, does not apply deposits twice.
+        let mut watcher = watcher;
+        watcher.db = Db::new(kvdb_memorydb::create(1), 0);
+        assert_eq!(watcher.poll_once().await.unwrap(), 0);
+        assert_eq!(executor.deposits.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn success_poll_once_skips_rejected_deposit() {
+        let executor = Arc::new(MockExecutor {
+            overflowing_amount: Some(U256::from(30)),
+            ..MockExecutor::default()
+        });
+        let watcher = watcher(8536, executor.clone()).await;
+
+        // The transfer is rejected, the deposit after it is still applied.
+        assert_eq!(watcher.poll_once().await.unwrap(), 1);
+        assert_eq!(watcher.cursor(), Some(8));
+        let deposits = executor.deposits.lock().unwrap().clone();
+        assert_eq!(deposits.len(), 1);
+        assert_eq!(deposits[0].amount, U256::from(100));
+
+        let transfer = &parse_deposits(&[], &logs(vec![transfer_log(3, 2, 0)]))[0];
+        assert_eq!(
+            watcher.rejected_deposit(&transfer.id),
+            Some(transfer.clone())
+        );
+        assert_eq!(watcher.rejected_deposit(&deposits[0].id), None);
+    }
+
+    #[test]
+    fn fail_new_with_unknown_token() {
+        let deposit_config = DepositConfig {
+            tokens: vec!["Unknown".to_string()],
+            ..DepositConfig::default()
+        };
+        let transport = Http::new("http://127.0.0.1:8545").unwrap();
+        let err = DepositWatcher::new(
+            transport,
+            Network::find("localhost").unwrap(),
+            &deposit_config,
+            Db::new(kvdb_memorydb::create(1), 0),
+            Arc::new(MockExecutor::default()),
+        )
+        .err()
+        .unwrap();
+
+        assert!(matches!(err, Error::UnknownContract { .. }));
+    }
+}
@@ -0,0 +1,64 @@
+use ethcontract::web3;
+
+/// Eth provider Result type.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Eth provider errors.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Error from the L1 node.
+    #[error("Web3 error: {}", .0)]
+    Web3(#[from] web3::Error),
+
+    /// Error from a contract call.
+    #[error("Contract error: {}", .0)]
+    Method(#[from] ethcontract::errors::MethodError),
+
+    /// If the committer key is not a valid private key.
+    #[error("Private key error: {}", .0)]
+    PrivateKey(#[from] ethcontract::errors::InvalidPrivateKey),
+
+    /// Error from ABI encoding.
+    #[error("ABI error: {}", .0)]
+    Abi(#[from] ethcontract::common::abi::Error),
+
+    /// Error from the executor.
+    #[error("Executor error: {}", .0)]
+    Executor(#[from] executor::Error),
+
+    /// Error from the database.
+    #[error("Database error: {}", .0)]
+    Db(#[from] std::io::Error),
+
+    /// If the configured network has no artifacts under `src/artifacts`.
+    #[error("Network({}) has no artifacts", .network)]
+    UnknownNetwork {
+        /// Configured network name
+        network: String,
+    },
+
+    /// If the configured network has no artifact for a contract.
+    #[error("Network({}) has no {} artifact", .network, .contract)]
+    UnknownContract {
+        /// Configured network name
+        network: String,
+        /// Contract name
+        contract: String,
+    },
+
+    /// If the L1 node is connected to another chain than the configured network.
+    #[error(
+        "Network({}) has chain id {}, but the L1 node returned {}",
+        .network,
+        .expected,
+        .actual
+    )]
+    ChainIdMismatch {
+        /// Configured network name
+        network: String,
+        /// Chain id of the configured network
+        expected: u64,
+        /// Chain id returned by the L1 node
+        actual: u64,
+    },
+}
@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use ethcontract::H256;
+use intmax_config::EthConfig;
+use tracing::warn;
+
+use crate::error::Result;
+use crate::EthProvider;
+
+/// Polls `Rollup.finalizedBatchNumber()` of the L1 node of `eth_config` every `interval` and
+/// reports every change to `on_finalized`.
+pub async fn watch_finalized_batches<F>(
+    eth_config: &EthConfig,
+    interval: Duration,
+    on_finalized: F,
+) -> Result<()>
+where
+    F: Fn(u64),
+{
+    let provider = EthProvider::connect(eth_config).await?;
+
+    let mut last = None;
+    loop {
+        match provider.finalized_batch_number().await {
+            Ok(batch) if last != Some(batch) => {
+                on_finalized(batch);
+                last = Some(batch);
+            }
+            Ok(_) => (),
+            Err(e) => warn!("failed to fetch the finalized batch: {}", e),
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Polls `Rollup.latestBatchNumber()` of the L1 node of `eth_config` every `interval` and
+/// reports the state root of every batch committed after `from`, in order, to `on_committed`.
+pub async fn watch_committed_batches<F>(
+    eth_config: &EthConfig,
+    interval: Duration,
+    from: u64,
+    on_committed: F,
+) -> Result<()>
+where
+    F: Fn(u64, H256),
+{
+    let provider = EthProvider::connect(eth_config).await?;
+
+    let mut next = from + 1;
+    loop {
+        match provider.latest_batch_number().await {
+            Ok(latest) => {
+                while next <= latest {
+                    match provider.state_root(next).await {
+                        Ok(state_root) => {
+                            on_committed(next, state_root);
+                            next += 1;
+                        }
+                        Err(e) => {
+                            warn!("failed to fetch the state root of batch {}: {}", next, e);
+                            break;
+                        }
+                    }
+                }
+            }
+            Err(e) => warn!("failed to fetch the latest batch: {}", e),
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
@@ -1,36 +1,283 @@
-mod contracts;
+pub mod contracts;
+mod deposit_watcher;
+mod error;
+mod finality_watcher;
+#[cfg(test)]
+mod test_utils;
 
+use contracts::rollup::Rollup;
+use contracts::Network;
+use deposit_watcher::{event_topic, MAX_BLOCK_RANGE};
+pub use deposit_watcher::{watch_deposits, DepositWatcher};
+pub use error::{Error, Result};
+use ethcontract::common::abi::{self, Token};
 use ethcontract::prelude::*;
+use ethcontract::transport::DynTransport;
+use ethcontract::web3::transports::WebSocket;
+use ethcontract::web3::types::{BlockNumber, FilterBuilder, Log};
 use ethcontract::web3::Transport;
+use ethcontract::{Account, PrivateKey};
+pub use finality_watcher::{watch_committed_batches, watch_finalized_batches};
 use intmax_config::EthConfig;
 use secp256k1::SecretKey;
 use std::str::FromStr;
 
+const CLAIMED_EVENT: &str = "Claimed(bytes32,address,address,uint256)";
+const BATCH_COMMITTED_EVENT: &str = "BatchCommitted(uint256,bytes32,bytes32)";
+
 pub struct EthProvider<T: Transport> {
     web3: Web3<T>,
     secret_key: SecretKey,
+    network: String,
 }
 
 impl<T: Transport> EthProvider<T> {
-    fn new(transport: T, config: &EthConfig) -> Self {
+    pub fn new(transport: T, config: &EthConfig) -> Self {
         let secret_key =
             SecretKey::from_str(config.committer_key.as_str()).expect("failed to load secret_key");
 
         EthProvider {
             web3: Web3::new(transport),
             secret_key,
+            network: config.network.clone(),
+        }
+    }
+
+    /// The network whose contract addresses this provider uses.
+    pub fn network(&self) -> Result<&'static Network> {
+        Network::find(&self.network).ok_or_else(|| Error::UnknownNetwork {
+            network: self.network.clone(),
+        })
+    }
+
+    /// Fails unless the L1 node is connected to the chain of the configured network,
+    /// so that we never send transactions to contract addresses of another chain.
+    pub async fn validate_chain_id(&self) -> Result<()> {
+        let network = self.network()?;
+        let actual = self.web3.eth().chain_id().await?.as_u64();
+        if actual != network.chain_id {
+            return Err(Error::ChainIdMismatch {
+                network: network.name.to_string(),
+                expected: network.chain_id,
+                actual,
+            });
         }
+
+        Ok(())
+    }
+
+    /// ABI encoded call of `Rollup.forceExit`. It is built offline, so that a user can send it
+    /// from any wallet without the operator.
+    pub fn force_exit_calldata(
+        &self,
+        batch_number: u64,
+        account: H160,
+        token: H160,
+        amount: U256,
+        proof: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        let function = Rollup::raw_contract().abi.function("forceExit")?;
+
+        Ok(function.encode_input(&[
+            Token::Uint(batch_number.into()),
+            Token::Address(account),
+            Token::Address(token),
+            Token::Uint(amount),
+            Token::Bytes(proof),
+        ])?)
     }
 }
 
+/// The `bytes proof` argument of the rollup contract: `abi.encode(bytes[] openings, bytes slot)`.
+pub fn encode_proof(openings: &[Vec<u8>], slot: &[u8]) -> Vec<u8> {
+    abi::encode(&[
+        Token::Array(openings.iter().cloned().map(Token::Bytes).collect()),
+        Token::Bytes(slot.to_vec()),
+    ])
+}
+
+impl EthProvider<DynTransport> {
+    /// Connects to the L1 node of `config` over ws(s) or http(s).
+    pub async fn connect(config: &EthConfig) -> Result<Self> {
+        let transport = if config.is_ws() {
+            DynTransport::new(WebSocket::new(&config.node_url()).await?)
+        } else {
+            DynTransport::new(Http::new(&config.node_url())?)
+        };
+
+        Ok(EthProvider::new(transport, config))
+    }
+
+    /// The rollup contract of the configured network.
+    pub fn rollup(&self) -> Result<Rollup> {
+        let network = self.network()?;
+        let address = network
+            .address("Rollup")
+            .ok_or_else(|| Error::UnknownContract {
+                network: network.name.to_string(),
+                contract: "Rollup".to_string(),
+            })?;
+
+        Ok(Rollup::at(&self.web3, address))
+    }
+
+    /// Number and state root of the latest batch committed on L1.
+    pub async fn latest_committed_batch(&self) -> Result<(u64, H256)> {
+        let number = self.latest_batch_number().await?;
+
+        Ok((number, self.state_root(number).await?))
+    }
+
+    /// The latest batch committed on L1.
+    pub async fn latest_batch_number(&self) -> Result<u64> {
+        Ok(self.rollup()?.latest_batch_number().call().await?.as_u64())
+    }
+
+    /// State root of the batch `number` committed on L1.
+    pub async fn state_root(&self, number: u64) -> Result<H256> {
+        let state_root = self.rollup()?.state_roots(number.into()).call().await?;
+
+        Ok(H256::from(state_root))
+    }
+
+    pub async fn block_number(&self) -> Result<u64> {
+        Ok(self.web3.eth().block_number().await?.as_u64())
+    }
+
+    /// Commits the batch `number` signed with the committer key, and returns the L1 transaction
+    /// hash. `tx_root` is the diff root proven by the aggregated `proof`.
+    pub async fn commit_batch(
+        &self,
+        number: u64,
+        state_root: H256,
+        tx_root: H256,
+        proof: Vec<u8>,
+    ) -> Result<H256> {
+        let network = self.network()?;
+        let key = PrivateKey::from_slice(&self.secret_key[..])?;
+        let tx = self
+            .rollup()?
+            .commit_batch(
+                number.into(),
+                state_root.0,
+                tx_root.0,
+                ethcontract::tokens::Bytes(proof),
+            )
+            .from(Account::Offline(key, Some(network.chain_id)))
+            .send()
+            .await?;
+
+        Ok(tx.hash())
+    }
+
+    /// Submits the Merkle root of the withdrawal batch `number` signed with the committer key,
+    /// and returns the L1 transaction hash.
+    pub async fn submit_withdrawal_root(&self, number: u64, root: H256) -> Result<H256> {
+        let network = self.network()?;
+        let key = PrivateKey::from_slice(&self.secret_key[..])?;
+        let tx = self
+            .rollup()?
+            .submit_withdrawal_root(number.into(), root.0)
+            .from(Account::Offline(key, Some(network.chain_id)))
+            .send()
+            .await?;
+
+        Ok(tx.hash())
+    }
+
+    /// Ids of the exits claimed on L1 between `from` and `to`, both included.
+    pub async fn claimed_exits(&self, from: u64, to: u64) -> Result<Vec<H256>> {
+        Ok(self
+            .rollup_logs(CLAIMED_EVENT, from, to)
+            .await?
+            .iter()
+            .filter_map(|log| log.topics.get(1).copied())
+            .collect())
+    }
+
+    /// Numbers and `txRoot`s, i.e. diff roots, of the batches committed on L1 between the L1
+    /// blocks `from` and `to`, both included, in order.
+    pub async fn committed_diff_roots(&self, from: u64, to: u64) -> Result<Vec<(u64, H256)>> {
+        Ok(self
+            .rollup_logs(BATCH_COMMITTED_EVENT, from, to)
+            .await?
+            .iter()
+            .filter_map(|log| {
+                let number = log.topics.get(1)?;
+                let tx_root = log.data.0.get(32..64)?;
+                Some((
+                    U256::from_big_endian(number.as_bytes()).low_u64(),
+                    H256::from_slice(tx_root),
+                ))
+            })
+            .collect())
+    }
+
+    /// Logs of `event` emitted by the rollup between `from` and `to`, both included, without
+    /// the removed ones.
+    async fn rollup_logs(&self, event: &str, from: u64, to: u64) -> Result<Vec<Log>> {
+        let rollup = self.rollup()?.address();
+        let mut found = Vec::new();
+        let mut start = from;
+        while start <= to {
+            let end = to.min(start + MAX_BLOCK_RANGE - 1);
+            let logs = self
+                .web3
+                .eth()
+                .logs(
+                    FilterBuilder::default()
+                        .from_block(BlockNumber::Number(start.into()))
+                        .to_block(BlockNumber::Number(end.into()))
+                        .address(vec![rollup])
+                        .topics(Some(vec![event_topic(event)]), None, None, None)
+                        .build(),
+                )
+                .await?;
+            found.extend(logs.into_iter().filter(|log| log.removed != Some(true)));
+            start = end + 1;
+        }
+
+        Ok(found)
+    }
+
+    /// The latest batch whose challenge period is over on L1.
+    pub async fn finalized_batch_number(&self) -> Result<u64> {
+        Ok(self
+            .rollup()?
+            .finalized_batch_number()
+            .call()
+            .await?
+            .as_u64())
+    }
+
+    /// Seconds a committed batch can be challenged before it is finalized.
+    pub async fn challenge_period(&self) -> Result<u64> {
+        Ok(self.rollup()?.challenge_period().call().await?.as_u64())
+    }
+}
+
+/// Connects to the L1 node of `config` and validates its chain id. Called at node startup.
+pub async fn validate_l1_network(config: &EthConfig) -> Result<()> {
+    EthProvider::connect(config)
+        .await?
+        .validate_chain_id()
+        .await
+}
+
 #[cfg(test)]
 mod tests {
 
+    use ethcontract::common::abi::Token;
     use ethcontract::futures::io::{BufReader, BufWriter};
     use ethcontract::futures::StreamExt;
-    use ethcontract::{futures, Address, Http, H160};
+    use ethcontract::{futures, Address, Http, H160, H256, U256};
 
-    use crate::EthProvider;
+    use crate::contracts::Network;
+    use crate::test_utils::{json_rpc_server, ws_config};
+    use crate::{
+        contracts, encode_proof, event_topic, validate_l1_network, Error, EthProvider,
+        BATCH_COMMITTED_EVENT, CLAIMED_EVENT,
+    };
     use intmax_config::{EthConfig, Scheme};
     use secp256k1::{PublicKey, Secp256k1};
 
@@ -90,6 +337,211 @@ mod tests {
         }
     }
 
+    #[test]
+    fn success_contracts_registered_at_build_time() {
+        let my_coin = contracts::my_coin::MyCoin::raw_contract();
+        assert_eq!(
+            my_coin.networks["31337"].address,
+            "e7f1725E7734CE288F8367e1Bb143E90bb3F0512"
+                .parse::<Address>()
+                .unwrap()
+        );
+
+        let rollup = contracts::rollup::Rollup::raw_contract();
+        assert_eq!(
+            rollup.networks["31337"].address,
+            "9fE46736679d2D9a65F0992F2272dE9f3c7fa6e0"
+                .parse::<Address>()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn success_network_addresses_generated() {
+        let network = Network::find("localhost").expect("localhost is generated");
+        assert_eq!(network.chain_id, 31337);
+        assert_eq!(network.chain_id, contracts::addresses::localhost::CHAIN_ID);
+        assert_eq!(
+            network.address("Rollup"),
+            Some(contracts::addresses::localhost::ROLLUP)
+        );
+        assert_eq!(
+            network.address("MyCoin"),
+            Some(contracts::my_coin::MyCoin::raw_contract().networks["31337"].address)
+        );
+        assert_eq!(network.address("Unknown"), None);
+        assert_eq!(Network::find("unknown"), None);
+    }
+
+    #[tokio::test]
+    async fn success_validate_l1_network() {
+        let config = ws_config(8543, "localhost");
+        let listener = futures::executor::block_on(compat::TcpListener::bind("127.0.0.1:8543"))
+            .expect("Failed to bind");
+        tokio::spawn(json_rpc_server(listener, |req| {
+            assert_eq!(req["method"], "eth_chainId");
+            serde_json::json!(format!("{:#x}", 31337u64))
+        }));
+
+        validate_l1_network(&config)
+            .await
+            .expect("chain id should match");
+    }
+
+    #[tokio::test]
+    async fn fail_validate_l1_network_with_other_chain() {
+        let config = ws_config(8542, "localhost");
+        let listener = futures::executor::block_on(compat::TcpListener::bind("127.0.0.1:8542"))
+            .expect("Failed to bind");
+        tokio::spawn(json_rpc_server(listener, |req| {
+            assert_eq!(req["method"], "eth_chainId");
+            serde_json::json!(format!("{:#x}", 1u64))
+        }));
+
+        let err = validate_l1_network(&config).await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::ChainIdMismatch {
+                expected: 31337,
+                actual: 1,
+                ..
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Network(localhost) has chain id 31337, but the L1 node returned 1"
+        );
+    }
+
+    #[tokio::test]
+    async fn success_finalized_batch_number() {
+        let config = ws_config(8539, "localhost");
+        let listener = futures::executor::block_on(compat::TcpListener::bind("127.0.0.1:8539"))
+            .expect("Failed to bind");
+        tokio::spawn(json_rpc_server(listener, |req| {
+            assert_eq!(req["method"], "eth_call");
+            assert_eq!(
+                req["params"][0]["to"],
+                "0x9fe46736679d2d9a65f0992f2272de9f3c7fa6e0"
+            );
+            serde_json::json!(format!("{:#066x}", 5u64))
+        }));
+
+        let provider = EthProvider::connect(&config).await.unwrap();
+        assert_eq!(provider.finalized_batch_number().await.unwrap(), 5);
+    }
+
+    #[tokio::test]
+    async fn success_claimed_exits() {
+        let config = ws_config(8537, "localhost");
+        let listener = futures::executor::block_on(compat::TcpListener::bind("127.0.0.1:8537"))
+            .expect("Failed to bind");
+        tokio::spawn(json_rpc_server(listener, |req| {
+            assert_eq!(req["method"], "eth_getLogs");
+            let filter = &req["params"][0];
+            let log = |id: u64, removed: bool| {
+                serde_json::json!({
+                    "address": "0x9fe46736679d2d9a65f0992f2272de9f3c7fa6e0",
+                    "topics": [
+                        format!("{:?}", event_topic(CLAIMED_EVENT)),
+                        format!("{:#066x}", id),
+                    ],
+                    "data": "0x",
+                    "blockNumber": filter["fromBlock"],
+                    "removed": removed,
+                })
+            };
+            match filter["fromBlock"].as_str() {
+                Some("0x1") => {
+                    assert_eq!(filter["toBlock"], "0x3e8");
+                    serde_json::json!([log(1, false), log(2, true)])
+                }
+                Some("0x3e9") => {
+                    assert_eq!(filter["toBlock"], "0x3ea");
+                    serde_json::json!([log(3, false)])
+                }
+                from => panic!("unexpected fromBlock {:?}", from),
+            }
+        }));
+
+        let provider = EthProvider::connect(&config).await.unwrap();
+        assert_eq!(
+            provider.claimed_exits(1, 1002).await.unwrap(),
+            vec![H256::from_low_u64_be(1), H256::from_low_u64_be(3)]
+        );
+    }
+
+    #[tokio::test]
+    async fn success_committed_diff_roots() {
+        let config = ws_config(8546, "localhost");
+        let listener = futures::executor::block_on(compat::TcpListener::bind("127.0.0.1:8546"))
+            .expect("Failed to bind");
+        tokio::spawn(json_rpc_server(listener, |req| {
+            assert_eq!(req["method"], "eth_getLogs");
+            let filter = &req["params"][0];
+            assert_eq!(filter["fromBlock"], "0x1");
+            assert_eq!(filter["toBlock"], "0xa");
+            let log = |number: u64, removed: bool| {
+                let tx_root = format!("{:02x}", number).repeat(32);
+                serde_json::json!({
+                    "address": "0x9fe46736679d2d9a65f0992f2272de9f3c7fa6e0",
+                    "topics": [
+                        format!("{:?}", event_topic(BATCH_COMMITTED_EVENT)),
+                        format!("{:#066x}", number),
+                    ],
+                    "data": format!("0x{}{}", "11".repeat(32), tx_root),
+                    "blockNumber": "0x1",
+                    "removed": removed,
+                })
+            };
+            serde_json::json!([log(1, false), log(2, true), log(3, false)])
+        }));
+
+        let provider = EthProvider::connect(&config).await.unwrap();
+        assert_eq!(
+            provider.committed_diff_roots(1, 10).await.unwrap(),
+            vec![(1, H256::repeat_byte(1)), (3, H256::repeat_byte(3))]
+        );
+    }
+
+    #[test]
+    fn success_force_exit_calldata() {
+        let config = ws_config(8538, "localhost");
+        let provider = EthProvider::new(Http::new(&config.node_url()).unwrap(), &config);
+        let account = H160::repeat_byte(0xaa);
+        let proof = encode_proof(&[vec![1, 2]], &[3]);
+
+        let calldata = provider
+            .force_exit_calldata(7, account, H160::zero(), U256::from(100), proof.clone())
+            .unwrap();
+
+        let function = contracts::rollup::Rollup::raw_contract()
+            .abi
+            .function("forceExit")
+            .unwrap();
+        assert_eq!(calldata[..4], function.short_signature());
+        assert_eq!(
+            function.decode_input(&calldata[4..]).unwrap(),
+            vec![
+                Token::Uint(U256::from(7)),
+                Token::Address(account),
+                Token::Address(H160::zero()),
+                Token::Uint(U256::from(100)),
+                Token::Bytes(proof),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn fail_validate_l1_network_with_unknown_network() {
+        let config = ws_config(8541, "unknown");
+        let transport = Http::new(&config.node_url()).unwrap();
+        let provider = EthProvider::new(transport, &config);
+
+        let err = provider.validate_chain_id().await.unwrap_err();
+        assert!(matches!(err, Error::UnknownNetwork { .. }));
+    }
+
     #[tokio::test]
     async fn success_eth_provider_new_with_http() {
         let config = EthConfig {
@@ -98,6 +550,7 @@ mod tests {
             port: 8545,
             host: "127.0.0.1".to_string(),
             scheme: Scheme::Http,
+            network: "localhost".to_string(),
         };
 
         let transport = Http::new(&config.node_url()).unwrap();
@@ -126,6 +579,7 @@ mod tests {
             port: 8544,
             host: "127.0.0.1".to_string(),
             scheme: Scheme::Ws,
+            network: "localhost".to_string(),
         };
 
         let addr = config.node_url();
@@ -0,0 +1,57 @@
+use ethcontract::futures::io::{BufReader, BufWriter};
+use ethcontract::futures::StreamExt;
+use ethcontract::web3::transports::ws::compat;
+use intmax_config::{EthConfig, Scheme};
+use serde_json::Value;
+use soketto::handshake;
+use tokio_stream::wrappers::TcpListenerStream;
+
+/// WebSocket JSON-RPC server answering every request with `handler(request)` as the result.
+pub async fn json_rpc_server<F>(listener: compat::TcpListener, handler: F)
+where
+    F: Fn(&Value) -> Value,
+{
+    let mut incoming = TcpListenerStream::new(listener);
+    while let Some(Ok(socket)) = incoming.next().await {
+        let socket = compat::compat(socket);
+        let mut server = handshake::Server::new(BufReader::new(BufWriter::new(socket)));
+        let key = {
+            let req = server.receive_request().await.unwrap();
+            req.key()
+        };
+        let accept = handshake::server::Response::Accept {
+            key,
+            protocol: None,
+        };
+        server.send_response(&accept).await.unwrap();
+        let (mut sender, mut receiver) = server.into_builder().finish();
+        loop {
+            let mut data = Vec::new();
+            match receiver.receive_data(&mut data).await {
+                Ok(data_type) if data_type.is_text() => {
+                    let req: Value = serde_json::from_slice(&data).unwrap();
+                    let res = serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": req["id"],
+                        "result": handler(&req),
+                    });
+                    sender.send_text(res.to_string()).await.unwrap();
+                    sender.flush().await.unwrap();
+                }
+                Err(soketto::connection::Error::Closed) => break,
+                e => panic!("Unexpected data: {:?}", e),
+            }
+        }
+    }
+}
+
+pub fn ws_config(port: u16, network: &str) -> EthConfig {
+    EthConfig {
+        committer_key: "10d18ee85b1a2e1d4b47feed91074a6bb4a17b55005144338208a0be031752d3"
+            .to_string(),
+        port,
+        host: "127.0.0.1".to_string(),
+        scheme: Scheme::Ws,
+        network: network.to_string(),
+    }
+}
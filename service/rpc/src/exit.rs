@@ -0,0 +1,304 @@
+use std::sync::Arc;
+
+use codec::Encode;
+
+use ethereum_types::{H160, H256};
+use executor::Withdrawal;
+use exitor::{ClaimStatus, ExitStatus as Status, ExitorTrait};
+use fc_rpc_core::types::Bytes;
+use jsonrpc_core::{BoxFuture, Result};
+use tracing::info_span;
+
+use intmax_json_rpc_api::types::{
+    ClaimProof, ClaimStatus as WireClaimStatus, ExitProof, ExitRequest, ExitStatus,
+};
+use intmax_json_rpc_api::ExitApi as ExitApiT;
+
+pub struct ExitApi {
+    exitor: Arc<dyn ExitorTrait + Send + Sync>,
+}
+
+impl ExitApi {
+    pub fn new(exitor: Arc<dyn ExitorTrait + Send + Sync>) -> ExitApi {
+        ExitApi { exitor }
+    }
+}
+
+impl ExitApiT for ExitApi {
+    fn request_exit(&self, req: ExitRequest) -> BoxFuture<Result<H256>> {
+        let _guard = info_span!("request_exit").entered();
+
+        let res = self
+            .exitor
+            .request_exit(
+                Withdrawal {
+                    account: req.from,
+                    token: req.token,
+                    amount: req.amount,
+                    nonce: req.nonce,
+                },
+                &req.signature.0,
+            )
+            .map_err(Into::into);
+
+        Box::pin(async move { res })
+    }
+
+    fn exit_proof(&self, id: H256) -> BoxFuture<Result<ExitProof>> {
+        let _guard = info_span!("exit_proof").entered();
+
+        let res = self
+            .exitor
+            .exit_proof(&id)
+            .map(|proof| ExitProof {
+                id: proof.id,
+                batch_number: proof.batch_number,
+                state_root: proof.state_root,
+                recipient: proof.withdrawal.account,
+                token: proof.withdrawal.token,
+                amount: proof.withdrawal.amount,
+                slot: Bytes(proof.proof.slot.encode()),
+                openings: proof.proof.openings.into_iter().map(Bytes).collect(),
+            })
+            .map_err(Into::into);
+
+        Box::pin(async move { res })
+    }
+
+    fn claim_proof(&self, id: H256) -> BoxFuture<Result<ClaimProof>> {
+        let _guard = info_span!("claim_proof").entered();
+
+        let res = self
+            .exitor
+            .claim_proof(&id)
+            .map(|proof| ClaimProof {
+                id: proof.id,
+                withdrawal_batch: proof.withdrawal_batch,
+                root: proof.root,
+                recipient: proof.withdrawal.account,
+                token: proof.withdrawal.token,
+                amount: proof.withdrawal.amount,
+                proof: proof.proof,
+                claimed: proof.status == ClaimStatus::Claimed,
+            })
+            .map_err(Into::into);
+
+        Box::pin(async move { res })
+    }
+
+    fn exit_status(&self, id: H256) -> BoxFuture<Result<ExitStatus>> {
+        let _guard = info_span!("exit_status").entered();
+
+        let res = self
+            .exitor
+            .exit_status(&id)
+            .map(into_exit_status)
+            .map_err(Into::into);
+
+        Box::pin(async move { res })
+    }
+
+    fn list_exits(&self, account: H160) -> BoxFuture<Result<Vec<ExitStatus>>> {
+        let _guard = info_span!("list_exits").entered();
+
+        let res = self
+            .exitor
+            .list_exits(&account)
+            .map(|exits| exits.into_iter().map(into_exit_status).collect())
+            .map_err(Into::into);
+
+        Box::pin(async move { res })
+    }
+}
+
+fn into_exit_status(status: Status) -> ExitStatus {
+    ExitStatus {
+        id: status.id,
+        recipient: status.withdrawal.account,
+        token: status.withdrawal.token,
+        amount: status.withdrawal.amount,
+        requested_at: status.requested_at,
+        block_number: status.block,
+        challenge_window_end: status.challenge_window_end,
+        proof_available: status.proof_available,
+        withdrawal_batch: status.withdrawal_batch,
+        finalization_tx: status.finalization_tx,
+        claim_status: status.claim_status.map(|claim| match claim {
+            ClaimStatus::Unclaimed => WireClaimStatus::Unclaimed,
+            ClaimStatus::Claimed => WireClaimStatus::Claimed,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use ethereum_types::{H160, U256};
+    use exitor::{ClaimProof as Claim, Error as ExitorError, ExitProof as Proof};
+    use intmax_verkle::{Proof as StateProof, Slot};
+    use jsonrpc_core::ErrorCode;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct MockExitor {
+        exits: Mutex<Vec<Withdrawal>>,
+    }
+
+    impl ExitorTrait for MockExitor {
+        fn request_exit(&self, withdrawal: Withdrawal, _: &[u8]) -> exitor::Result<H256> {
+            self.exits.lock().unwrap().push(withdrawal);
+            Ok(H256::from_low_u64_be(1))
+        }
+
+        fn exit_proof(&self, id: &H256) -> exitor::Result<Proof> {
+            let exits = self.exits.lock().unwrap();
+            let withdrawal = exits
+                .first()
+                .cloned()
+                .ok_or(ExitorError::UnknownExit { id: *id })?;
+
+            Ok(Proof {
+                id: *id,
+                batch_number: 2,
+                state_root: H256::repeat_byte(3),
+                withdrawal,
+                proof: StateProof {
+                    root: [3; 32],
+                    key: vec![1],
+                    value: Some(vec![2]),
+                    openings: vec![vec![1]],
+                    slot: Slot::Stem(vec![]),
+                },
+            })
+        }
+
+        fn finalize_batch(&self, _batch: u64) -> exitor::Result<()> {
+            Ok(())
+        }
+
+        fn claim_proof(&self, id: &H256) -> exitor::Result<Claim> {
+            let exits = self.exits.lock().unwrap();
+            let withdrawal = exits
+                .first()
+                .cloned()
+                .ok_or(ExitorError::NotBatched { id: *id })?;
+
+            Ok(Claim {
+                id: *id,
+                withdrawal_batch: 1,
+                root: H256::repeat_byte(1),
+                withdrawal,
+                proof: vec![H256::repeat_byte(2)],
+                status: ClaimStatus::Claimed,
+            })
+        }
+
+        fn exit_status(&self, id: &H256) -> exitor::Result<Status> {
+            let exits = self.exits.lock().unwrap();
+            let withdrawal = exits
+                .first()
+                .cloned()
+                .ok_or(ExitorError::UnknownExit { id: *id })?;
+
+            Ok(Status {
+                id: *id,
+                withdrawal,
+                requested_at: 1,
+                block: Some(2),
+                challenge_window_end: Some(3),
+                proof_available: true,
+                withdrawal_batch: Some(1),
+                finalization_tx: Some(H256::repeat_byte(1)),
+                claim_status: Some(ClaimStatus::Unclaimed),
+            })
+        }
+
+        fn list_exits(&self, account: &H160) -> exitor::Result<Vec<Status>> {
+            let requested = self
+                .exits
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|withdrawal| withdrawal.account == *account)
+                .count();
+            (0..requested)
+                .map(|_| self.exit_status(&H256::from_low_u64_be(1)))
+                .collect()
+        }
+    }
+
+    #[tokio::test]
+    async fn success_request_exit() {
+        let exit_api = ExitApi::new(Arc::new(MockExitor::default()));
+        let req = ExitRequest {
+            from: H160::random(),
+            amount: U256::from(10),
+            ..ExitRequest::default()
+        };
+
+        let id = exit_api.request_exit(req.clone()).await.unwrap();
+        let proof = exit_api.exit_proof(id).await.unwrap();
+
+        assert_eq!(proof.id, id);
+        assert_eq!(proof.batch_number, 2);
+        assert_eq!(proof.state_root, H256::repeat_byte(3));
+        assert_eq!(proof.recipient, req.from);
+        assert_eq!(proof.amount, req.amount);
+        assert_eq!(proof.openings, vec![Bytes(vec![1])]);
+        assert_eq!(proof.slot, Bytes(Slot::Stem(vec![]).encode()));
+    }
+
+    #[tokio::test]
+    async fn success_claim_proof() {
+        let exit_api = ExitApi::new(Arc::new(MockExitor::default()));
+        let req = ExitRequest {
+            from: H160::random(),
+            amount: U256::from(10),
+            ..ExitRequest::default()
+        };
+
+        let id = exit_api.request_exit(req.clone()).await.unwrap();
+        let proof = exit_api.claim_proof(id).await.unwrap();
+
+        assert_eq!(proof.withdrawal_batch, 1);
+        assert_eq!(proof.recipient, req.from);
+        assert_eq!(proof.proof, vec![H256::repeat_byte(2)]);
+        assert!(proof.claimed);
+    }
+
+    #[tokio::test]
+    async fn success_exit_status() {
+        let exit_api = ExitApi::new(Arc::new(MockExitor::default()));
+        let req = ExitRequest {
+            from: H160::random(),
+            amount: U256::from(10),
+            ..ExitRequest::default()
+        };
+
+        let id = exit_api.request_exit(req.clone()).await.unwrap();
+        let status = exit_api.exit_status(id).await.unwrap();
+        assert_eq!(status.recipient, req.from);
+        assert_eq!(status.block_number, Some(2));
+        assert_eq!(status.finalization_tx, Some(H256::repeat_byte(1)));
+        assert_eq!(status.claim_status, Some(WireClaimStatus::Unclaimed));
+
+        assert_eq!(exit_api.list_exits(req.from).await.unwrap(), vec![status]);
+        assert!(exit_api.list_exits(H160::zero()).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn fail_exit_proof() {
+        let exit_api = ExitApi::new(Arc::new(MockExitor::default()));
+
+        let err = exit_api.exit_proof(H256::zero()).await.unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidParams);
+
+        let err = exit_api.claim_proof(H256::zero()).await.unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidParams);
+
+        let err = exit_api.exit_status(H256::zero()).await.unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidParams);
+    }
+}
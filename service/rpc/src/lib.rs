@@ -1,21 +1,46 @@
-use ethereum_types::H256;
+use std::convert::TryInto;
+use std::sync::Arc;
+
+use codec::Encode;
+
+use ethereum_types::{H160, H256, U256, U64};
+use executor::{state, Transaction};
+use fc_rpc_core::types::Bytes;
 use fc_rpc_core::types::TransactionRequest;
 use jsonrpc_core::{BoxFuture, Result};
+use query_receiver::{BlockId, LogFilter, QueryReceiverTrait};
 use tracing::info_span;
 
+use intmax_json_rpc_api::types::{
+    AccountProof, AccountTransactions, Block, BlockNumber, BlockTransactions, Filter, Log,
+    NonMembershipProof, Transaction as RpcTransaction, TransactionKind,
+};
 use intmax_json_rpc_api::EthApi as EthApiT;
 use tx_receiver::{TxReceiver, TxReceiverTrait};
 
-#[derive(Debug)]
 pub struct EthApi {
     tx_receiver: TxReceiver,
+    query_receiver: Arc<dyn QueryReceiverTrait + Send + Sync>,
 }
 
 mod error;
+mod exit;
+mod su;
+mod sync;
+
+pub use exit::ExitApi;
+pub use su::SuApi;
+pub use sync::StateSyncApi;
 
 impl EthApi {
-    pub fn new(tx_receiver: TxReceiver) -> EthApi {
-        EthApi { tx_receiver }
+    pub fn new(
+        tx_receiver: TxReceiver,
+        query_receiver: Arc<dyn QueryReceiverTrait + Send + Sync>,
+    ) -> EthApi {
+        EthApi {
+            tx_receiver,
+            query_receiver,
+        }
     }
 }
 
@@ -30,6 +55,290 @@ impl EthApiT for EthApi {
 
         Box::pin(async move { Ok(H256::zero()) })
     }
+
+    fn balance(&self, account: H160, block: Option<BlockNumber>) -> BoxFuture<Result<U256>> {
+        let _guard = info_span!("balance").entered();
+
+        let res = self
+            .query_receiver
+            .balance(&account, into_block_id(block.unwrap_or_default()))
+            .map_err(Into::into);
+
+        Box::pin(async move { res })
+    }
+
+    fn transaction_count(
+        &self,
+        account: H160,
+        block: Option<BlockNumber>,
+    ) -> BoxFuture<Result<U256>> {
+        let _guard = info_span!("transaction_count").entered();
+
+        let res = self
+            .query_receiver
+            .transaction_count(&account, into_block_id(block.unwrap_or_default()))
+            .map_err(Into::into);
+
+        Box::pin(async move { res })
+    }
+
+    fn block_number(&self) -> BoxFuture<Result<U256>> {
+        let number = self.query_receiver.block_number();
+
+        Box::pin(async move { Ok(number.into()) })
+    }
+
+    fn block_by_number(&self, number: BlockNumber, full: bool) -> BoxFuture<Result<Option<Block>>> {
+        let _guard = info_span!("block_by_number").entered();
+
+        let block = self
+            .query_receiver
+            .block_by_number(into_block_id(number))
+            .map(|block| into_block(block, full));
+
+        Box::pin(async move { Ok(block) })
+    }
+
+    fn block_by_hash(&self, hash: H256, full: bool) -> BoxFuture<Result<Option<Block>>> {
+        let _guard = info_span!("block_by_hash").entered();
+
+        let block = self
+            .query_receiver
+            .block_by_hash(&hash)
+            .map(|block| into_block(block, full));
+
+        Box::pin(async move { Ok(block) })
+    }
+
+    fn chain_id(&self) -> BoxFuture<Result<U64>> {
+        let chain_id = self.query_receiver.chain_id();
+
+        Box::pin(async move { Ok(chain_id.into()) })
+    }
+
+    fn proof(
+        &self,
+        account: H160,
+        token: H160,
+        block: Option<BlockNumber>,
+    ) -> BoxFuture<Result<AccountProof>> {
+        let _guard = info_span!("proof").entered();
+
+        let res = self
+            .query_receiver
+            .balance_proof(&account, &token, into_block_id(block.unwrap_or_default()))
+            .map(|proof| AccountProof {
+                address: proof.account,
+                token: proof.token,
+                balance: proof.balance,
+                block_number: proof.block.map(Into::into),
+                state_root: proof.state_root,
+                key: state::balance_key(&proof.account, &proof.token),
+                scheme: proof.scheme.to_string(),
+                root: H256(proof.proof.root),
+                slot: Bytes(proof.proof.slot.encode()),
+                openings: proof.proof.openings.into_iter().map(Bytes).collect(),
+            })
+            .map_err(Into::into);
+
+        Box::pin(async move { res })
+    }
+
+    fn non_membership_proof(
+        &self,
+        key: H256,
+        block: Option<BlockNumber>,
+    ) -> BoxFuture<Result<NonMembershipProof>> {
+        let _guard = info_span!("non_membership_proof").entered();
+
+        let res = self
+            .query_receiver
+            .non_membership_proof(&key, into_block_id(block.unwrap_or_default()))
+            .map(|proof| NonMembershipProof {
+                key: proof.key,
+                block_number: proof.block.map(Into::into),
+                state_root: proof.state_root,
+                scheme: proof.scheme.to_string(),
+                root: H256(proof.proof.root),
+                slot: Bytes(proof.proof.slot.encode()),
+                openings: proof.proof.openings.into_iter().map(Bytes).collect(),
+            })
+            .map_err(Into::into);
+
+        Box::pin(async move { res })
+    }
+
+    fn logs(&self, filter: Filter) -> BoxFuture<Result<Vec<Log>>> {
+        let _guard = info_span!("logs").entered();
+
+        let res = self
+            .query_receiver
+            .logs(&into_log_filter(filter))
+            .map(|logs| logs.into_iter().map(into_log).collect())
+            .map_err(Into::into);
+
+        Box::pin(async move { res })
+    }
+
+    fn new_filter(&self, filter: Filter) -> BoxFuture<Result<U256>> {
+        let _guard = info_span!("new_filter").entered();
+
+        let id = self.query_receiver.new_filter(into_log_filter(filter));
+
+        Box::pin(async move { Ok(id.into()) })
+    }
+
+    fn filter_changes(&self, id: U256) -> BoxFuture<Result<Vec<Log>>> {
+        let _guard = info_span!("filter_changes").entered();
+
+        let res = into_filter_id(id).and_then(|id| {
+            self.query_receiver
+                .filter_changes(id)
+                .map(|logs| logs.into_iter().map(into_log).collect())
+                .map_err(Into::into)
+        });
+
+        Box::pin(async move { res })
+    }
+
+    fn uninstall_filter(&self, id: U256) -> BoxFuture<Result<bool>> {
+        let _guard = info_span!("uninstall_filter").entered();
+
+        let res = into_filter_id(id).map(|id| self.query_receiver.uninstall_filter(id));
+
+        Box::pin(async move { res })
+    }
+
+    fn account_transactions(
+        &self,
+        account: H160,
+        cursor: Option<U64>,
+        limit: Option<U64>,
+    ) -> BoxFuture<Result<AccountTransactions>> {
+        let _guard = info_span!("account_transactions").entered();
+
+        let page = self.query_receiver.account_transactions(
+            &account,
+            cursor.map(|cursor| cursor.as_u64()),
+            limit.map(|limit| limit.as_u64()),
+        );
+        let res = AccountTransactions {
+            transactions: page
+                .transactions
+                .iter()
+                .map(|tx| into_transaction(&tx.transaction, tx.block))
+                .collect(),
+            next_cursor: page.next_cursor.map(Into::into),
+        };
+
+        Box::pin(async move { Ok(res) })
+    }
+}
+
+fn into_filter_id(id: U256) -> Result<u64> {
+    id.try_into()
+        .map_err(|_| jsonrpc_core::Error::invalid_params(format!("unknown filter id: {}", id)))
+}
+
+fn into_log_filter(filter: Filter) -> LogFilter {
+    LogFilter {
+        from_block: into_block_id(filter.from_block.unwrap_or_default()),
+        to_block: into_block_id(filter.to_block.unwrap_or_default()),
+        block_hash: filter.block_hash,
+        address: filter.address.map(|a| a.into_vec()).unwrap_or_default(),
+        topics: filter
+            .topics
+            .unwrap_or_default()
+            .into_iter()
+            .map(|topics| topics.map(|t| t.into_vec()))
+            .collect(),
+    }
+}
+
+fn into_log(log: executor::Log) -> Log {
+    Log {
+        address: log.address,
+        topics: log.topics,
+        data: Bytes(log.data),
+        block_number: log.block_number.into(),
+        block_hash: log.block_hash,
+        transaction_hash: log.transaction_hash,
+        transaction_index: log.transaction_index.into(),
+        log_index: log.log_index.into(),
+        removed: false,
+    }
+}
+
+fn into_block_id(block: BlockNumber) -> BlockId {
+    match block {
+        BlockNumber::Latest => BlockId::Latest,
+        BlockNumber::Pending => BlockId::Pending,
+        BlockNumber::Finalized => BlockId::Finalized,
+        BlockNumber::Earliest => BlockId::Earliest,
+        BlockNumber::Num(number) => BlockId::Number(number),
+        BlockNumber::Hash(hash) => BlockId::Hash(hash),
+    }
+}
+
+fn into_block(block: executor::Block, full: bool) -> Block {
+    let transactions = if full {
+        BlockTransactions::Full(
+            block
+                .transactions
+                .iter()
+                .map(|transaction| into_transaction(transaction, block.number))
+                .collect(),
+        )
+    } else {
+        BlockTransactions::Hashes(block.transactions.iter().map(Transaction::hash).collect())
+    };
+
+    Block {
+        number: block.number.into(),
+        hash: block.hash,
+        parent_hash: block.parent_hash,
+        timestamp: block.timestamp.into(),
+        transactions,
+    }
+}
+
+fn into_transaction(transaction: &Transaction, block_number: u64) -> RpcTransaction {
+    let hash = transaction.hash();
+    match transaction {
+        Transaction::Deposit(deposit) => RpcTransaction {
+            hash,
+            kind: TransactionKind::Deposit,
+            from: None,
+            to: Some(deposit.recipient),
+            token: deposit.token,
+            value: deposit.amount,
+            nonce: None,
+            state_root: None,
+            block_number: block_number.into(),
+        },
+        Transaction::Withdraw(withdrawal) => RpcTransaction {
+            hash,
+            kind: TransactionKind::Withdraw,
+            from: Some(withdrawal.account),
+            to: None,
+            token: withdrawal.token,
+            value: withdrawal.amount,
+            nonce: Some(withdrawal.nonce),
+            state_root: None,
+            block_number: block_number.into(),
+        },
+        Transaction::UpdateState(diff) => RpcTransaction {
+            hash,
+            kind: TransactionKind::UpdateState,
+            from: Some(diff.user),
+            to: None,
+            token: H160::zero(),
+            value: U256::zero(),
+            nonce: Some(diff.nonce.into()),
+            state_root: Some(diff.new_root),
+            block_number: block_number.into(),
+        },
+    }
 }
 
 #[cfg(test)]
@@ -37,12 +346,201 @@ mod tests {
     use fc_rpc_core::types::TransactionRequest;
     use primitive_types::{H160, U256};
 
+    use executor::Deposit;
+    use intmax_json_rpc_api::types::ValueOrArray;
+    use intmax_verkle::{Proof, SchemeKind, Slot};
+    use query_receiver::{BalanceProof, Error as QueryError};
+
     use super::*;
 
+    /// Holds a single block 1 with a deposit.
+    struct MockQueryReceiver;
+
+    impl MockQueryReceiver {
+        fn block() -> executor::Block {
+            executor::Block::new(
+                1,
+                H256::zero(),
+                10,
+                H256::zero(),
+                vec![Transaction::Deposit(Deposit {
+                    id: H256::from_low_u64_be(1),
+                    l1_block: 1,
+                    recipient: ethereum_types::H160::repeat_byte(1),
+                    token: ethereum_types::H160::zero(),
+                    amount: ethereum_types::U256::from(10),
+                })],
+            )
+        }
+
+        fn log() -> executor::Log {
+            executor::Log {
+                address: ethereum_types::H160::zero(),
+                topics: vec![executor::deposit_topic()],
+                data: vec![10],
+                block_number: 1,
+                block_hash: Self::block().hash,
+                transaction_hash: Self::block().transactions[0].hash(),
+                transaction_index: 0,
+                log_index: 0,
+            }
+        }
+    }
+
+    impl QueryReceiverTrait for MockQueryReceiver {
+        fn balance(
+            &self,
+            _account: &ethereum_types::H160,
+            block: BlockId,
+        ) -> query_receiver::Result<ethereum_types::U256> {
+            match block {
+                BlockId::Number(number) if number != 1 => {
+                    Err(QueryError::Executor(executor::Error::UnknownBlockNumber {
+                        number,
+                    }))
+                }
+                _ => Ok(ethereum_types::U256::from(10)),
+            }
+        }
+
+        fn transaction_count(
+            &self,
+            _account: &ethereum_types::H160,
+            _block: BlockId,
+        ) -> query_receiver::Result<ethereum_types::U256> {
+            Ok(ethereum_types::U256::zero())
+        }
+
+        fn block_number(&self) -> u64 {
+            1
+        }
+
+        fn block_by_number(&self, block: BlockId) -> Option<executor::Block> {
+            match block {
+                BlockId::Latest | BlockId::Number(1) => Some(Self::block()),
+                _ => None,
+            }
+        }
+
+        fn block_by_hash(&self, hash: &H256) -> Option<executor::Block> {
+            Some(Self::block()).filter(|block| block.hash == *hash)
+        }
+
+        fn chain_id(&self) -> u64 {
+            42
+        }
+
+        fn balance_proof(
+            &self,
+            account: &ethereum_types::H160,
+            token: &ethereum_types::H160,
+            block: BlockId,
+        ) -> query_receiver::Result<BalanceProof> {
+            if block == BlockId::Pending {
+                return Err(QueryError::ProofUnavailable { number: 2 });
+            }
+
+            Ok(BalanceProof {
+                account: *account,
+                token: *token,
+                balance: ethereum_types::U256::from(10),
+                block: Some(1),
+                state_root: Some(H256::repeat_byte(2)),
+                scheme: SchemeKind::Verkle,
+                proof: Proof {
+                    root: [3; 32],
+                    key: state::balance_key(account, token).as_bytes().to_vec(),
+                    value: Some(vec![1]),
+                    openings: vec![],
+                    slot: Slot::Stem(vec![]),
+                },
+            })
+        }
+
+        fn non_membership_proof(
+            &self,
+            key: &H256,
+            block: BlockId,
+        ) -> query_receiver::Result<query_receiver::NonMembershipProof> {
+            if *key == H256::zero() {
+                return Err(QueryError::InState { key: *key });
+            }
+
+            Ok(query_receiver::NonMembershipProof {
+                key: *key,
+                block: Some(1).filter(|_| block != BlockId::Pending),
+                state_root: None,
+                scheme: SchemeKind::SparseMerkle,
+                proof: Proof {
+                    root: [3; 32],
+                    key: key.as_bytes().to_vec(),
+                    value: None,
+                    openings: vec![],
+                    slot: Slot::Other {
+                        stem: vec![2],
+                        values: [2; 32],
+                    },
+                },
+            })
+        }
+
+        fn logs(&self, filter: &LogFilter) -> query_receiver::Result<Vec<executor::Log>> {
+            if filter.from_block == BlockId::Number(2) {
+                return Err(QueryError::InvalidBlockRange { from: 2, to: 1 });
+            }
+
+            Ok(vec![Self::log()]
+                .into_iter()
+                .filter(|log| filter.matches(log))
+                .collect())
+        }
+
+        fn new_filter(&self, _filter: LogFilter) -> u64 {
+            1
+        }
+
+        fn filter_changes(&self, id: u64) -> query_receiver::Result<Vec<executor::Log>> {
+            match id {
+                1 => Ok(vec![Self::log()]),
+                id => Err(QueryError::UnknownFilter { id }),
+            }
+        }
+
+        fn uninstall_filter(&self, id: u64) -> bool {
+            id == 1
+        }
+
+        fn account_transactions(
+            &self,
+            account: &ethereum_types::H160,
+            cursor: Option<u64>,
+            _limit: Option<u64>,
+        ) -> query_receiver::AccountTransactions {
+            let block = Self::block();
+            let transactions = match (cursor, block.transactions[0].clone()) {
+                (None, Transaction::Deposit(deposit)) if deposit.recipient == *account => {
+                    vec![query_receiver::AccountTransaction {
+                        transaction: Transaction::Deposit(deposit),
+                        block: block.number,
+                    }]
+                }
+                _ => vec![],
+            };
+
+            query_receiver::AccountTransactions {
+                transactions,
+                next_cursor: None,
+            }
+        }
+    }
+
+    fn eth_api() -> EthApi {
+        EthApi::new(TxReceiver::new(), Arc::new(MockQueryReceiver))
+    }
+
     #[tokio::test]
     async fn success_send_transaction() {
-        let tx_receiver = TxReceiver::new();
-        let eth_api = EthApi::new(tx_receiver);
+        let eth_api = eth_api();
         let tx = TransactionRequest {
             from: Some(H160::random()),
             nonce: Some(U256::from(3000u32)),
@@ -55,9 +553,200 @@ mod tests {
 
     #[tokio::test]
     async fn fail_send_transaction() {
-        let tx_receiver = TxReceiver::new();
-        let eth_api = EthApi::new(tx_receiver);
+        let eth_api = eth_api();
 
         let _res = eth_api.send_transaction(TransactionRequest::default());
     }
+
+    #[tokio::test]
+    async fn success_balance() {
+        let eth_api = eth_api();
+        let account = ethereum_types::H160::repeat_byte(1);
+
+        let balance = eth_api.balance(account, None).await.unwrap();
+        assert_eq!(balance, ethereum_types::U256::from(10));
+        let nonce = eth_api.transaction_count(account, None).await.unwrap();
+        assert_eq!(nonce, ethereum_types::U256::zero());
+
+        let err = eth_api
+            .balance(account, Some(BlockNumber::Num(2)))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, jsonrpc_core::ErrorCode::InvalidParams);
+    }
+
+    #[tokio::test]
+    async fn success_blocks() {
+        let eth_api = eth_api();
+        let expected = MockQueryReceiver::block();
+
+        assert_eq!(
+            eth_api.block_number().await.unwrap(),
+            ethereum_types::U256::one()
+        );
+        assert_eq!(eth_api.chain_id().await.unwrap(), U64::from(42));
+
+        let block = eth_api
+            .block_by_number(BlockNumber::Latest, false)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(block.number, U64::one());
+        assert_eq!(block.hash, expected.hash);
+        assert_eq!(
+            block.transactions,
+            BlockTransactions::Hashes(vec![expected.transactions[0].hash()])
+        );
+
+        let block = eth_api
+            .block_by_hash(expected.hash, true)
+            .await
+            .unwrap()
+            .unwrap();
+        match block.transactions {
+            BlockTransactions::Full(transactions) => {
+                assert_eq!(transactions[0].kind, TransactionKind::Deposit);
+                assert_eq!(
+                    transactions[0].to,
+                    Some(ethereum_types::H160::repeat_byte(1))
+                );
+                assert_eq!(transactions[0].nonce, None);
+            }
+            BlockTransactions::Hashes(_) => panic!("full transactions are requested"),
+        }
+
+        assert_eq!(
+            eth_api.block_by_hash(H256::zero(), false).await.unwrap(),
+            None
+        );
+        assert_eq!(
+            eth_api
+                .block_by_number(BlockNumber::Pending, false)
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn success_proof() {
+        let eth_api = eth_api();
+        let account = ethereum_types::H160::repeat_byte(1);
+        let token = ethereum_types::H160::zero();
+
+        let proof = eth_api.proof(account, token, None).await.unwrap();
+        assert_eq!(proof.address, account);
+        assert_eq!(proof.balance, ethereum_types::U256::from(10));
+        assert_eq!(proof.block_number, Some(U64::one()));
+        assert_eq!(proof.state_root, Some(H256::repeat_byte(2)));
+        assert_eq!(proof.key, state::balance_key(&account, &token));
+        assert_eq!(proof.scheme, "Verkle");
+        assert_eq!(proof.root, H256::repeat_byte(3));
+        assert_eq!(proof.slot, Bytes(Slot::Stem(vec![]).encode()));
+
+        let err = eth_api
+            .proof(account, token, Some(BlockNumber::Pending))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, jsonrpc_core::ErrorCode::InvalidParams);
+    }
+
+    #[tokio::test]
+    async fn success_non_membership_proof() {
+        let eth_api = eth_api();
+        let key = H256::repeat_byte(1);
+
+        let proof = eth_api.non_membership_proof(key, None).await.unwrap();
+        assert_eq!(proof.key, key);
+        assert_eq!(proof.block_number, Some(U64::one()));
+        assert_eq!(proof.state_root, None);
+        assert_eq!(proof.scheme, "SparseMerkle");
+        assert_eq!(proof.root, H256::repeat_byte(3));
+        assert_eq!(
+            proof.slot,
+            Bytes(
+                Slot::Other {
+                    stem: vec![2],
+                    values: [2; 32],
+                }
+                .encode()
+            )
+        );
+
+        let proof = eth_api
+            .non_membership_proof(key, Some(BlockNumber::Pending))
+            .await
+            .unwrap();
+        assert_eq!(proof.block_number, None);
+
+        let err = eth_api
+            .non_membership_proof(H256::zero(), None)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, jsonrpc_core::ErrorCode::InvalidParams);
+    }
+
+    #[tokio::test]
+    async fn success_logs() {
+        let eth_api = eth_api();
+
+        let logs = eth_api.logs(Filter::default()).await.unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].block_number, U64::one());
+        assert_eq!(logs[0].data, Bytes(vec![10]));
+        assert!(!logs[0].removed);
+
+        let filter = Filter {
+            topics: Some(vec![Some(ValueOrArray::Value(H256::zero()))]),
+            ..Filter::default()
+        };
+        assert!(eth_api.logs(filter).await.unwrap().is_empty());
+
+        let filter = Filter {
+            from_block: Some(BlockNumber::Num(2)),
+            ..Filter::default()
+        };
+        let err = eth_api.logs(filter).await.unwrap_err();
+        assert_eq!(err.code, jsonrpc_core::ErrorCode::InvalidParams);
+    }
+
+    #[tokio::test]
+    async fn success_filters() {
+        let eth_api = eth_api();
+
+        let id = eth_api.new_filter(Filter::default()).await.unwrap();
+        assert_eq!(id, ethereum_types::U256::one());
+        assert_eq!(eth_api.filter_changes(id).await.unwrap().len(), 1);
+        assert!(eth_api.uninstall_filter(id).await.unwrap());
+
+        let unknown = ethereum_types::U256::from(2);
+        let err = eth_api.filter_changes(unknown).await.unwrap_err();
+        assert_eq!(err.code, jsonrpc_core::ErrorCode::InvalidParams);
+        assert!(!eth_api.uninstall_filter(unknown).await.unwrap());
+        let err = eth_api
+            .filter_changes(ethereum_types::U256::MAX)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, jsonrpc_core::ErrorCode::InvalidParams);
+    }
+
+    #[tokio::test]
+    async fn success_account_transactions() {
+        let eth_api = eth_api();
+
+        let page = eth_api
+            .account_transactions(ethereum_types::H160::repeat_byte(1), None, None)
+            .await
+            .unwrap();
+        assert_eq!(page.transactions.len(), 1);
+        assert_eq!(page.transactions[0].block_number, U64::one());
+        assert_eq!(page.transactions[0].kind, TransactionKind::Deposit);
+        assert_eq!(page.next_cursor, None);
+
+        let page = eth_api
+            .account_transactions(ethereum_types::H160::repeat_byte(1), Some(U64::one()), None)
+            .await
+            .unwrap();
+        assert!(page.transactions.is_empty());
+    }
 }
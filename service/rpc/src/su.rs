@@ -0,0 +1,95 @@
+use std::sync::Arc;
+
+use ethereum_types::H256;
+use jsonrpc_core::{BoxFuture, Result};
+use su_receiver::{StateDiff, SuReceiverTrait, UserStateProof as Proof};
+use tracing::info_span;
+
+use intmax_json_rpc_api::types::UserStateProof;
+use intmax_json_rpc_api::SuApi as SuApiT;
+
+pub struct SuApi {
+    su_receiver: Arc<dyn SuReceiverTrait + Send + Sync>,
+}
+
+impl SuApi {
+    pub fn new(su_receiver: Arc<dyn SuReceiverTrait + Send + Sync>) -> SuApi {
+        SuApi { su_receiver }
+    }
+}
+
+impl SuApiT for SuApi {
+    fn submit_state_update(&self, req: UserStateProof) -> BoxFuture<Result<H256>> {
+        let _guard = info_span!("submit_state_update").entered();
+
+        let res = self.su_receiver.submit_state_update(Proof {
+            diff: StateDiff {
+                user: req.user,
+                old_root: req.old_root,
+                new_root: req.new_root,
+                nonce: req.nonce.as_u64(),
+            },
+            proof: req.proof.0,
+        });
+
+        Box::pin(async move { res.await.map_err(Into::into) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethereum_types::{H160, U64};
+    use fc_rpc_core::types::Bytes;
+    use jsonrpc_core::ErrorCode;
+    use su_receiver::Error as SuError;
+
+    use super::*;
+
+    /// Accepts the proofs `[1]`.
+    struct MockSuReceiver;
+
+    impl SuReceiverTrait for MockSuReceiver {
+        fn submit_state_update(&self, proof: Proof) -> BoxFuture<su_receiver::Result<H256>> {
+            let res = if proof.proof == vec![1] {
+                Ok(proof.diff.hash())
+            } else {
+                Err(SuError::InvalidUserStateZKP {
+                    user_state_proof: "invalid".into(),
+                })
+            };
+
+            Box::pin(async move { res })
+        }
+    }
+
+    #[tokio::test]
+    async fn success_submit_state_update() {
+        let su_api = SuApi::new(Arc::new(MockSuReceiver));
+        let req = UserStateProof {
+            user: H160::repeat_byte(1),
+            old_root: H256::zero(),
+            new_root: H256::repeat_byte(2),
+            nonce: U64::zero(),
+            proof: Bytes(vec![1]),
+        };
+        let expected = StateDiff {
+            user: req.user,
+            old_root: req.old_root,
+            new_root: req.new_root,
+            nonce: 0,
+        }
+        .hash();
+
+        let hash = su_api.submit_state_update(req.clone()).await.unwrap();
+        assert_eq!(hash, expected);
+
+        let err = su_api
+            .submit_state_update(UserStateProof {
+                proof: Bytes(vec![2]),
+                ..req
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidParams);
+    }
+}
@@ -0,0 +1,354 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use ethereum_types::{H160, H256, U64};
+use fc_rpc_core::types::Bytes;
+use jsonrpc_core::{BoxFuture, Result};
+use jsonrpc_pubsub::typed::{Sink, Subscriber};
+use jsonrpc_pubsub::{PubSubMetadata, SubscriptionId};
+use su_receiver::{StateSyncTrait, SyncBlock, MAX_SYNC_BLOCKS};
+use tokio::runtime::Handle;
+use tokio::sync::oneshot;
+use tracing::{error, info_span};
+
+use intmax_json_rpc_api::types::{StateSync, StateSyncBlock, StateSyncUpdate, UserStateProof};
+use intmax_json_rpc_api::StateSyncApi as StateSyncApiT;
+
+type Subscriptions = Arc<Mutex<HashMap<u64, oneshot::Sender<()>>>>;
+
+/// Serves the state sync over `M`, the metadata of the server holding the pubsub sessions.
+pub struct StateSyncApi<M> {
+    syncer: Arc<dyn StateSyncTrait + Send + Sync>,
+    /// Runs the subscriptions. Subscribe calls are made off the runtime by the ws server.
+    runtime: Handle,
+    poll_interval: Duration,
+    next_id: AtomicU64,
+    subscriptions: Subscriptions,
+    _meta: PhantomData<fn() -> M>,
+}
+
+impl<M> StateSyncApi<M> {
+    /// Subscriptions check for new blocks every `poll_interval`.
+    pub fn new(
+        syncer: Arc<dyn StateSyncTrait + Send + Sync>,
+        runtime: Handle,
+        poll_interval: Duration,
+    ) -> StateSyncApi<M> {
+        StateSyncApi {
+            syncer,
+            runtime,
+            poll_interval,
+            next_id: AtomicU64::new(0),
+            subscriptions: Arc::default(),
+            _meta: PhantomData,
+        }
+    }
+}
+
+impl<M: PubSubMetadata> StateSyncApiT for StateSyncApi<M> {
+    type Metadata = M;
+
+    fn state_sync(
+        &self,
+        user: H160,
+        from: U64,
+        limit: Option<U64>,
+    ) -> BoxFuture<Result<StateSync>> {
+        let _guard = info_span!("state_sync").entered();
+
+        let res = self
+            .syncer
+            .state_sync(
+                &user,
+                from.as_u64(),
+                limit.map_or(MAX_SYNC_BLOCKS, |limit| limit.as_u64()),
+            )
+            .map(|page| StateSync {
+                blocks: page.blocks.into_iter().map(into_state_sync_block).collect(),
+                next_block: page.next_block.into(),
+            })
+            .map_err(Into::into);
+
+        Box::pin(async move { res })
+    }
+
+    fn state_diff(&self, hash: H256) -> BoxFuture<Result<Option<UserStateProof>>> {
+        let _guard = info_span!("state_diff").entered();
+
+        let res = self.syncer.state_diff(&hash).map(|proof| UserStateProof {
+            user: proof.diff.user,
+            old_root: proof.diff.old_root,
+            new_root: proof.diff.new_root,
+            nonce: proof.diff.nonce.into(),
+            proof: Bytes(proof.proof),
+        });
+
+        Box::pin(async move { Ok(res) })
+    }
+
+    fn subscribe_state_sync(
+        &self,
+        _: Self::Metadata,
+        subscriber: Subscriber<StateSyncBlock>,
+        user: H160,
+        from: U64,
+    ) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let sink = match subscriber.assign_id(SubscriptionId::Number(id)) {
+            Ok(sink) => sink,
+            // the request is gone.
+            Err(()) => return,
+        };
+
+        let (stop, stopped) = oneshot::channel();
+        self.subscriptions
+            .lock()
+            .expect("subscriptions lock poisoned")
+            .insert(id, stop);
+        let syncer = self.syncer.clone();
+        let subscriptions = self.subscriptions.clone();
+        let poll_interval = self.poll_interval;
+        self.runtime.spawn(async move {
+            tokio::select! {
+                _ = stopped => (),
+                _ = sync_until_closed(syncer, sink, user, from.as_u64(), poll_interval) => (),
+            }
+            subscriptions
+                .lock()
+                .expect("subscriptions lock poisoned")
+                .remove(&id);
+        });
+    }
+
+    fn unsubscribe_state_sync(
+        &self,
+        _: Option<Self::Metadata>,
+        id: SubscriptionId,
+    ) -> Result<bool> {
+        let stop = match id {
+            SubscriptionId::Number(id) => self
+                .subscriptions
+                .lock()
+                .expect("subscriptions lock poisoned")
+                .remove(&id),
+            SubscriptionId::String(_) => None,
+        };
+
+        Ok(match stop {
+            Some(stop) => {
+                let _ = stop.send(());
+                true
+            }
+            None => false,
+        })
+    }
+}
+
+/// Sends the blocks of `user` from `from` on to `sink` until the client goes away.
+async fn sync_until_closed(
+    syncer: Arc<dyn StateSyncTrait + Send + Sync>,
+    sink: Sink<StateSyncBlock>,
+    user: H160,
+    from: u64,
+    poll_interval: Duration,
+) {
+    let mut from = from;
+    loop {
+        match syncer.state_sync(&user, from, MAX_SYNC_BLOCKS) {
+            Ok(page) => {
+                for block in page.blocks {
+                    if sink.notify(Ok(into_state_sync_block(block))).is_err() {
+                        return;
+                    }
+                }
+                // a full page may be followed by more blocks.
+                let caught_up = page.next_block - from < MAX_SYNC_BLOCKS;
+                from = page.next_block;
+                if !caught_up {
+                    continue;
+                }
+            }
+            Err(e) => error!("failed to sync the state of {:?}: {}", user, e),
+        }
+
+        tokio::time::sleep(poll_interval).await;
+    }
+}
+
+fn into_state_sync_block(block: SyncBlock) -> StateSyncBlock {
+    StateSyncBlock {
+        number: block.number.into(),
+        hash: block.hash,
+        parent_hash: block.parent_hash,
+        timestamp: block.timestamp.into(),
+        diff_root: block.aggregated.diff_root,
+        aggregated_proof: Bytes(block.aggregated.proof),
+        updates: block
+            .updates
+            .into_iter()
+            .map(|update| StateSyncUpdate {
+                index: update.index.into(),
+                old_root: update.proof.diff.old_root,
+                new_root: update.proof.diff.new_root,
+                nonce: update.proof.diff.nonce.into(),
+                proof: Bytes(update.proof.proof),
+                diff_proof: update.diff_proof,
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonrpc_core::futures::StreamExt;
+    use jsonrpc_pubsub::Session;
+    use su_receiver::{AggregatedProof, StateDiff, SyncUpdate, UserStateProof as Proof};
+
+    use super::*;
+
+    /// Has an update of the user in every block up to 3.
+    struct MockSyncer;
+
+    impl StateSyncTrait for MockSyncer {
+        fn state_sync(
+            &self,
+            user: &H160,
+            from: u64,
+            limit: u64,
+        ) -> su_receiver::Result<su_receiver::StateSync> {
+            let end = 3.min(from + limit - 1);
+            let blocks = (from..=end)
+                .map(|number| SyncBlock {
+                    number,
+                    hash: H256::from_low_u64_be(number),
+                    parent_hash: H256::from_low_u64_be(number - 1),
+                    timestamp: number,
+                    aggregated: AggregatedProof {
+                        diff_root: H256::repeat_byte(1),
+                        proof: vec![1],
+                    },
+                    updates: vec![SyncUpdate {
+                        index: 0,
+                        proof: Proof {
+                            diff: StateDiff {
+                                user: *user,
+                                old_root: H256::from_low_u64_be(number - 1),
+                                new_root: H256::from_low_u64_be(number),
+                                nonce: number - 1,
+                            },
+                            proof: vec![2],
+                        },
+                        diff_proof: vec![None],
+                    }],
+                })
+                .collect();
+
+            Ok(su_receiver::StateSync {
+                blocks,
+                next_block: end.max(from - 1) + 1,
+            })
+        }
+
+        fn state_diff(&self, hash: &H256) -> Option<Proof> {
+            self.state_sync(&H160::repeat_byte(1), 1, 1).unwrap().blocks[0]
+                .updates
+                .iter()
+                .map(|update| update.proof.clone())
+                .find(|proof| proof.diff.hash() == *hash)
+        }
+    }
+
+    fn state_sync_api() -> StateSyncApi<Arc<Session>> {
+        StateSyncApi::new(
+            Arc::new(MockSyncer),
+            Handle::current(),
+            Duration::from_millis(10),
+        )
+    }
+
+    #[tokio::test]
+    async fn success_state_sync() {
+        let api = state_sync_api();
+        let user = H160::repeat_byte(1);
+
+        let page = api.state_sync(user, 2.into(), None).await.unwrap();
+        assert_eq!(page.next_block, 4.into());
+        assert_eq!(page.blocks.len(), 2);
+        let block = &page.blocks[0];
+        assert_eq!(block.number, 2.into());
+        assert_eq!(block.diff_root, H256::repeat_byte(1));
+        assert_eq!(block.aggregated_proof, Bytes(vec![1]));
+        assert_eq!(
+            block.updates[0],
+            StateSyncUpdate {
+                index: 0.into(),
+                old_root: H256::from_low_u64_be(1),
+                new_root: H256::from_low_u64_be(2),
+                nonce: 1.into(),
+                proof: Bytes(vec![2]),
+                diff_proof: vec![None],
+            }
+        );
+
+        let page = api
+            .state_sync(user, 1.into(), Some(1.into()))
+            .await
+            .unwrap();
+        assert_eq!(page.next_block, 2.into());
+    }
+
+    #[tokio::test]
+    async fn success_state_diff() {
+        let api = state_sync_api();
+        let diff = StateDiff {
+            user: H160::repeat_byte(1),
+            old_root: H256::zero(),
+            new_root: H256::from_low_u64_be(1),
+            nonce: 0,
+        };
+
+        let proof = api.state_diff(diff.hash()).await.unwrap();
+        assert_eq!(
+            proof,
+            Some(UserStateProof {
+                user: diff.user,
+                old_root: diff.old_root,
+                new_root: diff.new_root,
+                nonce: 0.into(),
+                proof: Bytes(vec![2]),
+            })
+        );
+        assert_eq!(api.state_diff(H256::zero()).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn success_subscribe_state_sync() {
+        let api = state_sync_api();
+        let (subscriber, id, mut notifications) = Subscriber::new_test("intmax_stateSync");
+
+        let meta = Arc::new(Session::new(
+            jsonrpc_core::futures::channel::mpsc::unbounded().0,
+        ));
+        api.subscribe_state_sync(meta, subscriber, H160::repeat_byte(1), 2.into());
+        let id = id.await.unwrap().unwrap();
+        assert_eq!(id, SubscriptionId::Number(0));
+
+        for number in 2..=3 {
+            let notification = notifications.next().await.unwrap();
+            let notification: serde_json::Value = serde_json::from_str(&notification).unwrap();
+            assert_eq!(notification["method"], "intmax_stateSync");
+            assert_eq!(
+                notification["params"]["result"]["number"],
+                format!("0x{:x}", number)
+            );
+        }
+
+        assert!(api.unsubscribe_state_sync(None, id.clone()).unwrap());
+        assert!(!api.unsubscribe_state_sync(None, id).unwrap());
+        // the subscription stops and drops its sink.
+        assert!(notifications.next().await.is_none());
+    }
+}
@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use commiter::Commiter;
+use db::Database;
+use eth_provider::EthProvider;
+use intmax_config::EthConfig;
+use intmax_verkle::VerkleStorage;
+use intmax_zk::ProofSystem;
+use tracing::{error, info, warn};
+
+/// Every `interval`, prepares the commitments of the newly sealed blocks and commits the ones
+/// after the latest batch committed on L1 with `Rollup.commitBatch`, in order.
+pub(crate) async fn commit_batches<S: VerkleStorage, D: Database, P: ProofSystem>(
+    commiter: Commiter<S, D, P>,
+    eth_config: EthConfig,
+    interval: Duration,
+) {
+    let provider = match EthProvider::connect(&eth_config).await {
+        Ok(provider) => provider,
+        Err(e) => {
+            error!("batch commiter stopped: {}", e);
+            return;
+        }
+    };
+
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let latest = match commiter.prepare_sealed_blocks() {
+            Ok(latest) => latest,
+            Err(e) => {
+                error!("failed to prepare the sealed blocks: {}", e);
+                continue;
+            }
+        };
+        let committed = match provider.latest_batch_number().await {
+            Ok(committed) => committed,
+            Err(e) => {
+                warn!("failed to fetch the latest committed batch: {}", e);
+                continue;
+            }
+        };
+
+        for number in committed + 1..=latest {
+            let commitment = match commiter.commitment(number) {
+                Ok(Some(commitment)) => commitment,
+                Ok(None) => break,
+                Err(e) => {
+                    error!("failed to prepare batch {}: {}", number, e);
+                    break;
+                }
+            };
+            match provider
+                .commit_batch(
+                    commitment.number,
+                    commitment.state_root,
+                    commitment.diff_root,
+                    commitment.proof,
+                )
+                .await
+            {
+                Ok(l1_tx) => info!("committed batch {} in {:?}", number, l1_tx),
+                Err(e) => {
+                    warn!("failed to commit batch {}: {}", number, e);
+                    break;
+                }
+            }
+        }
+    }
+}
@@ -1,7 +1,16 @@
+use std::future::Future;
+use std::pin::Pin;
+
+mod batches;
+mod withdrawals;
+
+type Task = Pin<Box<dyn Future<Output = ()> + Send>>;
+
 #[derive(Default)]
 pub struct Runner {
     http_server: Option<http::Server>,
     ws_server: Option<ws::Server>,
+    tasks: Vec<Task>,
 }
 
 impl Runner {
@@ -19,6 +28,12 @@ impl Runner {
         self
     }
 
+    /// Registers a background task, e.g. the L1 deposit watcher, run alongside the servers.
+    pub fn regist_task(mut self, task: impl Future<Output = ()> + Send + 'static) -> Runner {
+        self.tasks.push(Box::pin(task));
+        self
+    }
+
     pub async fn run(self) {
         let mut tasks = Vec::new();
 
@@ -34,24 +49,210 @@ impl Runner {
             }));
         };
 
+        // regist tasks: background tasks
+        for task in self.tasks {
+            tasks.push(tokio::spawn(task));
+        }
+
         futures::future::join_all(tasks.into_iter()).await;
     }
 }
 
-use intmax_config::Config;
-use intmax_json_rpc_api::EthApi as EthApiT;
-use intmax_rpc::EthApi;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use codec::Encode;
+use commiter::Commiter;
+use db::columns;
+use db::{Database, Db};
+use ethereum_types::{H160, H256};
+use executor::{Block, Executor};
+use exitor::{signature, CommittedBatch, Exitor, ExitorTrait};
+use intmax_config::{Config, StateTree as StateTreeKind};
+use intmax_json_rpc_api::{
+    EthApi as EthApiT, ExitApi as ExitApiT, StateSyncApi as StateSyncApiT, SuApi as SuApiT,
+};
+use intmax_rpc::{EthApi, ExitApi, StateSyncApi, SuApi};
+use intmax_verkle::{SparseMerkleTree, StateTree, VerkleStorage, VerkleTree};
+use intmax_zk::DevProofSystem;
+use kvdb_rocksdb::{Database as RocksDB, DatabaseConfig};
+use query_receiver::QueryReceiver;
+use su_receiver::{Aggregator, DaStore, StateSyncer, SuReceiver};
+use tracing::{error, info, warn};
 use tx_receiver::TxReceiver;
 
-pub fn gen_runner(config: &Config) -> Runner {
-    let tx_receiver = TxReceiver::new();
-    let eth_api = EthApi::new(tx_receiver);
+/// Number of submitted state updates waiting for verification before submitters wait.
+const STATE_UPDATE_BUFFER: usize = 1024;
+
+fn open_db(config: &Config) -> Db<RocksDB> {
+    let rocks_db = RocksDB::open(
+        &DatabaseConfig::with_columns(columns::NUM_COLUMNS),
+        &config.db.path,
+    )
+    .expect("db setup error.");
+
+    Db::new(rocks_db, columns::STATE)
+}
+
+type NodeExecutor = Executor<StateTree<Db<RocksDB>>, Db<RocksDB>>;
+
+fn gen_state_tree(config: &Config, db: &Db<RocksDB>) -> StateTree<Db<RocksDB>> {
+    let db = db.with_column(columns::STATE);
+    let cache_nodes = config.db.state_cache_nodes;
+    let tree = match config.db.state_tree {
+        StateTreeKind::Verkle => {
+            StateTree::Verkle(VerkleTree::new(db).with_cache_size(cache_nodes))
+        }
+        StateTreeKind::SparseMerkle => {
+            StateTree::SparseMerkle(SparseMerkleTree::new(db).with_cache_size(cache_nodes))
+        }
+    };
+    tree.check_scheme().expect("state tree scheme error.");
+    tree
+}
+
+fn gen_executor(config: &Config, db: &Db<RocksDB>) -> Arc<NodeExecutor> {
+    let executor = Executor::new(
+        gen_state_tree(config, db),
+        db.with_column(columns::EXECUTOR),
+    )
+    .with_version_window(config.block.state_version_blocks)
+    .with_log_index(db.with_column(columns::LOGS))
+    .with_account_index(db.with_column(columns::ACCOUNT_TXS));
+    executor
+        .restore_pending_writes()
+        .expect("state restore error.");
+
+    Arc::new(executor)
+}
+
+// TODO: verify the user state proofs with the production proof system.
+fn gen_proof_system(config: &Config) -> DevProofSystem {
+    if !config.proof.dev_proof_system {
+        panic!("no production proof system, set `dev_proof_system` under `[proof]` to run with the development one");
+    }
+
+    DevProofSystem
+}
+
+fn gen_aggregator(config: &Config, db: &Db<RocksDB>) -> Aggregator<Db<RocksDB>, DevProofSystem> {
+    Aggregator::new(
+        db.with_column(columns::SU_RECEIVER),
+        DaStore::new(db.with_column(columns::DA)),
+        gen_proof_system(config),
+    )
+}
+
+/// Seals the pending transactions into a block every `interval`.
+async fn produce_blocks<S: VerkleStorage, D: Database>(
+    executor: Arc<Executor<S, D>>,
+    interval: Duration,
+) {
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time is after the unix epoch")
+            .as_secs();
+        if let Err(e) = executor.seal_block(timestamp) {
+            error!("failed to seal a block: {}", e);
+        }
+    }
+}
+
+/// Prunes the state tree of the blocks out of the version window every `interval`, but keeps
+/// the latest batch committed on L1, which forced exits are proven against, and every batch
+/// after the oldest one with an unclaimed exit.
+async fn prune_state<S: VerkleStorage, D: Database>(
+    executor: Arc<Executor<S, D>>,
+    exitor: Arc<Exitor<S, D>>,
+    interval: Duration,
+) {
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let keep_from = [
+            exitor.latest_committed_batch(),
+            exitor.oldest_unclaimed_batch(),
+        ]
+        .into_iter()
+        .flatten()
+        .min();
+        match executor.prune_state(keep_from) {
+            Ok(0) => (),
+            Ok(dropped) => info!("pruned {} state tree nodes", dropped),
+            Err(e) => error!("failed to prune the state tree: {}", e),
+        }
+    }
+}
+
+/// Domain of the exit requests: the chain id of the rollup and the `Rollup` contract of the
+/// configured network.
+fn signing_domain(config: &Config) -> signature::Domain {
+    let rollup = eth_provider::contracts::Network::find(&config.eth_server.network)
+        .and_then(|network| network.address("Rollup"))
+        .expect("rollup address of the network.");
 
-    let gen_handler = |apis| intmax_json_rpc_servers::rpc_handler(EthApiT::to_delegate(apis));
+    signature::Domain {
+        chain_id: config.chain.chain_id,
+        verifying_contract: rollup,
+    }
+}
+
+pub fn gen_runner(config: &Config) -> Runner {
     // install global collector configured based on RUST_LOG env var.
     tracing_subscriber::fmt().init();
 
-    let rpc_handler = intmax_json_rpc_servers::rpc_handler(EthApiT::to_delegate(eth_api));
+    let db = open_db(config);
+    let executor = gen_executor(config, &db);
+    let exitor = Arc::new(
+        Exitor::new(executor.clone(), db.with_column(columns::EXITOR))
+            .with_domain(signing_domain(config)),
+    );
+
+    let query_receiver = Arc::new(
+        QueryReceiver::new(executor.clone(), exitor.clone(), config.chain.chain_id)
+            .with_max_log_blocks(config.block.max_log_blocks),
+    );
+
+    let proof_system = gen_proof_system(config);
+    warn!("user state proofs are verified with the development proof system");
+    let su_receiver = SuReceiver::new(
+        executor.clone(),
+        db.with_column(columns::SU_RECEIVER),
+        proof_system,
+    );
+    let (su_client, submissions) = su_receiver::channel(STATE_UPDATE_BUFFER);
+    let su_receiver_task = su_receiver.run(submissions);
+
+    let commiter = Commiter::new(executor.clone(), gen_aggregator(config, &db));
+    let commiter_task = batches::commit_batches(
+        commiter,
+        config.eth_server.clone(),
+        Duration::from_secs(config.block.interval_secs),
+    );
+
+    let state_syncer = Arc::new(StateSyncer::new(
+        executor.clone(),
+        gen_aggregator(config, &db),
+    ));
+    let runtime = tokio::runtime::Handle::current();
+
+    let gen_handler = || {
+        intmax_json_rpc_servers::rpc_handler((
+            EthApiT::to_delegate(EthApi::new(TxReceiver::new(), query_receiver.clone())),
+            ExitApiT::to_delegate(ExitApi::new(exitor.clone())),
+            SuApiT::to_delegate(SuApi::new(Arc::new(su_client.clone()))),
+            StateSyncApiT::to_delegate(StateSyncApi::new(
+                state_syncer.clone(),
+                runtime.clone(),
+                Duration::from_secs(config.block.interval_secs),
+            )),
+        ))
+    };
     let http_server = intmax_json_rpc_servers::start_http_server(
         &std::net::SocketAddr::new(
             config
@@ -61,7 +262,7 @@ pub fn gen_runner(config: &Config) -> Runner {
                 .expect("set valid ip address."),
             config.http_server.port,
         ),
-        gen_handler(EthApi::new(TxReceiver::new())),
+        gen_handler(),
     )
     .expect("http server setup error.");
     let ws_server = intmax_json_rpc_servers::start_ws_server(
@@ -69,10 +270,171 @@ pub fn gen_runner(config: &Config) -> Runner {
             config.ws_server.ip.parse().expect("set valid ip address."),
             config.ws_server.port,
         ),
-        rpc_handler,
+        gen_handler(),
     )
     .expect("http server setup error.");
+
+    let eth_config = config.eth_server.clone();
+    let deposit_config = config.deposit.clone();
+    let deposit_db = db.with_column(columns::DEPOSIT_WATCHER);
+    let deposit_executor = executor.clone();
+    let deposit_watcher = async move {
+        if let Err(e) =
+            eth_provider::watch_deposits(&eth_config, &deposit_config, deposit_db, deposit_executor)
+                .await
+        {
+            error!("deposit watcher stopped: {}", e);
+        }
+    };
+
+    let withdrawal_finalizer = withdrawals::finalize_withdrawals(
+        exitor.clone(),
+        config.eth_server.clone(),
+        Duration::from_secs(config.exit.withdrawal_batch_interval_secs),
+    );
+
+    let eth_config = config.eth_server.clone();
+    let commit_interval = Duration::from_secs(config.exit.finality_poll_interval_secs);
+    let commit_exitor = exitor.clone();
+    let commit_watcher = async move {
+        let on_committed = |number, state_root| {
+            let batch = CommittedBatch { number, state_root };
+            if let Err(e) = commit_exitor.record_committed_batch(&batch) {
+                error!("failed to record committed batch {}: {}", number, e);
+            }
+        };
+        let from = commit_exitor.latest_committed_batch().unwrap_or_default();
+        if let Err(e) =
+            eth_provider::watch_committed_batches(&eth_config, commit_interval, from, on_committed)
+                .await
+        {
+            error!("commit watcher stopped: {}", e);
+        }
+    };
+
+    let state_pruner = prune_state(
+        executor.clone(),
+        exitor.clone(),
+        Duration::from_secs(config.block.prune_interval_secs),
+    );
+
+    let eth_config = config.eth_server.clone();
+    let finality_interval = Duration::from_secs(config.exit.finality_poll_interval_secs);
+    let finality_watcher = async move {
+        let challenge_period = async {
+            eth_provider::EthProvider::connect(&eth_config)
+                .await?
+                .challenge_period()
+                .await
+        };
+        match challenge_period.await {
+            Ok(secs) => {
+                if let Err(e) = exitor.set_challenge_period(secs) {
+                    error!("failed to record the challenge period: {}", e);
+                }
+            }
+            Err(e) => warn!("failed to fetch the challenge period: {}", e),
+        }
+
+        let on_finalized = |batch| {
+            if let Err(e) = exitor.finalize_batch(batch) {
+                error!("failed to finalize batch {}: {}", batch, e);
+            }
+        };
+        if let Err(e) =
+            eth_provider::watch_finalized_batches(&eth_config, finality_interval, on_finalized)
+                .await
+        {
+            error!("finality watcher stopped: {}", e);
+        }
+    };
+
+    info!("sealing blocks every {}s", config.block.interval_secs);
+    let block_producer = produce_blocks(executor, Duration::from_secs(config.block.interval_secs));
+
     Runner::new()
         .regist_http_server(http_server)
         .regist_ws_server(ws_server)
+        .regist_task(deposit_watcher)
+        .regist_task(commit_watcher)
+        .regist_task(finality_watcher)
+        .regist_task(withdrawal_finalizer)
+        .regist_task(su_receiver_task)
+        .regist_task(commiter_task)
+        .regist_task(block_producer)
+        .regist_task(state_pruner)
+}
+
+/// ABI encoded `Rollup.forceExit` call exiting the whole balance of `token` held by `account`.
+/// The balance is proven from the local database against the latest batch committed on L1, so
+/// the node must be stopped while this runs.
+pub async fn force_exit_calldata(
+    config: &Config,
+    account: H160,
+    token: H160,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let provider = eth_provider::EthProvider::connect(&config.eth_server).await?;
+    provider.validate_chain_id().await?;
+    let (number, state_root) = provider.latest_committed_batch().await?;
+
+    let db = open_db(config);
+    let exitor = Exitor::new(gen_executor(config, &db), db.with_column(columns::EXITOR));
+    let proof =
+        exitor.forced_exit_proof(&account, &token, &CommittedBatch { number, state_root })?;
+
+    Ok(provider.force_exit_calldata(
+        proof.batch.number,
+        proof.account,
+        proof.token,
+        proof.amount,
+        eth_provider::encode_proof(&proof.proof.openings, &proof.proof.slot.encode()),
+    )?)
+}
+
+/// The state diffs of the blocks from `from` to `to`, the latest block if `None`, in the
+/// `su_receiver::DaStore` export format. The node must be stopped while this runs.
+pub fn export_state_diffs(config: &Config, from: u64, to: Option<u64>) -> Vec<u8> {
+    let db = open_db(config);
+    let to = to.unwrap_or_else(|| gen_executor(config, &db).latest_block_number());
+
+    DaStore::new(db.with_column(columns::DA)).export(from, to)
+}
+
+/// Writes a snapshot of the state at the latest block to `out`, see
+/// `executor::Executor::export_snapshot`. Returns the block. The node must be stopped while this
+/// runs.
+pub fn export_snapshot<W: Write>(config: &Config, out: W) -> executor::Result<Block> {
+    let db = open_db(config);
+
+    gen_executor(config, &db).export_snapshot(out)
+}
+
+/// Bootstraps a new database from a snapshot of `export_snapshot`, checking the rebuilt state
+/// root against the snapshot. Returns the block of the snapshot. The node must be stopped while
+/// this runs.
+pub fn import_snapshot<R: Read>(config: &Config, input: R) -> executor::Result<Block> {
+    let db = open_db(config);
+
+    gen_executor(config, &db).import_snapshot(input)
+}
+
+/// Imports an export of `export_state_diffs`, checking every block against the diff root
+/// committed on L1 since `deposit.from_block`. Returns the number of imported blocks. The node
+/// must be stopped while this runs.
+pub async fn import_state_diffs(
+    config: &Config,
+    data: &[u8],
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let provider = eth_provider::EthProvider::connect(&config.eth_server).await?;
+    provider.validate_chain_id().await?;
+    let head = provider.block_number().await?;
+    let diff_roots: HashMap<u64, H256> = provider
+        .committed_diff_roots(config.deposit.from_block, head)
+        .await?
+        .into_iter()
+        .collect();
+
+    let db = open_db(config);
+    Ok(DaStore::new(db.with_column(columns::DA))
+        .import(data, |number| diff_roots.get(&number).copied())?)
 }
@@ -0,0 +1,71 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use db::Database;
+use eth_provider::EthProvider;
+use ethcontract::transport::DynTransport;
+use exitor::Exitor;
+use intmax_config::EthConfig;
+use intmax_verkle::VerkleStorage;
+use tracing::{error, warn};
+
+/// Every `interval`, puts the finalized exits into a withdrawal batch, submits the roots of the
+/// unsubmitted batches to L1 and records the exits claimed on L1 since the last run.
+pub(crate) async fn finalize_withdrawals<S: VerkleStorage, D: Database>(
+    exitor: Arc<Exitor<S, D>>,
+    eth_config: EthConfig,
+    interval: Duration,
+) {
+    let provider = match EthProvider::connect(&eth_config).await {
+        Ok(provider) => provider,
+        Err(e) => {
+            error!("withdrawal finalizer stopped: {}", e);
+            return;
+        }
+    };
+
+    loop {
+        tokio::time::sleep(interval).await;
+
+        if let Err(e) = exitor.build_withdrawal_batch() {
+            error!("failed to build a withdrawal batch: {}", e);
+        }
+
+        for batch in exitor.unsubmitted_withdrawal_batches() {
+            match provider
+                .submit_withdrawal_root(batch.number, batch.root)
+                .await
+            {
+                Ok(l1_tx) => {
+                    if let Err(e) = exitor.mark_submitted(batch.number, l1_tx) {
+                        error!("failed to record withdrawal batch {}: {}", batch.number, e);
+                    }
+                }
+                Err(e) => {
+                    warn!("failed to submit withdrawal batch {}: {}", batch.number, e);
+                    break;
+                }
+            }
+        }
+
+        if let Err(e) = record_claims(&exitor, &provider).await {
+            warn!("failed to record claims: {}", e);
+        }
+    }
+}
+
+async fn record_claims<S: VerkleStorage, D: Database>(
+    exitor: &Exitor<S, D>,
+    provider: &EthProvider<DynTransport>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let head = provider.block_number().await?;
+    let from = exitor.claim_cursor().map_or(0, |cursor| cursor + 1);
+    if from > head {
+        return Ok(());
+    }
+
+    let claimed = provider.claimed_exits(from, head).await?;
+    exitor.record_claims(&claimed, head)?;
+
+    Ok(())
+}